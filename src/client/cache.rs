@@ -0,0 +1,45 @@
+use std::fs;
+use std::path::PathBuf;
+
+use lib::prelude::*;
+
+/// maps downloaded from servers, kept compressed and named after their hash
+pub struct MapCache {
+    dir: PathBuf,
+}
+
+impl MapCache {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// hashes come from the server, anything but plain hex could escape the cache directory
+    fn path(&self, info: &MapInfo) -> Option<PathBuf> {
+        let valid = info.hash.len() == 64 && info.hash.chars().all(|c| c.is_ascii_hexdigit());
+        valid.then(|| self.dir.join(format!("{}.bin", info.hash)))
+    }
+
+    pub fn load(&self, info: &MapInfo) -> Option<MapTiles> {
+        let path = self.path(info)?;
+        let data = fs::read(&path).ok()?;
+
+        match unpack_map(&info.hash, &data) {
+            Ok(tiles) => Some(tiles),
+            Err(err) => {
+                log::warn!("dropping cached map {:?}: {}", path, err);
+                let _ = fs::remove_file(&path);
+                None
+            }
+        }
+    }
+
+    pub fn store(&self, info: &MapInfo, data: &[u8]) {
+        let Some(path) = self.path(info) else {
+            return;
+        };
+
+        if let Err(err) = fs::create_dir_all(&self.dir).and_then(|_| fs::write(&path, data)) {
+            log::error!("failed to cache map {}: {}", info.name, err);
+        }
+    }
+}
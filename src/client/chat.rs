@@ -0,0 +1,174 @@
+use raylib::prelude::*;
+
+use lib::prelude::*;
+
+/// in-game text chat: input box and scrolling message log
+pub struct Chat {
+    /// channel being typed into, `None` while the input box is closed
+    pub open: Option<ChatChannel>,
+    input: String,
+    history: Vec<(ChatMessage, Instant)>,
+    scroll: usize,
+}
+
+impl Chat {
+    pub fn new() -> Self {
+        Self {
+            open: None,
+            input: String::new(),
+            history: Vec::new(),
+            scroll: 0,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open.is_some()
+    }
+
+    pub fn push(&mut self, message: ChatMessage) {
+        self.history.push((message, Instant::now()));
+        if self.history.len() > CHAT_HISTORY_SIZE {
+            self.history.remove(0);
+        }
+    }
+
+    fn open(&mut self, channel: ChatChannel) {
+        // drop the character of the key that opened the chat
+        while unsafe { raylib::ffi::GetCharPressed() } > 0 {}
+
+        self.open = Some(channel);
+        self.input.clear();
+        self.scroll = 0;
+    }
+
+    fn close(&mut self) {
+        self.open = None;
+        self.input.clear();
+        self.scroll = 0;
+    }
+}
+
+impl NetUpdateHandle for Chat {
+    type Network = GameNetwork;
+
+    fn net_update(&mut self, handle: &RaylibHandle, network: &mut Self::Network) {
+        let Some(channel) = self.open else {
+            if handle.is_key_pressed(KeyboardKey::KEY_T) {
+                self.open(ChatChannel::ALL);
+            } else if handle.is_key_pressed(KeyboardKey::KEY_Y) {
+                self.open(ChatChannel::TEAM);
+            }
+
+            return;
+        };
+
+        loop {
+            let code = unsafe { raylib::ffi::GetCharPressed() };
+            if code <= 0 {
+                break;
+            }
+
+            if let Some(c) = char::from_u32(code as u32) {
+                if !c.is_control() && self.input.chars().count() < CHAT_MAX_LENGTH {
+                    self.input.push(c);
+                }
+            }
+        }
+
+        if handle.is_key_pressed(KeyboardKey::KEY_BACKSPACE) {
+            self.input.pop();
+        }
+
+        let wheel = handle.get_mouse_wheel_move();
+        if wheel > 0.0 {
+            self.scroll = (self.scroll + 1).min(self.history.len().saturating_sub(1));
+        } else if wheel < 0.0 {
+            self.scroll = self.scroll.saturating_sub(1);
+        }
+
+        if handle.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+            self.close();
+        } else if handle.is_key_pressed(KeyboardKey::KEY_ENTER) {
+            if !self.input.trim().is_empty() {
+                network.client.send_message(
+                    DefaultChannel::ReliableUnordered,
+                    GameNetworkPacket::NET_CHAT_SEND(channel, self.input.clone())
+                        .serialized()
+                        .unwrap(),
+                );
+            }
+
+            self.close();
+        }
+    }
+}
+
+impl UserInterfaceHandle for Chat {
+    fn display(&mut self, d: &mut RaylibDrawHandle) {
+        let (font_size, line_height, lines) = (18, 22, 6);
+        let bottom = WINDOW_BOTTOM_LEFT_Y - 140;
+
+        // while closed only recent messages are shown, fading out
+        let visible = self
+            .history
+            .iter()
+            .rev()
+            .skip(self.scroll)
+            .filter(|(_, posted)| self.is_open() || posted.elapsed().as_secs_f32() < CHAT_FADE_TIME)
+            .take(lines);
+
+        for (index, (message, posted)) in visible.enumerate() {
+            let alpha = if self.is_open() {
+                1.0
+            } else {
+                (CHAT_FADE_TIME - posted.elapsed().as_secs_f32()).clamp(0.0, 1.0)
+            };
+
+            let (text, color) = match (message.sender, message.channel) {
+                (None, _) => (format!("* {}", message.text), Color::GOLD),
+                (Some(_), ChatChannel::TEAM) => (
+                    format!("[TEAM] {}: {}", message.name, message.text),
+                    Color::SKYBLUE,
+                ),
+                (Some(_), ChatChannel::ALL) => {
+                    (format!("{}: {}", message.name, message.text), Color::WHITE)
+                }
+            };
+
+            let y = bottom - (index as i32 + 1) * line_height;
+            let width = text::measure_text(&text, font_size);
+
+            d.draw_rectangle(
+                WINDOW_BOTTOM_LEFT_X - 4,
+                y - 2,
+                width + 8,
+                line_height,
+                Color::new(0, 0, 0, 90).fade(alpha * 0.35),
+            );
+            d.draw_text(&text, WINDOW_BOTTOM_LEFT_X, y, font_size, color.fade(alpha));
+        }
+
+        if let Some(channel) = self.open {
+            let prompt = match channel {
+                ChatChannel::ALL => "all",
+                ChatChannel::TEAM => "team",
+            };
+            let text = format!("[{}] {}_", prompt, self.input);
+
+            d.draw_rectangle(
+                WINDOW_BOTTOM_LEFT_X - 4,
+                bottom + 4,
+                420,
+                line_height + 4,
+                Color::new(0, 0, 0, 160),
+            );
+            d.draw_text(
+                &text,
+                WINDOW_BOTTOM_LEFT_X,
+                bottom + 6,
+                font_size,
+                Color::WHITE,
+            );
+        }
+    }
+}
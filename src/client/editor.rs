@@ -0,0 +1,809 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use raylib::prelude::*;
+
+use lib::prelude::*;
+use lib::types::*;
+
+use crate::{render_layers_under, render_map_entity, render_tiles};
+
+static HELP: &str = "1 wall  2-0 -= tiles  L layer  F spawn  G flag  Z zone  O door  \
+                     T teleporter  P pad  TAB team  RMB erase  MMB/WASD pan  wheel zoom  \
+                     ctrl+z/y undo/redo  ctrl+s save  ctrl+o reload";
+static HISTORY_LIMIT: usize = 64;
+static ZOOM_LIMITS: (f32, f32) = (0.1, 2.0);
+static PAN_SPEED: f32 = 900.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Tool {
+    Tile(TileVariant),
+    Spawn,
+    Flag,
+    Zone,
+    Door,
+    Teleporter,
+    Pad,
+}
+
+impl Tool {
+    fn name(&self) -> String {
+        match self {
+            Tool::Tile(variant) => variant.to_string(),
+            Tool::Spawn => String::from("SPAWN"),
+            Tool::Flag => String::from("FLAG"),
+            Tool::Zone => String::from("ZONE"),
+            Tool::Door => String::from("DOOR"),
+            Tool::Teleporter => String::from("TELEPORTER"),
+            Tool::Pad => String::from("PAD"),
+        }
+    }
+}
+
+/// the editable part of a map, kept around for undo and redo
+#[derive(Debug, Clone, PartialEq)]
+struct Snapshot {
+    tiles: TileGrid,
+    layers: BTreeMap<MapLayer, TileGrid>,
+    spawns: Vec<SpawnPoint>,
+    entities: Vec<MapEntity>,
+}
+
+/// standalone map editor, started with `client --editor <file.map>`
+pub struct Editor {
+    path: PathBuf,
+    map: MapFile,
+    camera: Camera2D,
+    tool: Tool,
+    /// layer the tile tools paint on
+    layer: MapLayer,
+    team: Option<Team>,
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    /// state before the stroke being painted, committed to the history once the mouse is released
+    stroke: Option<Snapshot>,
+    /// first corner of the zone being dragged out, or the pad being aimed
+    zone_start: Option<(i32, i32)>,
+    last_mouse: RVector2,
+    modified: bool,
+    status: Option<(String, Instant)>,
+}
+
+impl Editor {
+    /// opens the map at `path`, a blank one of the given size is created when it does not exist
+    pub fn open(path: PathBuf, size: (i32, i32)) -> Result<Self, String> {
+        let map = if path.exists() {
+            load(&path)?
+        } else {
+            blank(&path, size)?
+        };
+
+        let mut editor = Self {
+            path,
+            map,
+            camera: Camera2D {
+                offset: RVector2::new(WINDOW_CENTER_X, WINDOW_CENTER_Y),
+                target: RVector2::zero(),
+                rotation: 0.0,
+                zoom: 0.5,
+            },
+            tool: Tool::Tile(TileVariant::WALL),
+            layer: MapLayer::COLLISION,
+            team: None,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            stroke: None,
+            zone_start: None,
+            last_mouse: RVector2::zero(),
+            modified: false,
+            status: None,
+        };
+        editor.center_camera();
+
+        Ok(editor)
+    }
+
+    fn center_camera(&mut self) {
+        let (width, height) = self.map.tiles.bounds();
+        self.camera.target = RVector2::new(width / 2.0, height / 2.0);
+    }
+
+    fn notify(&mut self, text: impl Into<String>) {
+        let text = text.into();
+        log::info!("{}", text);
+        self.status = Some((text, Instant::now()));
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            tiles: self.map.tiles.clone(),
+            layers: self.map.layers.clone(),
+            spawns: self.map.spawns.clone(),
+            entities: self.map.entities.clone(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.map.tiles = snapshot.tiles;
+        self.map.layers = snapshot.layers;
+        self.map.spawns = snapshot.spawns;
+        self.map.entities = snapshot.entities;
+        self.modified = true;
+    }
+
+    /// records the state before an edit, anything that was undone is lost
+    fn push_history(&mut self, snapshot: Snapshot) {
+        if self.undo.len() >= HISTORY_LIMIT {
+            self.undo.remove(0);
+        }
+
+        self.undo.push(snapshot);
+        self.redo.clear();
+        self.modified = true;
+    }
+
+    fn undo(&mut self) {
+        if let Some(snapshot) = self.undo.pop() {
+            self.redo.push(self.snapshot());
+            self.restore(snapshot);
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(snapshot) = self.redo.pop() {
+            self.undo.push(self.snapshot());
+            self.restore(snapshot);
+        }
+    }
+
+    fn save(&mut self) {
+        let source = self.map.to_source();
+        if let Err(err) = fs::write(&self.path, &source) {
+            self.notify(format!("failed to save {}: {}", self.path.display(), err));
+            return;
+        }
+
+        self.modified = false;
+        let name = self.path.file_name().unwrap_or_default().to_string_lossy();
+        let diagnostics = lint(&name, &source);
+        match diagnostics.first() {
+            Some(first) => self.notify(format!(
+                "saved {} with {} problems, {}",
+                self.path.display(),
+                diagnostics.len(),
+                first
+            )),
+            None => self.notify(format!("saved {}", self.path.display())),
+        }
+    }
+
+    fn reload(&mut self) {
+        if !self.path.exists() {
+            self.notify("nothing saved yet");
+            return;
+        }
+
+        match load(&self.path) {
+            Ok(map) => {
+                let before = self.snapshot();
+                self.map = map;
+                self.push_history(before);
+                self.modified = false;
+                self.notify(format!("reloaded {}", self.path.display()));
+            }
+            Err(err) => self.notify(err),
+        }
+    }
+
+    fn cursor_cell(&self, handle: &RaylibHandle) -> (i32, i32) {
+        let world = handle.get_screen_to_world2D(handle.get_mouse_position(), self.camera);
+        TileGrid::world_to_grid(world.x, world.y)
+    }
+
+    fn move_camera(&mut self, handle: &RaylibHandle) {
+        let mouse = handle.get_mouse_position();
+        if handle.is_mouse_button_down(MouseButton::MOUSE_MIDDLE_BUTTON) {
+            self.camera.target.x -= (mouse.x - self.last_mouse.x) / self.camera.zoom;
+            self.camera.target.y -= (mouse.y - self.last_mouse.y) / self.camera.zoom;
+        }
+        self.last_mouse = mouse;
+
+        if !handle.is_key_down(KeyboardKey::KEY_LEFT_CONTROL) {
+            let step = PAN_SPEED * handle.get_frame_time() / self.camera.zoom;
+            for (keys, (dx, dy)) in [
+                ([KeyboardKey::KEY_W, KeyboardKey::KEY_UP], (0.0, -1.0)),
+                ([KeyboardKey::KEY_S, KeyboardKey::KEY_DOWN], (0.0, 1.0)),
+                ([KeyboardKey::KEY_A, KeyboardKey::KEY_LEFT], (-1.0, 0.0)),
+                ([KeyboardKey::KEY_D, KeyboardKey::KEY_RIGHT], (1.0, 0.0)),
+            ] {
+                if keys.iter().any(|key| handle.is_key_down(*key)) {
+                    self.camera.target.x += dx * step;
+                    self.camera.target.y += dy * step;
+                }
+            }
+        }
+
+        // zoom towards the cursor
+        let wheel = handle.get_mouse_wheel_move();
+        if wheel != 0.0 {
+            let anchor = handle.get_screen_to_world2D(mouse, self.camera);
+            self.camera.zoom =
+                (self.camera.zoom * (1.0 + wheel * 0.1)).clamp(ZOOM_LIMITS.0, ZOOM_LIMITS.1);
+            self.camera.offset = mouse;
+            self.camera.target = anchor;
+        }
+    }
+
+    fn select_tool(&mut self, handle: &RaylibHandle) {
+        // ctrl+z is undo, not the zone tool
+        if handle.is_key_down(KeyboardKey::KEY_LEFT_CONTROL) {
+            return;
+        }
+
+        for (key, tool) in [
+            (KeyboardKey::KEY_ONE, Tool::Tile(TileVariant::WALL)),
+            (KeyboardKey::KEY_TWO, Tool::Tile(TileVariant::WALL_SIDE)),
+            (KeyboardKey::KEY_THREE, Tool::Tile(TileVariant::WALL_TOP)),
+            (KeyboardKey::KEY_FOUR, Tool::Tile(TileVariant::GROUND)),
+            (KeyboardKey::KEY_FIVE, Tool::Tile(TileVariant::COVER)),
+            (KeyboardKey::KEY_SIX, Tool::Tile(TileVariant::WATER)),
+            (KeyboardKey::KEY_SEVEN, Tool::Tile(TileVariant::HAZARD)),
+            (KeyboardKey::KEY_EIGHT, Tool::Tile(TileVariant::CRATE)),
+            (KeyboardKey::KEY_NINE, Tool::Tile(TileVariant::BRICK)),
+            (KeyboardKey::KEY_ZERO, Tool::Tile(TileVariant::ROOF)),
+            (KeyboardKey::KEY_MINUS, Tool::Tile(TileVariant::FOLIAGE)),
+            (KeyboardKey::KEY_EQUAL, Tool::Tile(TileVariant::DEBRIS)),
+            (KeyboardKey::KEY_F, Tool::Spawn),
+            (KeyboardKey::KEY_G, Tool::Flag),
+            (KeyboardKey::KEY_Z, Tool::Zone),
+            (KeyboardKey::KEY_O, Tool::Door),
+            (KeyboardKey::KEY_T, Tool::Teleporter),
+            (KeyboardKey::KEY_P, Tool::Pad),
+        ] {
+            if handle.is_key_pressed(key) {
+                self.tool = tool;
+                self.zone_start = None;
+            }
+        }
+
+        if handle.is_key_pressed(KeyboardKey::KEY_L) {
+            self.layer = match self.layer {
+                MapLayer::FLOOR => MapLayer::DECORATION,
+                MapLayer::DECORATION => MapLayer::COLLISION,
+                MapLayer::COLLISION => MapLayer::OVERHEAD,
+                MapLayer::OVERHEAD => MapLayer::FLOOR,
+            };
+        }
+
+        if handle.is_key_pressed(KeyboardKey::KEY_TAB) {
+            self.team = match self.team {
+                None => Some(Team::RED),
+                Some(Team::RED) => Some(Team::BLUE),
+                Some(Team::BLUE) => None,
+            };
+        }
+    }
+
+    fn shortcuts(&mut self, handle: &RaylibHandle) {
+        if !handle.is_key_down(KeyboardKey::KEY_LEFT_CONTROL) {
+            return;
+        }
+
+        let shift = handle.is_key_down(KeyboardKey::KEY_LEFT_SHIFT);
+        if handle.is_key_pressed(KeyboardKey::KEY_Z) && !shift {
+            self.undo();
+        } else if handle.is_key_pressed(KeyboardKey::KEY_Y)
+            || (handle.is_key_pressed(KeyboardKey::KEY_Z) && shift)
+        {
+            self.redo();
+        } else if handle.is_key_pressed(KeyboardKey::KEY_S) {
+            self.save();
+        } else if handle.is_key_pressed(KeyboardKey::KEY_O) {
+            self.reload();
+        }
+    }
+
+    /// applies the current tool under the cursor, left places and right erases
+    fn edit(&mut self, handle: &RaylibHandle) {
+        let cell = self.cursor_cell(handle);
+        let place = handle.is_mouse_button_down(MouseButton::MOUSE_LEFT_BUTTON);
+        let clicked = handle.is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON);
+        let erase = handle.is_mouse_button_down(MouseButton::MOUSE_RIGHT_BUTTON);
+
+        if (place || erase) && self.stroke.is_none() {
+            self.stroke = Some(self.snapshot());
+        }
+
+        if self.map.tiles.contains(cell) {
+            match (self.tool, place, erase) {
+                // visual layers are painted over nothing and erased back to nothing
+                (Tool::Tile(variant), true, _) if self.layer != MapLayer::COLLISION => {
+                    let (width, height) = self.map.tiles.size();
+                    let grid = self.map.layers.entry(self.layer).or_insert_with(|| {
+                        TileGrid::new(width, height).expect("sized like the collision grid")
+                    });
+                    grid.set(cell, Some(variant));
+                    autotile_at(grid, cell);
+                }
+                (Tool::Tile(_), false, true) if self.layer != MapLayer::COLLISION => {
+                    if let Some(grid) = self.map.layers.get_mut(&self.layer) {
+                        grid.set(cell, None);
+                        autotile_at(grid, cell);
+                        if grid.is_empty() {
+                            self.map.layers.remove(&self.layer);
+                        }
+                    }
+                }
+                // walls around the cell may need another side or top either way
+                (Tool::Tile(variant), true, _) => {
+                    self.map.tiles.set(cell, Some(variant));
+                    autotile_at(&mut self.map.tiles, cell);
+                }
+                (Tool::Tile(_), false, true) => {
+                    self.map.tiles.set(cell, Some(TileVariant::GROUND));
+                    autotile_at(&mut self.map.tiles, cell);
+                }
+                (Tool::Spawn, _, true) => self.map.spawns.retain(|spawn| spawn.position != cell),
+                (Tool::Spawn, true, _)
+                    if !self.map.spawns.iter().any(|spawn| spawn.position == cell)
+                        && !self.map.tiles.is_solid(cell) =>
+                {
+                    self.map.spawns.push(SpawnPoint {
+                        position: cell,
+                        team: self.team,
+                    });
+                }
+                (Tool::Flag, _, true) => self.map.entities.retain(|entity| {
+                    !matches!(entity.kind, MapEntityKind::FLAG(_)) || entity.position != cell
+                }),
+                (Tool::Flag, true, _) => {
+                    // one flag base per team
+                    self.map.entities.retain(|entity| {
+                        entity.kind != MapEntityKind::FLAG(self.team) || self.team.is_none()
+                    });
+                    if !self.map.tiles.is_solid(cell) {
+                        self.map.entities.push(MapEntity {
+                            kind: MapEntityKind::FLAG(self.team),
+                            position: cell,
+                        });
+                    }
+                }
+                (Tool::Zone, _, true) => self.map.entities.retain(|entity| {
+                    let MapEntityKind::ZONE(w, h) = entity.kind else {
+                        return true;
+                    };
+                    let (x, y) = entity.position;
+                    !(cell.0 >= x && cell.0 < x + w && cell.1 >= y && cell.1 < y + h)
+                }),
+                (Tool::Zone | Tool::Pad, true, _) => {
+                    self.zone_start.get_or_insert(cell);
+                }
+                (Tool::Door | Tool::Teleporter | Tool::Pad, _, true) => {
+                    let tool = self.tool;
+                    self.map.entities.retain(|entity| {
+                        entity.position != cell
+                            || !matches!(
+                                (tool, &entity.kind),
+                                (Tool::Door, MapEntityKind::DOOR(_))
+                                    | (Tool::Teleporter, MapEntityKind::TELEPORTER(_))
+                                    | (Tool::Pad, MapEntityKind::PAD(..))
+                            )
+                    })
+                }
+                // clicking a door again flips whether it starts open
+                (Tool::Door, true, _) if clicked => {
+                    let door = self.map.entities.iter_mut().find(|entity| {
+                        entity.position == cell && matches!(entity.kind, MapEntityKind::DOOR(_))
+                    });
+                    match door {
+                        Some(MapEntity {
+                            kind: MapEntityKind::DOOR(open),
+                            ..
+                        }) => *open = !*open,
+                        _ if !self.map.tiles.is_solid(cell) => self.map.entities.push(MapEntity {
+                            kind: MapEntityKind::DOOR(false),
+                            position: cell,
+                        }),
+                        _ => {}
+                    }
+                }
+                (Tool::Teleporter, true, _)
+                    if clicked
+                        && !self.map.tiles.is_solid(cell)
+                        && MapFile::teleporter_exit(&self.map.entities, cell).is_none() =>
+                {
+                    let name = self.teleporter_name();
+                    self.map.entities.push(MapEntity {
+                        kind: MapEntityKind::TELEPORTER(name),
+                        position: cell,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        if handle.is_mouse_button_released(MouseButton::MOUSE_LEFT_BUTTON) {
+            if let (Tool::Pad, Some(start)) = (self.tool, self.zone_start.take()) {
+                // a pad is dragged from where it sits to where it throws
+                if start != cell && !self.map.tiles.is_solid(start) {
+                    self.map.entities.retain(|entity| {
+                        entity.position != start || !matches!(entity.kind, MapEntityKind::PAD(..))
+                    });
+                    self.map.entities.push(MapEntity {
+                        kind: MapEntityKind::PAD(cell.0 - start.0, cell.1 - start.1),
+                        position: start,
+                    });
+                }
+            }
+            if let (Tool::Zone, Some(start)) = (self.tool, self.zone_start.take()) {
+                let (x0, y0) = (start.0.min(cell.0).max(0), start.1.min(cell.1).max(0));
+                let (x1, y1) = (
+                    start.0.max(cell.0).min(self.map.tiles.width() - 1),
+                    start.1.max(cell.1).min(self.map.tiles.height() - 1),
+                );
+                self.map.entities.push(MapEntity {
+                    kind: MapEntityKind::ZONE(x1 - x0 + 1, y1 - y0 + 1),
+                    position: (x0, y0),
+                });
+            }
+        }
+
+        if !place && !erase {
+            if let Some(before) = self.stroke.take() {
+                if before != self.snapshot() {
+                    self.push_history(before);
+                }
+            }
+        }
+    }
+
+    /// name for a new teleporter, completing the first unpaired one or starting a new pair
+    fn teleporter_name(&self) -> String {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for entity in &self.map.entities {
+            if let MapEntityKind::TELEPORTER(name) = &entity.kind {
+                *counts.entry(name).or_default() += 1;
+            }
+        }
+
+        let mut unpaired = counts
+            .iter()
+            .filter(|(_, count)| **count == 1)
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>();
+        unpaired.sort();
+        if let Some(name) = unpaired.first() {
+            return name.to_string();
+        }
+
+        (0..)
+            .map(|index| match index {
+                0..=25 => ((b'a' + index as u8) as char).to_string(),
+                _ => format!("t{}", index),
+            })
+            .find(|name| !counts.contains_key(name.as_str()))
+            .unwrap()
+    }
+
+    pub fn render(&self, d: &mut RaylibMode2D<RaylibDrawHandle>, assets: &GameAssets) {
+        let (width, height) = self.map.tiles.bounds();
+
+        render_layers_under(d, &self.map.tiles, &self.map.layers, &self.camera, assets);
+        d.draw_rectangle_lines_ex(
+            Rectangle::new(0.0, 0.0, width, height),
+            4,
+            Color::WHITE.fade(0.5),
+        );
+        self.render_entities(d);
+
+        // kept see-through unless it is being painted
+        if let Some(overhead) = self.map.layers.get(&MapLayer::OVERHEAD) {
+            let alpha = if self.layer == MapLayer::OVERHEAD {
+                1.0
+            } else {
+                0.5
+            };
+            render_tiles(d, overhead, &self.camera, assets, |_, _| {
+                Some(Color::WHITE.fade(alpha))
+            });
+        }
+
+        let cell = {
+            let world = d.get_screen_to_world2D(d.get_mouse_position(), self.camera);
+            TileGrid::world_to_grid(world.x, world.y)
+        };
+        let (x0, y0, x1, y1) = match (self.tool, self.zone_start) {
+            (Tool::Zone, Some(start)) => (
+                start.0.min(cell.0),
+                start.1.min(cell.1),
+                start.0.max(cell.0),
+                start.1.max(cell.1),
+            ),
+            _ => (cell.0, cell.1, cell.0, cell.1),
+        };
+        let (wx, wy) = TileGrid::grid_to_world((x0, y0));
+        d.draw_rectangle_lines_ex(
+            Rectangle::new(
+                wx,
+                wy,
+                (x1 - x0 + 1) as f32 * WORLD_TILE_SIZE,
+                (y1 - y0 + 1) as f32 * WORLD_TILE_SIZE,
+            ),
+            3,
+            Color::YELLOW,
+        );
+
+        if let (Tool::Pad, Some(start)) = (self.tool, self.zone_start) {
+            let half = WORLD_TILE_SIZE / 2.0;
+            let (sx, sy) = TileGrid::grid_to_world(start);
+            d.draw_line_ex(
+                RVector2::new(sx + half, sy + half),
+                RVector2::new(wx + half, wy + half),
+                3.0,
+                Color::YELLOW,
+            );
+        }
+    }
+
+    fn render_entities(&self, d: &mut RaylibMode2D<RaylibDrawHandle>) {
+        let half = WORLD_TILE_SIZE / 2.0;
+
+        for zone in self.map.zones() {
+            let (x, y, w, h) = zone;
+            let (wx, wy) = TileGrid::grid_to_world((x, y));
+            let area = Rectangle::new(
+                wx,
+                wy,
+                w as f32 * WORLD_TILE_SIZE,
+                h as f32 * WORLD_TILE_SIZE,
+            );
+            d.draw_rectangle_rec(area, Color::GOLD.fade(0.25));
+            d.draw_rectangle_lines_ex(area, 3, Color::GOLD);
+        }
+
+        for spawn in &self.map.spawns {
+            let (x, y) = TileGrid::grid_to_world(spawn.position);
+            d.draw_circle(
+                (x + half) as i32,
+                (y + half) as i32,
+                half * 0.6,
+                team_color(spawn.team),
+            );
+            d.draw_circle_lines(
+                (x + half) as i32,
+                (y + half) as i32,
+                half * 0.6,
+                Color::BLACK,
+            );
+        }
+
+        for entity in &self.map.entities {
+            let (x, y) = TileGrid::grid_to_world(entity.position);
+            match &entity.kind {
+                MapEntityKind::FLAG(team) => {
+                    d.draw_rectangle(
+                        (x + half - 3.0) as i32,
+                        (y + 10.0) as i32,
+                        6,
+                        50,
+                        Color::BLACK,
+                    );
+                    d.draw_rectangle(
+                        (x + half + 3.0) as i32,
+                        (y + 10.0) as i32,
+                        24,
+                        16,
+                        team_color(*team),
+                    );
+                }
+                MapEntityKind::PICKUP(item) => {
+                    d.draw_rectangle_lines_ex(
+                        Rectangle::new(
+                            x + 10.0,
+                            y + 10.0,
+                            WORLD_TILE_SIZE - 20.0,
+                            WORLD_TILE_SIZE - 20.0,
+                        ),
+                        2,
+                        Color::LIME,
+                    );
+                    d.draw_text(
+                        item,
+                        (x + 12.0) as i32,
+                        (y + half - 6.0) as i32,
+                        12,
+                        Color::LIME,
+                    );
+                }
+                MapEntityKind::DOOR(open) => {
+                    let color = if *open { Color::LIME } else { Color::ORANGE };
+                    let area = Rectangle::new(x, y, WORLD_TILE_SIZE, WORLD_TILE_SIZE);
+                    d.draw_rectangle_rec(area, color.fade(0.3));
+                    d.draw_rectangle_lines_ex(area, 3, color);
+                    d.draw_text(
+                        if *open { "OPEN" } else { "DOOR" },
+                        (x + 8.0) as i32,
+                        (y + half - 8.0) as i32,
+                        16,
+                        color,
+                    );
+                }
+                MapEntityKind::TELEPORTER(_) => {
+                    render_map_entity(d, entity);
+                    if let Some(exit) =
+                        MapFile::teleporter_exit(&self.map.entities, entity.position)
+                    {
+                        let (ex, ey) = TileGrid::grid_to_world(exit);
+                        d.draw_line_ex(
+                            RVector2::new(x + half, y + half),
+                            RVector2::new(ex + half, ey + half),
+                            2.0,
+                            Color::VIOLET.fade(0.4),
+                        );
+                    }
+                }
+                MapEntityKind::PAD(dx, dy) => {
+                    render_map_entity(d, entity);
+                    let (tx, ty) =
+                        TileGrid::grid_to_world((entity.position.0 + dx, entity.position.1 + dy));
+                    d.draw_line_ex(
+                        RVector2::new(x + half, y + half),
+                        RVector2::new(tx + half, ty + half),
+                        2.0,
+                        Color::SKYBLUE.fade(0.6),
+                    );
+                    d.draw_circle_lines(
+                        (tx + half) as i32,
+                        (ty + half) as i32,
+                        half * 0.4,
+                        Color::SKYBLUE,
+                    );
+                }
+                MapEntityKind::ZONE(..) => {}
+            }
+        }
+    }
+}
+
+impl UpdateHandle for Editor {
+    fn update(&mut self, handle: &RaylibHandle) {
+        self.move_camera(handle);
+        self.select_tool(handle);
+        self.shortcuts(handle);
+        self.edit(handle);
+    }
+}
+
+impl UserInterfaceHandle for Editor {
+    fn display(&mut self, d: &mut RaylibDrawHandle) {
+        let cell = {
+            let world = d.get_screen_to_world2D(d.get_mouse_position(), self.camera);
+            TileGrid::world_to_grid(world.x, world.y)
+        };
+        let team = self
+            .team
+            .map_or(String::from("ANY"), |team| team.to_string());
+        let info = format!(
+            "{}{}  {}x{}  tool {}  layer {}  team {}  cell {},{}",
+            self.path.display(),
+            if self.modified { " *" } else { "" },
+            self.map.tiles.width(),
+            self.map.tiles.height(),
+            self.tool.name(),
+            self.layer.section(),
+            team,
+            cell.0,
+            cell.1
+        );
+
+        d.draw_rectangle(0, 0, WINDOW_WIDTH, 52, Color::new(0, 0, 0, 180));
+        d.draw_text(&info, WINDOW_PADDING / 2, 6, 20, Color::WHITE);
+        d.draw_text(HELP, WINDOW_PADDING / 2, 30, 16, Color::LIGHTGRAY);
+
+        if let Some((text, posted)) = &self.status {
+            if posted.elapsed().as_secs_f32() < 4.0 {
+                d.draw_rectangle(
+                    0,
+                    WINDOW_HEIGHT - 30,
+                    WINDOW_WIDTH,
+                    30,
+                    Color::new(0, 0, 0, 180),
+                );
+                d.draw_text(
+                    text,
+                    WINDOW_PADDING / 2,
+                    WINDOW_HEIGHT - 25,
+                    18,
+                    Color::WHITE,
+                );
+            }
+        }
+    }
+}
+
+/// runs the editor until the window is closed
+pub fn run(
+    handle: &mut RaylibHandle,
+    thread: &RaylibThread,
+    assets: &GameAssets,
+    mut editor: Editor,
+) {
+    while !handle.window_should_close() {
+        editor.update(handle);
+
+        let camera = editor.camera;
+        let mut draw = handle.begin_drawing(thread);
+        draw.clear_background(WINDOW_BACKGROUND_COLOR);
+
+        let mut draw_2d = draw.begin_mode2D(camera);
+        editor.render(&mut draw_2d, assets);
+        std::mem::drop(draw_2d);
+        editor.display(&mut draw);
+    }
+
+    if editor.modified {
+        log::warn!(
+            "closed the editor with unsaved changes to {}",
+            editor.path.display()
+        );
+    }
+}
+
+fn load(path: &Path) -> Result<MapFile, String> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let source = fs::read_to_string(path)
+        .map_err(|err| format!("failed to read {}: {}", path.display(), err))?;
+
+    // problems such as unknown symbols are left for the author to fix in the editor
+    let (map, errors) = MapFile::parse_recovering(&name, &source)
+        .map_err(|err| format!("failed to parse {}: {}", path.display(), err))?;
+    for err in errors {
+        log::warn!("{}: {}", path.display(), err);
+    }
+
+    Ok(map)
+}
+
+/// ground surrounded by walls
+fn blank(path: &Path, (width, height): (i32, i32)) -> Result<MapFile, String> {
+    let mut tiles = TileGrid::filled(width, height, TileVariant::GROUND)?;
+    for x in 0..width {
+        tiles.set((x, 0), Some(TileVariant::WALL_TOP));
+        tiles.set((x, height - 1), Some(TileVariant::WALL_TOP));
+    }
+    for y in 1..height - 1 {
+        tiles.set((0, y), Some(TileVariant::WALL_SIDE));
+        tiles.set((width - 1, y), Some(TileVariant::WALL_SIDE));
+    }
+
+    Ok(MapFile {
+        version: MAP_VERSION,
+        header: MapHeader {
+            name: path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            author: String::new(),
+            width,
+            height,
+            players: (2, SERVER_MAX_CLIENTS as u32),
+            modes: Vec::new(),
+        },
+        legend: MapFile::default_legend(),
+        tiles,
+        layers: BTreeMap::new(),
+        grid_line: 0,
+        spawns: Vec::new(),
+        entities: Vec::new(),
+    })
+}
+
+fn team_color(team: Option<Team>) -> Color {
+    match team {
+        Some(Team::RED) => Color::RED,
+        Some(Team::BLUE) => Color::BLUE,
+        None => Color::WHITE,
+    }
+}
@@ -1,9 +1,14 @@
+mod cache;
+mod chat;
+mod editor;
+mod vote;
+
 use env_logger;
 use raylib::prelude::*;
 use strum::VariantArray;
 
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::io::Read;
 use std::net::SocketAddr;
@@ -13,6 +18,12 @@ use std::time::SystemTime;
 
 use lib::prelude::*;
 use lib::types::*;
+use renet::DisconnectReason;
+
+use cache::MapCache;
+use chat::Chat;
+use editor::Editor;
+use vote::MapVote;
 
 fn main() {
     env_logger::init_from_env(Logger::env());
@@ -41,6 +52,17 @@ fn main() {
     };
     let assets = Rc::new(RefCell::new(ga_loaded.assets));
 
+    if let Some((path, size)) = editor_args() {
+        match Editor::open(path, size) {
+            Ok(editor) => editor::run(&mut handle, &thread, &assets.borrow(), editor),
+            Err(err) => {
+                log::error!("{}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     let mut data: [u8; 256] = [0; 256];
     for (index, byte) in settings.username.bytes().enumerate() {
         if index >= INITIAL_PAYLOAD_SIZE {
@@ -62,7 +84,8 @@ fn main() {
     };
 
     let mut menu = GameMenu::new(Rc::clone(&assets));
-    let mut game = Game::new(assets.clone(), settings);
+    let cache = MapCache::new(current_dir.join("cache").join("maps"));
+    let mut game = Game::new(assets.clone(), settings, network.uuid, cache);
 
     while !handle.window_should_close() {
         let delta_time = DELTA_TIME;
@@ -78,6 +101,15 @@ fn main() {
             game.net_update(&handle, &mut network);
         }
 
+        game.player.follow(game.world.bounds());
+
+        // escape closes the chat box instead of the game while typing
+        handle.set_exit_key(if game.chat.is_open() {
+            None
+        } else {
+            Some(KeyboardKey::KEY_ESCAPE)
+        });
+
         let mut draw = handle.begin_drawing(&thread);
         draw.clear_background(WINDOW_BACKGROUND_COLOR);
 
@@ -89,6 +121,9 @@ fn main() {
             game.render(&mut draw_2d);
             std::mem::drop(draw_2d);
             game.display(&mut draw);
+        } else if network.client.is_disconnected() {
+            std::mem::drop(draw_2d);
+            game.display_disconnected(&mut draw, network.client.disconnect_reason());
         }
 
         match network.transport.send_packets(&mut network.client) {
@@ -102,20 +137,112 @@ fn main() {
     }
 }
 
+/// `--editor <file.map> [--size WxH]` opens the map editor instead of joining the server
+fn editor_args() -> Option<(PathBuf, (i32, i32))> {
+    let mut args = std::env::args().skip(1);
+    let mut path = None;
+    let mut size = (32, 24);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--editor" => path = args.next().map(PathBuf::from),
+            "--size" => {
+                if let Some((w, h)) = args.next().as_deref().and_then(|size| size.split_once('x')) {
+                    if let (Ok(w), Ok(h)) = (w.parse::<i32>(), h.parse::<i32>()) {
+                        size = (w.max(1), h.max(1));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    path.map(|path| (path, size))
+}
+
 struct Game {
     pub assets: SharedAssets<GameAssets>,
     pub player: Player,
     pub world: GameWorld,
+    pub id: RawClientId,
+    /// centered banner text and the moment it was posted
+    pub announcement: Option<(String, Instant)>,
+    pub kill_feed: Vec<(KillEvent, Instant)>,
+    /// last confirmed hit and whether it finished the victim
+    pub hit_marker: Option<(Instant, bool)>,
+    pub chat: Chat,
+    pub vote: Option<MapVote>,
+    /// explanation sent by the server right before it dropped us
+    pub disconnect_reason: Option<String>,
+    pub cache: MapCache,
+    /// map being fetched from the server, missing from the cache
+    pub download: Option<MapDownload>,
+    /// jump pad flight in progress
+    pub launch: Option<Launch>,
+}
+
+/// flight from a jump pad, the player is moved along the line between the two points
+struct Launch {
+    from: (f32, f32),
+    to: (f32, f32),
+    started: Instant,
+    duration: f32,
 }
 
 impl Game {
-    pub fn new(assets: SharedAssets<GameAssets>, settings: GameSettings) -> Self {
+    pub fn new(
+        assets: SharedAssets<GameAssets>,
+        settings: GameSettings,
+        id: RawClientId,
+        cache: MapCache,
+    ) -> Self {
         Self {
             assets: Rc::clone(&assets),
             player: Player::new(settings.username, Rc::clone(&assets)),
             world: GameWorld::new(),
+            id,
+            announcement: None,
+            kill_feed: Vec::new(),
+            hit_marker: None,
+            chat: Chat::new(),
+            vote: None,
+            disconnect_reason: None,
+            cache,
+            download: None,
+            launch: None,
+        }
+    }
+
+    /// whether the local player (or their team) is the given zone owner
+    fn is_own(&self, owner: &ZoneOwner) -> bool {
+        *owner == ZoneOwner::of(self.id, self.player.team)
+    }
+
+    fn owner_color(&self, owner: &ZoneOwner) -> Color {
+        if self.is_own(owner) {
+            Color::new(42, 192, 138, 255)
+        } else {
+            Color::new(230, 72, 72, 255)
+        }
+    }
+
+    fn owner_label(&self, owner: &ZoneOwner) -> String {
+        match owner {
+            ZoneOwner::TEAM(team) => format!("{:?}", team),
+            _ if self.is_own(owner) => String::from("YOU"),
+            ZoneOwner::PLAYER(id) => self.player_name(*id),
         }
     }
+
+    /// display name as known from the scoreboard
+    fn player_name(&self, id: RawClientId) -> String {
+        self.world
+            .scoreboard
+            .iter()
+            .find(|entry| entry.id == id)
+            .map(|entry| entry.name.clone())
+            .unwrap_or_else(|| format!("#{}", id % 10000))
+    }
 }
 
 impl UpdateHandle for Game {
@@ -129,7 +256,6 @@ impl NetUpdateHandle for Game {
 
     fn net_update(&mut self, handle: &RaylibHandle, network: &mut Self::Network) {
         let local_player = &mut self.player;
-        let assets = self.assets.borrow();
 
         // reliable order messages
         while let Some(message) = network
@@ -138,42 +264,82 @@ impl NetUpdateHandle for Game {
         {
             if let Ok(packet) = rmp_serde::from_slice::<GameNetworkPacket>(&message) {
                 match packet {
-                    GameNetworkPacket::NET_WORLD_MAP(map) => {
-                        let mut tiles = HashMap::new();
-                        for ((x, y), tile) in map {
-                            let tile_texture = match tile {
-                                TileVariant::WALL_SIDE => LTexture::TILE_WALL_SIDE,
-                                TileVariant::WALL_TOP => LTexture::TILE_WALL_TOP,
-                                TileVariant::GROUND => LTexture::TILE_GROUND,
-                            };
-                            // hydration
-                            if let Some(buffer) = assets.textures.get(&tile_texture) {
-                                let buffer: &Texture2D = buffer;
-                                let (w, h) = (buffer.width as f32, buffer.height as f32);
-
-                                tiles.insert(
-                                    (x, y),
-                                    Tile::new(
-                                        tile,
-                                        tile_texture,
-                                        x as u8,
-                                        y as u8,
-                                        w,
-                                        h,
-                                        WORLD_TILE_SIZE,
-                                    ),
-                                );
-                            }
+                    GameNetworkPacket::NET_MAP_INFO(info) => {
+                        self.world.clear();
+                        self.download = None;
+                        self.launch = None;
+
+                        if let Some(tiles) = self.cache.load(&info) {
+                            log::info!("loaded map {} from cache", info.name);
+                            self.world.load_map(tiles);
+                        } else {
+                            log::info!("downloading map {} ({} bytes)", info.name, info.size);
+                            network.client.send_message(
+                                DefaultChannel::ReliableUnordered,
+                                GameNetworkPacket::NET_MAP_REQUEST(info.hash.clone())
+                                    .serialized()
+                                    .unwrap(),
+                            );
+                            self.download = Some(MapDownload::new(info));
+                        }
+                    }
+                    GameNetworkPacket::NET_MAP_CHUNK(hash, index, data) => {
+                        let Some(download) = &mut self.download else {
+                            continue;
+                        };
+                        if download.info.hash != hash {
+                            continue;
                         }
 
-                        self.world.tiles = tiles;
+                        download.insert(index, data);
+                        if download.is_complete() {
+                            let download = self.download.take().unwrap();
+                            let info = download.info.clone();
+
+                            match download.finish() {
+                                Ok((data, tiles)) => {
+                                    self.cache.store(&info, &data);
+                                    self.world.load_map(tiles);
+                                }
+                                Err(err) => {
+                                    log::error!("failed to load map {}: {}", info.name, err)
+                                }
+                            }
+                        }
+                    }
+                    GameNetworkPacket::NET_MAP_DELTA(delta) => {
+                        self.world.apply_delta(delta);
+                    }
+                    GameNetworkPacket::NET_MAP_ENTITIES(entities) => {
+                        self.world.entities = entities;
+                    }
+                    GameNetworkPacket::NET_PLAYER_TELEPORT((x, y)) => {
+                        self.launch = None;
+                        let position = local_player.move_to(nalgebra::Vector2::new(x, y));
+                        network.client.send_message(
+                            DefaultChannel::Unreliable,
+                            GameNetworkPacket::NET_PLAYER_WORLD_POSITION(
+                                network.uuid,
+                                (position.x, position.y),
+                            )
+                            .serialized()
+                            .unwrap(),
+                        );
+                    }
+                    GameNetworkPacket::NET_PLAYER_LAUNCH(to, duration) => {
+                        self.launch = Some(Launch {
+                            from: (local_player.rectangle.x, local_player.rectangle.y),
+                            to,
+                            started: Instant::now(),
+                            duration,
+                        });
                     }
                     GameNetworkPacket::NET_WORLD_PLAYERS(players) => {
                         self.world.enemies = players
                             .into_iter()
                             .map(|(id, data)| {
                                 let client_id = ClientId::from_raw(id);
-                                let enemy = Enemy::new(
+                                let mut enemy = Enemy::new(
                                     client_id,
                                     data.position.0,
                                     data.position.1,
@@ -181,6 +347,7 @@ impl NetUpdateHandle for Game {
                                     data.health,
                                     Rc::clone(&self.assets),
                                 );
+                                enemy.team = data.team;
 
                                 (client_id, enemy)
                             })
@@ -197,13 +364,14 @@ impl NetUpdateHandle for Game {
                             local_player.rectangle.x = pos_x;
                             local_player.rectangle.y = pos_y;
                             local_player.inventory.cash = data.cash;
+                            local_player.team = data.team;
                             local_player.ready = true;
 
                             local_player.inventory.select(data.weapon);
                             local_player.inventory.add(Weapon::new(data.weapon));
                         } else {
                             let id = ClientId::from_raw(data._id);
-                            let enemy = Enemy::new(
+                            let mut enemy = Enemy::new(
                                 id,
                                 pos_x,
                                 pos_y,
@@ -211,10 +379,46 @@ impl NetUpdateHandle for Game {
                                 data.health,
                                 Rc::clone(&self.assets),
                             );
+                            enemy.team = data.team;
 
                             self.world.enemies.insert(id, enemy);
                         }
                     }
+                    GameNetworkPacket::NET_CHAT_MESSAGE(message) => {
+                        self.chat.push(message);
+                    }
+                    GameNetworkPacket::NET_ROUND_START(round) => {
+                        self.announcement = Some((format!("ROUND {}", round), Instant::now()));
+                    }
+                    GameNetworkPacket::NET_ROUND_END(winner) => {
+                        let text = match winner {
+                            Some((id, _)) if id == self.id => String::from("YOU WON THE ROUND"),
+                            Some((_, name)) => format!("{} WON THE ROUND", name.to_uppercase()),
+                            None => String::from("NOBODY SURVIVED"),
+                        };
+
+                        self.world.safe_zone = None;
+                        self.announcement = Some((text, Instant::now()));
+                    }
+                    GameNetworkPacket::NET_MAP_VOTE_START(candidates, remaining) => {
+                        self.vote = Some(MapVote::new(candidates, remaining));
+                    }
+                    GameNetworkPacket::NET_MAP_VOTE_STATE(tally) => {
+                        if let Some(vote) = &mut self.vote {
+                            vote.set_tally(tally);
+                        }
+                    }
+                    GameNetworkPacket::NET_MAP_VOTE_END(map) => {
+                        self.vote = None;
+                        self.announcement = Some((
+                            format!("NEXT MAP: {}", map.trim_end_matches(".map").to_uppercase()),
+                            Instant::now(),
+                        ));
+                    }
+                    GameNetworkPacket::NET_DISCONNECT_REASON(reason) => {
+                        log::warn!("server is closing the connection: {}", reason);
+                        self.disconnect_reason = Some(reason);
+                    }
                     _ => {}
                 }
             };
@@ -235,12 +439,32 @@ impl NetUpdateHandle for Game {
                     }
                     GameNetworkPacket::NET_PLAYER_RESPAWN(d_id, data) => {
                         if d_id == network.transport.client_id().raw() {
+                            self.launch = None;
                             local_player.rectangle.x = data.position.0;
                             local_player.rectangle.y = data.position.1;
                             local_player.health = data.health;
                             local_player.ready = true;
+                            local_player.dead = false;
+                            local_player.team = data.team;
                             local_player.inventory.cash = data.cash;
                             local_player.inventory.reset_weapons();
+                        } else if let Some(puppet) =
+                            self.world.enemies.get_mut(&ClientId::from_raw(d_id))
+                        {
+                            puppet.rectangle.x = data.position.0;
+                            puppet.rectangle.y = data.position.1;
+                            puppet.health = data.health;
+                            puppet.team = data.team;
+                        }
+                    }
+                    GameNetworkPacket::NET_PLAYER_PROTECTED(id, seconds) => {
+                        let until = Some(Instant::now() + Duration::from_secs_f32(seconds));
+                        if id == network.transport.client_id().raw() {
+                            local_player.protected_until = until;
+                        } else if let Some(puppet) =
+                            self.world.enemies.get_mut(&ClientId::from_raw(id))
+                        {
+                            puppet.protected_until = until;
                         }
                     }
                     GameNetworkPacket::NET_PLAYER_KILL_REWARD(data) => {
@@ -265,21 +489,23 @@ impl NetUpdateHandle for Game {
 
                         if let Some(cid) = cid {
                             if cid == network.transport.client_id().raw() {
-                                local_player.health -= damage as Health;
-                                local_player.health = nalgebra::clamp(
-                                    local_player.health,
-                                    0,
-                                    ENTITY_PLAYER_MAX_HEALTH,
-                                );
+                                local_player.take_damage(damage);
                             } else if let Some(puppet) =
                                 self.world.enemies.get_mut(&ClientId::from_raw(cid))
                             {
-                                puppet.health -= damage as Health;
-                                puppet.health =
-                                    nalgebra::clamp(puppet.health, 0, ENTITY_PLAYER_MAX_HEALTH);
+                                puppet.take_damage(damage);
                             }
                         }
                     }
+                    GameNetworkPacket::NET_PLAYER_DAMAGE(id, damage) => {
+                        if id == network.transport.client_id().raw() {
+                            local_player.take_damage(damage);
+                        } else if let Some(puppet) =
+                            self.world.enemies.get_mut(&ClientId::from_raw(id))
+                        {
+                            puppet.take_damage(damage);
+                        }
+                    }
                     GameNetworkPacket::NET_PLAYER_WEAPON_SELECT(id, variant) => {
                         if let Some(enemy) = self.world.enemies.get_mut(&ClientId::from_raw(id)) {
                             enemy.weapon = Some(variant)
@@ -299,6 +525,21 @@ impl NetUpdateHandle for Game {
                         local_player.inventory.refill_ammo();
                         local_player.inventory.cash -= PLAYER_AMMO_COST
                     }
+                    GameNetworkPacket::NET_KILL_EVENT(event) => {
+                        self.kill_feed.push((event, Instant::now()));
+                        self.kill_feed.retain(|(_, posted)| {
+                            posted.elapsed().as_secs_f32() < HUD_KILL_FEED_DURATION
+                        });
+
+                        if self.kill_feed.len() > HUD_KILL_FEED_SIZE {
+                            self.kill_feed.remove(0);
+                        }
+                    }
+                    GameNetworkPacket::NET_DAMAGE_EVENT(event) => {
+                        if event.attacker == self.id {
+                            self.hit_marker = Some((Instant::now(), event.lethal));
+                        }
+                    }
                     _ => {}
                 }
             }
@@ -318,14 +559,71 @@ impl NetUpdateHandle for Game {
                             enemy.rectangle.y = y;
                         }
                     }
+                    GameNetworkPacket::NET_ZONE_STATE(zones, scores) => {
+                        self.world.zones = zones;
+                        self.world.zone_scores = scores;
+                    }
+                    GameNetworkPacket::NET_SAFE_ZONE(center, radius) => {
+                        self.world.safe_zone = Some((center, radius));
+                    }
+                    GameNetworkPacket::NET_SCOREBOARD(mut entries) => {
+                        entries.sort_by(|a, b| {
+                            b.stats
+                                .score
+                                .cmp(&a.stats.score)
+                                .then(b.stats.kills.cmp(&a.stats.kills))
+                        });
+                        self.world.scoreboard = entries;
+                    }
                     _ => {}
                 }
             }
         }
 
+        self.chat.net_update(handle, network);
+        local_player.chatting = self.chat.is_open();
+
+        if let Some(vote) = &mut self.vote {
+            if !self.chat.is_open() {
+                vote.net_update(handle, network);
+            }
+        }
+
         local_player.net_update(handle, network);
 
-        let position = local_player.on_move(handle);
+        // flying off a pad, walls and cover don't stop the player until they land
+        if let Some(launch) = &self.launch {
+            let progress = (launch.started.elapsed().as_secs_f32() / launch.duration).min(1.0);
+            let position = local_player.move_to(nalgebra::Vector2::new(
+                launch.from.0 + (launch.to.0 - launch.from.0) * progress,
+                launch.from.1 + (launch.to.1 - launch.from.1) * progress,
+            ));
+            network.client.send_message(
+                DefaultChannel::Unreliable,
+                GameNetworkPacket::NET_PLAYER_WORLD_POSITION(
+                    network.uuid,
+                    (position.x, position.y),
+                )
+                .serialized()
+                .unwrap(),
+            );
+
+            if progress >= 1.0 {
+                self.launch = None;
+            }
+            return;
+        }
+
+        let standing_on = TileGrid::world_to_grid(
+            local_player.rectangle.x + local_player.rectangle.width / 2.0,
+            local_player.rectangle.y + local_player.rectangle.height / 2.0,
+        );
+        let speed = self
+            .world
+            .tiles
+            .get(standing_on)
+            .map_or(1.0, |tile| tile.properties().speed);
+        let position = local_player.on_move(handle, speed);
         if self.world.in_bounds(
             position.x,
             position.y,
@@ -341,17 +639,15 @@ impl NetUpdateHandle for Game {
                 local_player.rectangle.height,
             );
 
-            for tile in self
+            if self
                 .world
-                .offset_tiles((local_player.grid.x, local_player.grid.y))
+                .tiles
+                .around((local_player.grid.x, local_player.grid.y), 1)
+                .any(|(grid, tile)| {
+                    tile.is_solid() && rectangle.check_collision_recs(&TileGrid::cell_rect(grid))
+                })
             {
-                if let Some(tile) = tile {
-                    if tile.variant != TileVariant::GROUND
-                        && rectangle.check_collision_recs(&tile.dest_rect)
-                    {
-                        return;
-                    }
-                }
+                return;
             }
 
             let position = local_player.move_to(position);
@@ -376,42 +672,65 @@ impl RenderHandle for Game {
             return;
         }
 
-        if self.world.tiles.len() > 0 {
-            for tile in self.world.tiles.values() {
-                let texture = assets.textures.get(&tile.texture).unwrap();
+        if !self.world.tiles.is_empty() {
+            render_layers_under(
+                d,
+                &self.world.tiles,
+                &self.world.layers,
+                &self.player.camera,
+                &assets,
+            );
+            self.world.render_tile_damage(d, &assets);
 
-                d.draw_texture_pro(
-                    texture,
-                    tile.src_rect,
-                    tile.dest_rect,
-                    RVector2::zero(),
-                    0.0,
-                    Color::WHITE,
-                );
+            for entity in &self.world.entities {
+                render_map_entity(d, entity);
+            }
 
-                #[cfg(debug_assertions)]
-                {
-                    d.draw_text(
-                        &format!("{:?} {:?}", tile.grid.x, tile.grid.y),
-                        tile.dest_rect.x as i32,
-                        tile.dest_rect.y as i32,
-                        12,
-                        Color::new(255, 255, 255, 50),
-                    );
-                    d.draw_rectangle_lines(
-                        tile.dest_rect.x as i32,
-                        tile.dest_rect.y as i32,
-                        tile.dest_rect.width as i32,
-                        tile.dest_rect.height as i32,
-                        Color::new(255, 255, 255, 50),
+            for zone in &self.world.zones {
+                let (x, y, w, h) = zone.world_area();
+                let area = Rectangle::new(x, y, w, h);
+
+                let tint = match (zone.status, &zone.owner) {
+                    (ZoneStatus::CONTESTED, _) => {
+                        let pulse = ((d.get_time() * 6.0).sin() * 0.5 + 0.5) as f32;
+                        Color::new(255, 161, 0, 40 + (pulse * 60.0) as u8)
+                    }
+                    (_, Some(owner)) => self.owner_color(owner).fade(0.25),
+                    (_, None) => Color::new(255, 255, 255, 30),
+                };
+
+                d.draw_rectangle_rec(area, tint);
+
+                // capture progress fills the zone from the bottom up
+                if let ZoneStatus::CAPTURING(capturer) = zone.status {
+                    let filled = h * zone.progress.clamp(0.0, 1.0);
+                    d.draw_rectangle_rec(
+                        Rectangle::new(x, y + h - filled, w, filled),
+                        self.owner_color(&capturer).fade(0.35),
                     );
                 }
+
+                d.draw_rectangle_lines_ex(area, 2, tint.fade(0.8));
             }
 
-            let (w, h) = (
-                (self.world.tiles.len() as f32).sqrt() * WORLD_TILE_SIZE,
-                (self.world.tiles.len() as f32).sqrt() * WORLD_TILE_SIZE,
-            );
+            // everything past the safe zone edge is tinted red
+            if let Some(((cx, cy), radius)) = self.world.safe_zone {
+                let (w, h) = self.world.bounds();
+                let outer = (w * w + h * h).sqrt() + radius;
+
+                d.draw_ring(
+                    RVector2::new(cx, cy),
+                    radius,
+                    outer,
+                    0.0,
+                    360.0,
+                    72,
+                    Color::new(230, 72, 72, 60),
+                );
+                d.draw_circle_lines(cx as i32, cy as i32, radius, Color::new(230, 72, 72, 200));
+            }
+
+            let (w, h) = self.world.bounds();
             d.draw_rectangle_lines_ex(Rectangle::new(0.0, 0.0, w, h), 1, Color::LIGHTGRAY);
         }
 
@@ -421,6 +740,20 @@ impl RenderHandle for Game {
         for enemy in self.world.enemies.values_mut() {
             enemy.render(d);
         }
+
+        // roofs and foliage around the local player turn see-through
+        if let Some(overhead) = self.world.layers.get(&MapLayer::OVERHEAD) {
+            let player = &self.player.rectangle;
+            let center = RVector2::new(
+                player.x + player.width / 2.0,
+                player.y + player.height / 2.0,
+            );
+            render_tiles(d, overhead, &self.player.camera, &assets, |position, _| {
+                let near = TileGrid::cell_rect(position)
+                    .check_collision_circle_rec(center, OVERHEAD_REVEAL_RADIUS);
+                Some(Color::WHITE.fade(if near { OVERHEAD_REVEAL_ALPHA } else { 1.0 }))
+            });
+        }
     }
 }
 
@@ -560,28 +893,99 @@ impl GameSettings {
 }
 
 pub struct GameWorld {
-    tiles: HashMap<(i32, i32), Tile>,
+    /// the collision layer
+    tiles: TileGrid,
+    /// floor, decoration and overhead layers
+    layers: BTreeMap<MapLayer, TileGrid>,
+    /// damage stage of every damaged tile
+    tile_damage: HashMap<(i32, i32), u8>,
+    /// map deltas that arrived while the map was still downloading
+    pending_delta: MapDelta,
     projectiles: HashMap<RawProjectileId, Projectile>,
     enemies: HashMap<ClientId, Enemy>,
+    zones: Vec<ZoneData>,
+    zone_scores: HashMap<ZoneOwner, u32>,
+    safe_zone: Option<((f32, f32), f32)>,
+    scoreboard: Vec<ScoreboardEntry>,
+    /// doors, teleporters and pads of the current map
+    entities: Vec<MapEntity>,
 }
 
 impl GameWorld {
     fn new() -> Self {
         Self {
-            tiles: HashMap::new(),
+            tiles: TileGrid::default(),
+            layers: BTreeMap::new(),
+            tile_damage: HashMap::new(),
+            pending_delta: MapDelta::default(),
             enemies: HashMap::new(),
             projectiles: HashMap::new(),
+            zones: Vec::new(),
+            zone_scores: HashMap::new(),
+            safe_zone: None,
+            scoreboard: Vec::new(),
+            entities: Vec::new(),
         }
     }
 
-    fn offset_tiles(&self, (x, y): (i32, i32)) -> Vec<Option<&Tile>> {
-        POINT_OFFSETS
-            .into_iter()
-            .map(|(dx, dy)| (x + dx as i32, y + dy as i32))
-            .collect::<Vec<(i32, i32)>>()
-            .into_iter()
-            .map(|(gx, gy)| self.tiles.get(&(gx, gy)))
-            .collect::<Vec<_>>()
+    /// drops everything left over from the previous map
+    fn clear(&mut self) {
+        self.tiles = TileGrid::default();
+        self.layers.clear();
+        self.tile_damage.clear();
+        self.pending_delta = MapDelta::default();
+        self.projectiles.clear();
+        self.zones.clear();
+        self.zone_scores.clear();
+        self.safe_zone = None;
+        self.entities.clear();
+    }
+
+    fn load_map(&mut self, tiles: MapTiles) {
+        self.tiles = tiles.collision;
+        self.layers = tiles.layers;
+
+        let delta = std::mem::take(&mut self.pending_delta);
+        self.apply_delta(delta);
+    }
+
+    fn apply_delta(&mut self, delta: MapDelta) {
+        if self.tiles.is_empty() {
+            for change in delta.changes {
+                self.pending_delta.push(change);
+            }
+            return;
+        }
+
+        for change in delta.changes {
+            self.tiles.set(change.position, Some(change.tile));
+            if change.damage > 0 {
+                self.tile_damage.insert(change.position, change.damage);
+            } else {
+                self.tile_damage.remove(&change.position);
+            }
+        }
+    }
+
+    /// cracks over damaged tiles, `TILE_CRACKS` has a frame for every stage past the first
+    fn render_tile_damage(&self, d: &mut RaylibMode2D<RaylibDrawHandle>, assets: &GameAssets) {
+        let Some(texture) = assets.textures.get(&LTexture::TILE_CRACKS) else {
+            return;
+        };
+        let frames = (TILE_DAMAGE_STAGES - 1) as f32;
+        let (width, height) = (texture.width as f32 / frames, texture.height as f32);
+
+        for (position, stage) in &self.tile_damage {
+            let source = Rectangle::new((*stage - 1) as f32 * width, 0.0, width, height);
+            d.draw_texture_pro(
+                texture,
+                source,
+                TileGrid::cell_rect(*position),
+                RVector2::zero(),
+                0.0,
+                Color::WHITE,
+            );
+        }
     }
 
     fn render_projectiles(&mut self, d: &mut RaylibMode2D<RaylibDrawHandle>) {
@@ -601,8 +1005,7 @@ impl GameWorld {
     }
 
     fn bounds(&self) -> (f32, f32) {
-        let length = (self.tiles.len() as f32).sqrt() * WORLD_TILE_SIZE;
-        (length, length)
+        self.tiles.bounds()
     }
 
     fn in_bounds(&self, x: f32, y: f32, width: f32, height: f32) -> bool {
@@ -611,6 +1014,181 @@ impl GameWorld {
     }
 }
 
+/// draws the floor, decoration and collision layers in that order, ground in the collision
+/// grid is the lack of anything to collide with so the layers below show through it
+fn render_layers_under(
+    d: &mut RaylibMode2D<RaylibDrawHandle>,
+    collision: &TileGrid,
+    layers: &BTreeMap<MapLayer, TileGrid>,
+    camera: &Camera2D,
+    assets: &GameAssets,
+) {
+    let below = [MapLayer::FLOOR, MapLayer::DECORATION]
+        .into_iter()
+        .filter_map(|layer| layers.get(&layer))
+        .collect::<Vec<_>>();
+
+    for grid in &below {
+        render_tiles(d, grid, camera, assets, |_, _| Some(Color::WHITE));
+    }
+    render_tiles(d, collision, camera, assets, |position, tile| {
+        let covered = below.iter().any(|grid| grid.get(position).is_some());
+        (tile != TileVariant::GROUND || !covered).then_some(Color::WHITE)
+    });
+}
+
+/// draws the tiles inside the camera view, large maps would stall otherwise
+///
+/// `tint` colors every tile, tiles it returns `None` for are skipped
+fn render_tiles(
+    d: &mut RaylibMode2D<RaylibDrawHandle>,
+    tiles: &TileGrid,
+    camera: &Camera2D,
+    assets: &GameAssets,
+    tint: impl Fn((i32, i32), TileVariant) -> Option<Color>,
+) {
+    let top_left = TileGrid::world_to_grid(
+        camera.target.x - camera.offset.x / camera.zoom,
+        camera.target.y - camera.offset.y / camera.zoom,
+    );
+    let bottom_right = TileGrid::world_to_grid(
+        camera.target.x + (WINDOW_WIDTH as f32 - camera.offset.x) / camera.zoom,
+        camera.target.y + (WINDOW_HEIGHT as f32 - camera.offset.y) / camera.zoom,
+    );
+
+    for ((x, y), variant) in tiles.region(top_left, bottom_right) {
+        let Some(tint) = tint((x, y), variant) else {
+            continue;
+        };
+        let (texture_id, frame, (columns, rows)) = tile_texture(tiles, (x, y), variant, assets);
+        let Some(texture) = assets.textures.get(&texture_id) else {
+            continue;
+        };
+        let mut tile = Tile::new(
+            variant,
+            texture_id,
+            x,
+            y,
+            texture.width as f32 / columns as f32,
+            texture.height as f32 / rows as f32,
+            WORLD_TILE_SIZE,
+        );
+        tile.src_rect.x = (frame % columns) as f32 * tile.src_rect.width;
+        tile.src_rect.y = (frame / columns) as f32 * tile.src_rect.height;
+
+        d.draw_texture_pro(
+            texture,
+            tile.src_rect,
+            tile.dest_rect,
+            RVector2::zero(),
+            0.0,
+            tint,
+        );
+
+        #[cfg(debug_assertions)]
+        {
+            d.draw_text(
+                &format!("{:?} {:?}", tile.grid.x, tile.grid.y),
+                tile.dest_rect.x as i32,
+                tile.dest_rect.y as i32,
+                12,
+                Color::new(255, 255, 255, 50),
+            );
+            d.draw_rectangle_lines(
+                tile.dest_rect.x as i32,
+                tile.dest_rect.y as i32,
+                tile.dest_rect.width as i32,
+                tile.dest_rect.height as i32,
+                Color::new(255, 255, 255, 50),
+            );
+        }
+    }
+}
+
+/// marks teleporters and pads, doors are drawn as tiles
+fn render_map_entity(d: &mut RaylibMode2D<RaylibDrawHandle>, entity: &MapEntity) {
+    let half = WORLD_TILE_SIZE / 2.0;
+    let (x, y) = TileGrid::grid_to_world(entity.position);
+    let center = RVector2::new(x + half, y + half);
+
+    match &entity.kind {
+        MapEntityKind::TELEPORTER(name) => {
+            let pulse = ((d.get_time() * 3.0).sin() * 0.5 + 0.5) as f32;
+            d.draw_circle_v(center, half * 0.8, Color::PURPLE.fade(0.25 + pulse * 0.2));
+            d.draw_ring(
+                center,
+                half * 0.65,
+                half * 0.8,
+                0.0,
+                360.0,
+                36,
+                Color::VIOLET,
+            );
+            d.draw_text(
+                name,
+                (x + 6.0) as i32,
+                (y + 4.0) as i32,
+                14,
+                Color::WHITE.fade(0.8),
+            );
+        }
+        MapEntityKind::PAD(dx, dy) => {
+            d.draw_circle_v(center, half * 0.75, Color::SKYBLUE.fade(0.35));
+            d.draw_circle_lines(
+                center.x as i32,
+                center.y as i32,
+                half * 0.75,
+                Color::SKYBLUE,
+            );
+
+            // arrow towards where the pad throws
+            let direction = RVector2::new(*dx as f32, *dy as f32).normalized();
+            let tip = center + direction * half * 0.6;
+            let side = RVector2::new(-direction.y, direction.x) * half * 0.3;
+            let back = center - direction * half * 0.1;
+            d.draw_line_ex(center - direction * half * 0.5, tip, 4.0, Color::WHITE);
+            d.draw_line_ex(tip, back + side, 4.0, Color::WHITE);
+            d.draw_line_ex(tip, back - side, 4.0, Color::WHITE);
+        }
+        _ => {}
+    }
+}
+
+/// texture of a tile, the frame to draw and how many (columns, rows) of frames it has
+///
+/// walls draw their edges and corners from atlases when those are installed, see `wall_frame`
+fn tile_texture(
+    tiles: &TileGrid,
+    position: (i32, i32),
+    variant: TileVariant,
+    assets: &GameAssets,
+) -> (LTexture, u8, (u8, u8)) {
+    let has = |texture| assets.textures.contains_key(&texture);
+    let frame = || wall_frame(variant, wall_mask(tiles, position));
+
+    match variant {
+        TileVariant::WALL_SIDE if has(LTexture::TILE_WALL_SIDE_EDGES) => {
+            (LTexture::TILE_WALL_SIDE_EDGES, frame(), (4, 1))
+        }
+        TileVariant::WALL_TOP | TileVariant::WALL if has(LTexture::TILE_WALL_TOP_EDGES) => {
+            (LTexture::TILE_WALL_TOP_EDGES, frame(), (4, 4))
+        }
+        TileVariant::WALL_SIDE => (LTexture::TILE_WALL_SIDE, 0, (1, 1)),
+        TileVariant::WALL_TOP | TileVariant::WALL => (LTexture::TILE_WALL_TOP, 0, (1, 1)),
+        TileVariant::GROUND => (LTexture::TILE_GROUND, 0, (1, 1)),
+        TileVariant::COVER => (LTexture::TILE_COVER, 0, (1, 1)),
+        TileVariant::WATER => (LTexture::TILE_WATER, 0, (1, 1)),
+        TileVariant::HAZARD => (LTexture::TILE_HAZARD, 0, (1, 1)),
+        TileVariant::CRATE => (LTexture::TILE_CRATE, 0, (1, 1)),
+        TileVariant::BRICK => (LTexture::TILE_BRICK, 0, (1, 1)),
+        TileVariant::DOOR => (LTexture::TILE_DOOR, 0, (1, 1)),
+        TileVariant::DOOR_OPEN => (LTexture::TILE_DOOR_OPEN, 0, (1, 1)),
+        TileVariant::ROOF => (LTexture::TILE_ROOF, 0, (1, 1)),
+        TileVariant::FOLIAGE => (LTexture::TILE_FOLIAGE, 0, (1, 1)),
+        TileVariant::DEBRIS => (LTexture::TILE_DEBRIS, 0, (1, 1)),
+    }
+}
+
 impl UserInterfaceHandle for Game {
     fn display(&mut self, d: &mut RaylibDrawHandle) {
         let local_player = &self.player;
@@ -639,6 +1217,141 @@ impl UserInterfaceHandle for Game {
             );
         }
 
+        if let Some((text, posted)) = &self.announcement {
+            let elapsed = posted.elapsed().as_secs_f32();
+
+            if elapsed < 3.0 {
+                let alpha = (1.0 - (elapsed - 2.0).max(0.0)).clamp(0.0, 1.0);
+                let text_size = text::measure_text_ex(poppins_black, text, 48.0, 1.0);
+
+                d.draw_text_ex(
+                    poppins_black,
+                    text,
+                    RVector2::new(
+                        WINDOW_CENTER_X - text_size.x / 2.0,
+                        WINDOW_HEIGHT as f32 / 4.0,
+                    ),
+                    48.0,
+                    1.0,
+                    Color::WHITE.fade(alpha),
+                );
+            }
+        }
+
+        if let Some(download) = &self.download {
+            let text = format!(
+                "DOWNLOADING {} {}%",
+                download.info.name.to_uppercase(),
+                (download.progress() * 100.0) as u32
+            );
+            let text_size = text::measure_text_ex(poppins_black, &text, 24.0, 1.0);
+
+            d.draw_text_ex(
+                poppins_black,
+                &text,
+                RVector2::new(WINDOW_CENTER_X - text_size.x / 2.0, WINDOW_CENTER_Y),
+                24.0,
+                1.0,
+                Color::WHITE,
+            );
+        }
+
+        // king of the hill scores
+        if !self.world.zones.is_empty() {
+            let mut scores = self.world.zone_scores.iter().collect::<Vec<_>>();
+            scores.sort_by(|a, b| b.1.cmp(a.1));
+
+            for (index, (owner, points)) in scores.iter().take(4).enumerate() {
+                let text = format!("{} {}", self.owner_label(owner), points);
+                let text_size = text::measure_text_ex(poppins_black, &text, 24.0, 1.0);
+
+                d.draw_text_ex(
+                    poppins_black,
+                    &text,
+                    RVector2::new(
+                        WINDOW_CENTER_X - text_size.x / 2.0,
+                        WINDOW_PADDING as f32 + index as f32 * 26.0,
+                    ),
+                    24.0,
+                    1.0,
+                    self.owner_color(owner),
+                );
+            }
+        }
+
+        // kill feed, newest on top
+        for (index, (event, posted)) in self.kill_feed.iter().rev().enumerate() {
+            let remaining = HUD_KILL_FEED_DURATION - posted.elapsed().as_secs_f32();
+            let alpha = remaining.clamp(0.0, 1.0);
+
+            let victim = self.player_name(event.victim);
+            let mut text = match (event.killer, event.weapon) {
+                (Some(killer), Some(weapon)) => format!(
+                    "{} [{}] {}",
+                    self.player_name(killer),
+                    weapon.weapon_instance().stats.name(),
+                    victim
+                ),
+                (Some(killer), None) => format!("{} killed {}", self.player_name(killer), victim),
+                (None, _) => format!("{} died", victim),
+            };
+
+            if !event.assisters.is_empty() {
+                let assisters = event
+                    .assisters
+                    .iter()
+                    .map(|id| self.player_name(*id))
+                    .collect::<Vec<_>>();
+                text = format!("{} (+{})", text, assisters.join(", "));
+            }
+
+            let involved = event.killer == Some(self.id) || event.victim == self.id;
+            let text_size = text::measure_text_ex(poppins, &text, 18.0, 1.0);
+            let (x, y) = (
+                WINDOW_TOP_RIGHT_X as f32 - text_size.x,
+                WINDOW_TOP_RIGHT_Y as f32 + index as f32 * 24.0,
+            );
+
+            d.draw_rectangle_rec(
+                Rectangle::new(x - 6.0, y - 2.0, text_size.x + 12.0, text_size.y + 4.0),
+                if involved {
+                    Color::new(230, 72, 72, 120)
+                } else {
+                    Color::new(0, 0, 0, 100)
+                }
+                .fade(alpha * 0.5),
+            );
+            d.draw_text_ex(
+                poppins,
+                &text,
+                RVector2::new(x, y),
+                18.0,
+                1.0,
+                Color::WHITE.fade(alpha),
+            );
+        }
+
+        // hit marker around the crosshair
+        if let Some((hit, lethal)) = self.hit_marker {
+            let elapsed = hit.elapsed().as_secs_f32();
+
+            if elapsed < HUD_HIT_MARKER_DURATION {
+                let alpha = 1.0 - elapsed / HUD_HIT_MARKER_DURATION;
+                let color = if lethal { Color::RED } else { Color::WHITE }.fade(alpha);
+                let mouse = d.get_mouse_position();
+                let (inner, outer) = (4.0, 10.0);
+
+                for (dx, dy) in [(1.0, 1.0), (1.0, -1.0), (-1.0, 1.0), (-1.0, -1.0)] {
+                    d.draw_line_ex(
+                        RVector2::new(mouse.x + dx * inner, mouse.y + dy * inner),
+                        RVector2::new(mouse.x + dx * outer, mouse.y + dy * outer),
+                        2.0,
+                        color,
+                    );
+                }
+            }
+        }
+
         if local_player.ready && local_player.is_alive() {
             // health bar
             d.draw_rectangle_rounded(
@@ -835,5 +1548,136 @@ impl UserInterfaceHandle for Game {
                 );
             };
         }
+
+        self.chat.display(d);
+
+        if let Some(vote) = &mut self.vote {
+            vote.display(d);
+        }
+
+        if !self.chat.is_open() && d.is_key_down(KeyboardKey::KEY_TAB) {
+            self.display_scoreboard(d);
+        }
+    }
+}
+
+impl Game {
+    /// shown once the connection is gone, with the server's reason when it gave one
+    fn display_disconnected(&self, d: &mut RaylibDrawHandle, reason: Option<DisconnectReason>) {
+        let assets = self.assets.borrow();
+        let poppins = assets.fonts.get(&LFont::FNT_POPPINS).unwrap();
+        let poppins_black = assets.fonts.get(&LFont::FNT_POPPINS_BLACK).unwrap();
+
+        let reason = self
+            .disconnect_reason
+            .clone()
+            .or(reason.map(|reason| reason.to_string()))
+            .unwrap_or(String::from("connection lost"));
+
+        for (font, text, font_size, y) in [
+            (poppins_black, "DISCONNECTED", 48.0, WINDOW_CENTER_Y - 48.0),
+            (poppins, reason.as_str(), 22.0, WINDOW_CENTER_Y + 8.0),
+        ] {
+            let text_size = text::measure_text_ex(font, text, font_size, 1.0);
+            d.draw_text_ex(
+                font,
+                text,
+                RVector2::new(WINDOW_CENTER_X - text_size.x / 2.0, y),
+                font_size,
+                1.0,
+                Color::WHITE,
+            );
+        }
+    }
+
+    /// Tab-held overlay listing every player
+    fn display_scoreboard(&self, d: &mut RaylibDrawHandle) {
+        let assets = self.assets.borrow();
+        let poppins = assets.fonts.get(&LFont::FNT_POPPINS).unwrap();
+        let poppins_black = assets.fonts.get(&LFont::FNT_POPPINS_BLACK).unwrap();
+
+        let (width, row_height, font_size) = (600.0, 28.0, 20.0);
+        let height = row_height * (self.world.scoreboard.len() + 1) as f32 + WINDOW_PADDING as f32;
+        let (x, y) = (
+            WINDOW_CENTER_X - width / 2.0,
+            WINDOW_CENTER_Y - height / 2.0,
+        );
+        let columns = [
+            ("K", 300.0),
+            ("D", 350.0),
+            ("A", 400.0),
+            ("SCORE", 450.0),
+            ("PING", 530.0),
+        ];
+
+        d.draw_rectangle_rounded(
+            Rectangle::new(x, y, width, height),
+            0.05,
+            0,
+            Color::new(0, 0, 0, 180),
+        );
+
+        let padding = WINDOW_PADDING as f32 / 2.0;
+        d.draw_text_ex(
+            poppins_black,
+            "PLAYER",
+            RVector2::new(x + padding, y + padding),
+            font_size,
+            1.0,
+            Color::LIGHTGRAY,
+        );
+        for (title, offset) in columns {
+            d.draw_text_ex(
+                poppins_black,
+                title,
+                RVector2::new(x + offset, y + padding),
+                font_size,
+                1.0,
+                Color::LIGHTGRAY,
+            );
+        }
+
+        for (index, entry) in self.world.scoreboard.iter().enumerate() {
+            let row_y = y + padding + row_height * (index + 1) as f32;
+            let color = match entry.team {
+                Some(Team::RED) => TEAM_RED_COLOR,
+                Some(Team::BLUE) => TEAM_BLUE_COLOR,
+                None => Color::WHITE,
+            };
+
+            if entry.id == self.id {
+                d.draw_rectangle_rec(
+                    Rectangle::new(x, row_y - 2.0, width, row_height),
+                    Color::new(255, 255, 255, 30),
+                );
+            }
+
+            let values = [
+                entry.stats.kills.to_string(),
+                entry.stats.deaths.to_string(),
+                entry.stats.assists.to_string(),
+                entry.stats.score.to_string(),
+                format!("{}ms", entry.ping),
+            ];
+
+            d.draw_text_ex(
+                poppins,
+                &entry.name,
+                RVector2::new(x + padding, row_y),
+                font_size,
+                1.0,
+                color,
+            );
+            for (value, (_, offset)) in values.iter().zip(columns) {
+                d.draw_text_ex(
+                    poppins,
+                    value,
+                    RVector2::new(x + offset, row_y),
+                    font_size,
+                    1.0,
+                    Color::WHITE,
+                );
+            }
+        }
     }
 }
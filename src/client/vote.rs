@@ -0,0 +1,112 @@
+use raylib::prelude::*;
+
+use lib::prelude::*;
+
+static VOTE_KEYS: [KeyboardKey; 9] = [
+    KeyboardKey::KEY_ONE,
+    KeyboardKey::KEY_TWO,
+    KeyboardKey::KEY_THREE,
+    KeyboardKey::KEY_FOUR,
+    KeyboardKey::KEY_FIVE,
+    KeyboardKey::KEY_SIX,
+    KeyboardKey::KEY_SEVEN,
+    KeyboardKey::KEY_EIGHT,
+    KeyboardKey::KEY_NINE,
+];
+
+/// end of match poll for the next map, number keys pick a candidate
+pub struct MapVote {
+    candidates: Vec<String>,
+    tally: Vec<u32>,
+    closes: Instant,
+    choice: Option<usize>,
+}
+
+impl MapVote {
+    pub fn new(candidates: Vec<String>, remaining: f32) -> Self {
+        Self {
+            tally: vec![0; candidates.len()],
+            candidates,
+            closes: Instant::now() + Duration::from_secs_f32(remaining),
+            choice: None,
+        }
+    }
+
+    pub fn set_tally(&mut self, tally: Vec<u32>) {
+        if tally.len() == self.candidates.len() {
+            self.tally = tally;
+        }
+    }
+}
+
+impl NetUpdateHandle for MapVote {
+    type Network = GameNetwork;
+
+    fn net_update(&mut self, handle: &RaylibHandle, network: &mut Self::Network) {
+        for (index, key) in VOTE_KEYS.iter().enumerate().take(self.candidates.len()) {
+            if handle.is_key_pressed(*key) && self.choice != Some(index) {
+                self.choice = Some(index);
+                network.client.send_message(
+                    DefaultChannel::ReliableUnordered,
+                    GameNetworkPacket::NET_MAP_VOTE(index as u8)
+                        .serialized()
+                        .unwrap(),
+                );
+            }
+        }
+    }
+}
+
+impl UserInterfaceHandle for MapVote {
+    fn display(&mut self, d: &mut RaylibDrawHandle) {
+        let (width, row_height, font_size) = (360, 30, 20);
+        let height = row_height * (self.candidates.len() as i32 + 1) + WINDOW_PADDING;
+        let (x, y) = (WINDOW_CENTER_X as i32 - width / 2, WINDOW_PADDING * 3);
+        let total = self.tally.iter().sum::<u32>().max(1);
+
+        d.draw_rectangle(x, y, width, height, Color::new(0, 0, 0, 180));
+
+        let remaining = self.closes.saturating_duration_since(Instant::now());
+        d.draw_text(
+            &format!("VOTE FOR THE NEXT MAP ({}s)", remaining.as_secs()),
+            x + WINDOW_PADDING / 2,
+            y + WINDOW_PADDING / 2,
+            font_size,
+            Color::WHITE,
+        );
+
+        for (index, (name, votes)) in self.candidates.iter().zip(&self.tally).enumerate() {
+            let row = y + WINDOW_PADDING / 2 + row_height * (index as i32 + 1);
+            let bar = (width - WINDOW_PADDING) * *votes as i32 / total as i32;
+            let color = if self.choice == Some(index) {
+                Color::GOLD
+            } else {
+                Color::LIGHTGRAY
+            };
+
+            d.draw_rectangle(
+                x + WINDOW_PADDING / 2,
+                row,
+                bar,
+                row_height - 6,
+                color.fade(0.25),
+            );
+            d.draw_text(
+                &format!("{}. {}", index + 1, name.trim_end_matches(".map")),
+                x + WINDOW_PADDING / 2 + 6,
+                row + 2,
+                font_size,
+                color,
+            );
+
+            let count = votes.to_string();
+            d.draw_text(
+                &count,
+                x + width - WINDOW_PADDING / 2 - 6 - text::measure_text(&count, font_size),
+                row + 2,
+                font_size,
+                color,
+            );
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use raylib::color::Color;
 
-use crate::types::{Cash, Health};
+use crate::types::{Cash, Damage, Health};
 
 pub static INITIAL_PAYLOAD_SIZE: usize = 255;
 pub static INTIIAL_PLAYER_CASH: Cash = 200;
@@ -10,6 +10,32 @@ pub static PLAYER_DEATH_PENALTY: Cash = 700;
 pub static PLAYER_HEATLH_COST: Cash = 450;
 pub static PLAYER_AMMO_COST: Cash = 320;
 
+pub static SCORE_KILL: i32 = 100;
+pub static SCORE_ASSIST: i32 = 50;
+
+pub static ZONE_CAPTURE_TIME: f32 = 5.0; // seconds of uncontested presence to take a zone over
+pub static ZONE_POINTS_PER_SECOND: f32 = 1.0;
+
+pub static ROUND_INTERMISSION_TIME: f32 = 5.0;
+pub static MAP_VOTE_CANDIDATES: usize = 3;
+pub static MAP_VOTE_TIME: f32 = 15.0;
+pub static MAP_MAX_SIZE: i32 = 4096; // tiles along either side
+/// maps larger than this are sent to clients in several pieces
+pub static MAP_CHUNK_SIZE: usize = 16 * 1024;
+pub static MAP_CHUNKS_PER_TICK: usize = 4; // sent to each downloading client
+pub static SAFE_ZONE_GRACE_TIME: f32 = 15.0; // seconds before the safe zone starts closing in
+pub static SAFE_ZONE_SHRINK_TIME: f32 = 90.0;
+pub static SAFE_ZONE_MIN_RADIUS: f32 = WORLD_TILE_SIZE * 2.0;
+pub static SAFE_ZONE_DAMAGE: Damage = 5; // per second spent outside
+
+pub static DOOR_USE_RANGE: f32 = WORLD_TILE_SIZE * 1.5; // from the player's center to the door's
+pub static TELEPORTER_COOLDOWN: f32 = 1.0; // seconds before the same player can teleport again
+pub static PAD_FLIGHT_TIME: f32 = 0.6;
+
+pub static SPAWN_PROTECTION_TIME: f32 = 3.0; // seconds of invulnerability, firing ends it early
+pub static SPAWN_SAFE_DISTANCE: f32 = WORLD_TILE_SIZE * 10.0; // enemies further away don't matter
+pub static SPAWN_CHOICES: usize = 3; // best scored spawns one is picked from at random
+
 pub static WORLD_TILE_SIZE: f32 = 70.0;
 pub static ENTITY_PLAYER_SIZE: f32 = WORLD_TILE_SIZE * 0.8;
 pub static ENTITY_WEAPON_SIZE: f32 = ENTITY_PLAYER_SIZE * 0.0018;
@@ -36,7 +62,23 @@ pub static WINDOW_BOTTOM_LEFT_Y: i32 = WINDOW_HEIGHT - WINDOW_PADDING;
 pub static WINDOW_CENTER_X: f32 = WINDOW_WIDTH as f32 / 2.0;
 pub static WINDOW_CENTER_Y: f32 = WINDOW_HEIGHT as f32 / 2.0;
 
+pub static OVERHEAD_REVEAL_RADIUS: f32 = WORLD_TILE_SIZE * 1.5; // around the player, roofs fade out
+pub static OVERHEAD_REVEAL_ALPHA: f32 = 0.3;
+
+pub static HUD_KILL_FEED_SIZE: usize = 5;
+pub static HUD_KILL_FEED_DURATION: f32 = 5.0;
+pub static HUD_HIT_MARKER_DURATION: f32 = 0.25;
+
+pub static CHAT_MAX_LENGTH: usize = 120;
+pub static CHAT_HISTORY_SIZE: usize = 50;
+pub static CHAT_RATE_LIMIT: usize = 4; // messages allowed per window
+pub static CHAT_RATE_WINDOW: f32 = 5.0;
+pub static CHAT_FADE_TIME: f32 = 10.0;
+
 pub static PLAYER_COLOR: Color = Color::new(246, 251, 255, 255);
+pub static TEAM_RED_COLOR: Color = Color::new(230, 72, 72, 255);
+pub static TEAM_BLUE_COLOR: Color = Color::new(72, 136, 230, 255);
+pub static SPAWN_PROTECTION_COLOR: Color = Color::new(130, 230, 255, 255);
 pub static PLAYER_CAMERA_OFFSET: f32 = 20.0;
 pub static PLAYER_INIT_VELOCITY_X: f32 = 450.0;
 pub static PLAYER_INIT_VELOCITY_Y: f32 = 450.0;
@@ -48,7 +48,20 @@ asset!(
         CONS_OLIVE_OIL,
         TILE_WALL_SIDE,
         TILE_WALL_TOP,
+        TILE_WALL_SIDE_EDGES,
+        TILE_WALL_TOP_EDGES,
         TILE_GROUND,
+        TILE_COVER,
+        TILE_WATER,
+        TILE_HAZARD,
+        TILE_CRATE,
+        TILE_BRICK,
+        TILE_CRACKS,
+        TILE_DOOR,
+        TILE_DOOR_OPEN,
+        TILE_ROOF,
+        TILE_FOLIAGE,
+        TILE_DEBRIS,
         UI_LOADING,
         UI_LOGO,
         UI_AKA_69,
@@ -111,6 +124,8 @@ impl GameAssetsLoader {
                             return Err(std::io::Error::new(std::io::ErrorKind::NotFound, error));
                         }
                     }
+                } else {
+                    log::warn!("missing texture {:?}", path);
                 }
             }
 
@@ -131,6 +146,8 @@ impl GameAssetsLoader {
                             return Err(std::io::Error::new(std::io::ErrorKind::NotFound, error));
                         }
                     }
+                } else {
+                    log::warn!("missing font {:?}", path);
                 }
             }
 
@@ -3,8 +3,11 @@ use raylib::prelude::*;
 use nalgebra::Vector2;
 use renet::ClientId;
 use std::rc::Rc;
+use std::time::Instant;
 
-use super::WeaponVariant;
+use super::{render_spawn_protection, WeaponVariant};
+
+use crate::modes::Team;
 
 use crate::configs::*;
 use crate::core::*;
@@ -18,6 +21,9 @@ pub struct Enemy {
     pub origin: Vector2<f32>,
     pub health: Health,
     pub weapon: Option<WeaponVariant>,
+    pub team: Option<Team>,
+    /// spawn protection as announced by the server
+    pub protected_until: Option<Instant>,
     assets: SharedAssets<GameAssets>,
 }
 
@@ -37,18 +43,38 @@ impl Enemy {
             origin: Default::default(),
             health: hp,
             weapon: None,
+            team: None,
+            protected_until: None,
             assets,
         }
     }
 }
 
+impl Enemy {
+    pub fn take_damage(&mut self, damage: Damage) {
+        self.health -= damage as Health;
+        self.health = nalgebra::clamp(self.health, 0, ENTITY_PLAYER_MAX_HEALTH);
+    }
+}
+
 impl RenderHandle for Enemy {
     fn render(&mut self, d: &mut RaylibMode2D<RaylibDrawHandle>)
     where
         Self: AssetsHandle,
     {
+        if self.health <= 0 {
+            return;
+        }
+
         let assets = Rc::clone(&self.assets);
-        d.draw_rectangle_pro(self.rectangle, RVector2::zero(), 0.0, Color::WHITE);
+        let color = match self.team {
+            Some(Team::RED) => TEAM_RED_COLOR,
+            Some(Team::BLUE) => TEAM_BLUE_COLOR,
+            None => Color::WHITE,
+        };
+
+        d.draw_rectangle_pro(self.rectangle, RVector2::zero(), 0.0, color);
+        render_spawn_protection(d, &self.rectangle, self.protected_until);
         if let Some(wpn) = self.weapon {
             wpn.weapon_instance()
                 .render_weapon(d, &self.rectangle, self.orientation, assets);
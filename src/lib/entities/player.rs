@@ -22,8 +22,15 @@ pub struct Player {
     pub velocity: Vector2<f32>,
     pub direction: Vector2<f32>,
     pub health: Health,
+    pub team: Option<Team>,
     pub ready: bool,
+    /// death was reported to the server, waiting for a respawn
+    pub dead: bool,
+    /// keyboard and mouse belong to the chat box
+    pub chatting: bool,
     pub reloading: bool,
+    /// spawn protection as announced by the server
+    pub protected_until: Option<Instant>,
     timers: Timer<Timers>,
     assets: SharedAssets<GameAssets>,
 }
@@ -57,23 +64,25 @@ impl Player {
                 target: RVector2::new(rectangle.x, rectangle.y),
             },
             ready: false,
+            dead: false,
+            chatting: false,
             reloading: false,
+            protected_until: None,
             velocity: Vector2::new(
                 configs::PLAYER_INIT_VELOCITY_X,
                 configs::PLAYER_INIT_VELOCITY_Y,
             ),
             direction: Vector2::new(1.0, 1.0),
             health: ENTITY_PLAYER_MAX_HEALTH,
+            team: None,
             timers: Timer::default(),
             assets,
         }
     }
 
     pub fn move_to(&mut self, position: Vector2<f32>) -> Vector2<f32> {
-        self.grid = Point2::new(
-            (position.x / WORLD_TILE_SIZE).round() as i32,
-            (position.y / WORLD_TILE_SIZE).round() as i32,
-        );
+        let (gx, gy) = TileGrid::world_to_grid(position.x, position.y);
+        self.grid = Point2::new(gx, gy);
 
         self.rectangle.x = position.x;
         self.rectangle.y = position.y;
@@ -84,9 +93,27 @@ impl Player {
         Vector2::new(self.rectangle.x, self.rectangle.y)
     }
 
-    pub fn on_move(&mut self, handle: &RaylibHandle) -> Vector2<f32> {
+    /// centers the camera on the player without showing anything past the map edges
+    pub fn follow(&mut self, (width, height): (f32, f32)) {
+        let zoom = self.camera.zoom;
+        self.camera.target.x = follow_axis(
+            self.rectangle.x,
+            self.camera.offset.x / zoom,
+            configs::WINDOW_WIDTH as f32 / zoom,
+            width,
+        );
+        self.camera.target.y = follow_axis(
+            self.rectangle.y,
+            self.camera.offset.y / zoom,
+            configs::WINDOW_HEIGHT as f32 / zoom,
+            height,
+        );
+    }
+
+    /// `speed` scales the velocity, for ground that slows players down
+    pub fn on_move(&mut self, handle: &RaylibHandle, speed: f32) -> Vector2<f32> {
         let mut new_position = Vector2::new(self.rectangle.x, self.rectangle.y);
-        let velocity = self.velocity.component_mul(&self.direction);
+        let velocity = self.velocity.component_mul(&self.direction) * speed;
         let dt = handle.get_frame_time();
         new_position.x += velocity.x * dt;
         new_position.y += velocity.y * dt;
@@ -97,7 +124,7 @@ impl Player {
     pub fn on_shoot(&mut self, handle: &RaylibHandle, network: &mut GameNetwork) {
         let assets = self.assets.borrow();
 
-        if handle.is_mouse_button_down(MouseButton::MOUSE_LEFT_BUTTON) {
+        if !self.chatting && handle.is_mouse_button_down(MouseButton::MOUSE_LEFT_BUTTON) {
             if let Some(wpn) = self.inventory.selected_weapon_mut() {
                 if !self.reloading
                     && self.timers.after(
@@ -132,6 +159,7 @@ impl Player {
                                     velocity: (p.velocity.x, p.velocity.y),
                                     orientation: p.orientation,
                                     shooter: network.transport.client_id().raw(),
+                                    weapon: wpn.variant,
                                     damage: *wpn.stats.damage(),
                                 })
                                 .serialized()
@@ -147,6 +175,11 @@ impl Player {
     pub fn is_alive(&self) -> bool {
         self.health > 0
     }
+
+    pub fn take_damage(&mut self, damage: Damage) {
+        self.health -= damage as Health;
+        self.health = nalgebra::clamp(self.health, 0, ENTITY_PLAYER_MAX_HEALTH);
+    }
 }
 
 impl UpdateHandle for Player {
@@ -157,6 +190,10 @@ impl UpdateHandle for Player {
 
         self.direction = Vector2::new(0.0, 0.0);
 
+        if self.chatting {
+            return;
+        }
+
         if handle.is_key_down(KeyboardKey::KEY_W) {
             self.direction.y = -1.0
         }
@@ -194,13 +231,18 @@ impl NetUpdateHandle for Player {
             return;
         }
 
+        let typing = self.chatting;
+
         if self.health <= 0 {
-            network.client.send_message(
-                DefaultChannel::ReliableUnordered,
-                GameNetworkPacket::NET_PLAYER_DIED(network.transport.client_id().raw())
-                    .serialized()
-                    .unwrap(),
-            );
+            if !self.dead {
+                self.dead = true;
+                network.client.send_message(
+                    DefaultChannel::ReliableUnordered,
+                    GameNetworkPacket::NET_PLAYER_DIED(network.transport.client_id().raw())
+                        .serialized()
+                        .unwrap(),
+                );
+            }
 
             return;
         }
@@ -216,7 +258,7 @@ impl NetUpdateHandle for Player {
         );
 
         if let Some(wpn) = self.inventory.selected_weapon_mut() {
-            if handle.is_key_pressed(KeyboardKey::KEY_R) {
+            if !typing && handle.is_key_pressed(KeyboardKey::KEY_R) {
                 self.reloading = true;
                 self.timers.add(Timers::PlayerReloading);
             }
@@ -230,7 +272,8 @@ impl NetUpdateHandle for Player {
                 wpn.reload();
             }
 
-            if handle.is_key_pressed(KeyboardKey::KEY_E)
+            if !typing
+                && handle.is_key_pressed(KeyboardKey::KEY_E)
                 && wpn.curr_total_ammo < wpn.stats.total_ammo
                 && self.inventory.cash >= PLAYER_AMMO_COST
             {
@@ -241,7 +284,15 @@ impl NetUpdateHandle for Player {
             }
         }
 
-        if handle.is_key_pressed(KeyboardKey::KEY_Q)
+        if !typing && handle.is_key_pressed(KeyboardKey::KEY_F) {
+            network.client.send_message(
+                DefaultChannel::ReliableUnordered,
+                GameNetworkPacket::NET_PLAYER_USE().serialized().unwrap(),
+            );
+        }
+
+        if !typing
+            && handle.is_key_pressed(KeyboardKey::KEY_Q)
             && self.health < ENTITY_PLAYER_MAX_HEALTH
             && self.inventory.cash >= PLAYER_HEATLH_COST
         {
@@ -256,7 +307,7 @@ impl NetUpdateHandle for Player {
         for wpn_variant in WeaponVariant::VARIANTS {
             let wpn = Weapon::new(*wpn_variant);
 
-            if handle.is_key_pressed(wpn.equip_key()) && !self.reloading {
+            if !typing && handle.is_key_pressed(wpn.equip_key()) && !self.reloading {
                 if !self.inventory.has(wpn_variant) {
                     network.client.send_message(
                         DefaultChannel::ReliableUnordered,
@@ -288,6 +339,7 @@ impl RenderHandle for Player {
         }
 
         d.draw_rectangle_pro(self.rectangle, RVector2::zero(), 0.0, configs::PLAYER_COLOR);
+        render_spawn_protection(d, &self.rectangle, self.protected_until);
 
         self.inventory
             .render_weapon(d, &self.rectangle, self.orientation);
@@ -344,3 +396,56 @@ impl AssetsHandle for Player {
         Rc::clone(&self.assets)
     }
 }
+
+/// camera target along one axis, `before` is how much of the view lies before the target
+fn follow_axis(target: f32, before: f32, view: f32, size: f32) -> f32 {
+    if size <= 0.0 {
+        target
+    } else if size <= view {
+        // maps smaller than the window stay centered
+        (size - view) / 2.0 + before
+    } else {
+        target.clamp(before, size - view + before)
+    }
+}
+
+/// pulsing shield around a spawn protected player, fading out as the protection runs out
+pub fn render_spawn_protection(
+    d: &mut RaylibMode2D<RaylibDrawHandle>,
+    rectangle: &Rectangle,
+    protected_until: Option<Instant>,
+) {
+    let Some(left) = protected_until
+        .map(|until| {
+            until
+                .saturating_duration_since(Instant::now())
+                .as_secs_f32()
+        })
+        .filter(|left| *left > 0.0)
+    else {
+        return;
+    };
+
+    let center = RVector2::new(
+        rectangle.x + rectangle.width / 2.0,
+        rectangle.y + rectangle.height / 2.0,
+    );
+    let pulse = ((d.get_time() * 6.0).sin() as f32 + 1.0) / 2.0;
+    let fade = (left / configs::SPAWN_PROTECTION_TIME).min(1.0);
+    let radius = rectangle.width * (0.75 + pulse * 0.1);
+
+    d.draw_circle_v(
+        center,
+        radius,
+        configs::SPAWN_PROTECTION_COLOR.fade(0.15 * fade),
+    );
+    d.draw_ring(
+        center,
+        radius - 2.0,
+        radius,
+        0.0,
+        360.0,
+        32,
+        configs::SPAWN_PROTECTION_COLOR.fade((0.5 + pulse * 0.4) * fade),
+    );
+}
@@ -25,10 +25,8 @@ impl Projectile {
             speed as f32 * orientation.sin(),
         );
 
-        let grid = Point2::new(
-            (position.0.round() / WORLD_TILE_SIZE) as i32,
-            (position.1.round() / WORLD_TILE_SIZE) as i32,
-        );
+        let (gx, gy) = TileGrid::world_to_grid(position.0, position.1);
+        let grid = Point2::new(gx, gy);
 
         Self {
             id,
@@ -1,16 +1,383 @@
 use nalgebra::{Point2, Vector2};
 use raylib::math::Rectangle;
 use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
 
 use crate::prelude::*;
+use crate::types::*;
 
-pub type Tiles = std::collections::HashMap<(i32, i32), TileVariant>;
+/// how a tile kind plays, the server and the client read the same table
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileProperties {
+    pub blocks_movement: bool,
+    pub blocks_projectiles: bool,
+    /// multiplier on the speed of players walking over it
+    pub speed: f32,
+    /// damage per second dealt to players standing on it
+    pub damage: Damage,
+    /// damage it takes before breaking into ground, indestructible without
+    pub health: Option<Health>,
+}
+
+pub static TILE_PROPS_GROUND: TileProperties = TileProperties {
+    blocks_movement: false,
+    blocks_projectiles: false,
+    speed: 1.0,
+    damage: 0,
+    health: None,
+};
+pub static TILE_PROPS_WALL: TileProperties = TileProperties {
+    blocks_movement: true,
+    blocks_projectiles: true,
+    ..TILE_PROPS_GROUND
+};
+/// low cover, shot over but not walked over
+pub static TILE_PROPS_COVER: TileProperties = TileProperties {
+    blocks_movement: true,
+    ..TILE_PROPS_GROUND
+};
+pub static TILE_PROPS_WATER: TileProperties = TileProperties {
+    speed: 0.5,
+    ..TILE_PROPS_GROUND
+};
+pub static TILE_PROPS_HAZARD: TileProperties = TileProperties {
+    damage: 10,
+    ..TILE_PROPS_GROUND
+};
+pub static TILE_PROPS_CRATE: TileProperties = TileProperties {
+    health: Some(100),
+    ..TILE_PROPS_WALL
+};
+/// a wall that can be shot through given enough rounds
+pub static TILE_PROPS_BRICK: TileProperties = TileProperties {
+    health: Some(120),
+    ..TILE_PROPS_WALL
+};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Display, EnumString)]
 pub enum TileVariant {
     WALL_SIDE,
     WALL_TOP,
     GROUND,
+    /// wall whose side or top is picked from its neighbours when the map is loaded
+    WALL,
+    COVER,
+    WATER,
+    HAZARD,
+    CRATE,
+    BRICK,
+    /// closed door, placed by `DOOR` map entities rather than the grid
+    DOOR,
+    DOOR_OPEN,
+    /// drawn over players from the overhead layer, like foliage
+    ROOF,
+    FOLIAGE,
+    /// rubble and stains for the decoration layer
+    DEBRIS,
+}
+
+impl TileVariant {
+    pub fn properties(self) -> &'static TileProperties {
+        match self {
+            TileVariant::WALL_SIDE | TileVariant::WALL_TOP | TileVariant::WALL => &TILE_PROPS_WALL,
+            TileVariant::GROUND => &TILE_PROPS_GROUND,
+            TileVariant::COVER => &TILE_PROPS_COVER,
+            TileVariant::WATER => &TILE_PROPS_WATER,
+            TileVariant::HAZARD => &TILE_PROPS_HAZARD,
+            TileVariant::CRATE => &TILE_PROPS_CRATE,
+            TileVariant::BRICK => &TILE_PROPS_BRICK,
+            TileVariant::DOOR => &TILE_PROPS_WALL,
+            TileVariant::DOOR_OPEN => &TILE_PROPS_GROUND,
+            TileVariant::ROOF | TileVariant::FOLIAGE | TileVariant::DEBRIS => &TILE_PROPS_GROUND,
+        }
+    }
+
+    /// blocks players
+    pub fn is_solid(self) -> bool {
+        self.properties().blocks_movement
+    }
+
+    pub fn blocks_projectiles(self) -> bool {
+        self.properties().blocks_projectiles
+    }
+
+    /// one of the wall variants, walls are the only tiles that are auto-tiled
+    pub fn is_wall(self) -> bool {
+        matches!(
+            self,
+            TileVariant::WALL_SIDE | TileVariant::WALL_TOP | TileVariant::WALL
+        )
+    }
+
+    /// wire id of the variant, 0 is kept for empty cells
+    pub fn id(self) -> u8 {
+        match self {
+            TileVariant::WALL_SIDE => 1,
+            TileVariant::WALL_TOP => 2,
+            TileVariant::GROUND => 3,
+            TileVariant::WALL => 4,
+            TileVariant::COVER => 5,
+            TileVariant::WATER => 6,
+            TileVariant::HAZARD => 7,
+            TileVariant::CRATE => 8,
+            TileVariant::BRICK => 9,
+            TileVariant::DOOR => 10,
+            TileVariant::DOOR_OPEN => 11,
+            TileVariant::ROOF => 12,
+            TileVariant::FOLIAGE => 13,
+            TileVariant::DEBRIS => 14,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(TileVariant::WALL_SIDE),
+            2 => Some(TileVariant::WALL_TOP),
+            3 => Some(TileVariant::GROUND),
+            4 => Some(TileVariant::WALL),
+            5 => Some(TileVariant::COVER),
+            6 => Some(TileVariant::WATER),
+            7 => Some(TileVariant::HAZARD),
+            8 => Some(TileVariant::CRATE),
+            9 => Some(TileVariant::BRICK),
+            10 => Some(TileVariant::DOOR),
+            11 => Some(TileVariant::DOOR_OPEN),
+            12 => Some(TileVariant::ROOF),
+            13 => Some(TileVariant::FOLIAGE),
+            14 => Some(TileVariant::DEBRIS),
+            _ => None,
+        }
+    }
+}
+
+/// dense row-major tile storage, cells left out of ragged maps stay empty
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(into = "PackedTileGrid", try_from = "PackedTileGrid")]
+pub struct TileGrid {
+    width: i32,
+    height: i32,
+    cells: Vec<Option<TileVariant>>,
+}
+
+/// one byte per cell on the wire
+#[derive(Deserialize, Serialize)]
+struct PackedTileGrid {
+    width: i32,
+    height: i32,
+    cells: Vec<u8>,
+}
+
+impl From<TileGrid> for PackedTileGrid {
+    fn from(grid: TileGrid) -> Self {
+        Self {
+            width: grid.width,
+            height: grid.height,
+            cells: grid
+                .cells
+                .into_iter()
+                .map(|cell| cell.map_or(0, TileVariant::id))
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<PackedTileGrid> for TileGrid {
+    type Error = String;
+
+    fn try_from(packed: PackedTileGrid) -> Result<Self, Self::Error> {
+        // sizes come off the network or disk, a product that overflows is as bad as a short grid
+        let fits = packed.width >= 0
+            && packed.height >= 0
+            && packed
+                .width
+                .checked_mul(packed.height)
+                .is_some_and(|size| packed.cells.len() == size as usize);
+        if !fits {
+            return Err(format!(
+                "{} cells do not fill a {}x{} grid",
+                packed.cells.len(),
+                packed.width,
+                packed.height
+            ));
+        }
+
+        let cells = packed
+            .cells
+            .into_iter()
+            .map(|id| match id {
+                0 => Ok(None),
+                id => TileVariant::from_id(id)
+                    .map(Some)
+                    .ok_or_else(|| format!("unknown tile id {}", id)),
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            width: packed.width,
+            height: packed.height,
+            cells,
+        })
+    }
+}
+
+impl TileGrid {
+    /// an empty grid, refused when a side is longer than `MAP_MAX_SIZE`
+    pub fn new(width: i32, height: i32) -> Result<Self, String> {
+        let (width, height) = (width.max(0), height.max(0));
+        let cells = width
+            .checked_mul(height)
+            .filter(|_| width <= MAP_MAX_SIZE && height <= MAP_MAX_SIZE)
+            .ok_or_else(|| {
+                format!(
+                    "a {}x{} map is larger than the maximum of {}x{}",
+                    width, height, MAP_MAX_SIZE, MAP_MAX_SIZE
+                )
+            })?;
+
+        Ok(Self {
+            width,
+            height,
+            cells: vec![None; cells as usize],
+        })
+    }
+
+    pub fn filled(width: i32, height: i32, variant: TileVariant) -> Result<Self, String> {
+        let mut grid = Self::new(width, height)?;
+        grid.cells.fill(Some(variant));
+        Ok(grid)
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    /// (width, height) in tiles
+    pub fn size(&self) -> (i32, i32) {
+        (self.width, self.height)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.iter().all(Option::is_none)
+    }
+
+    pub fn contains(&self, (x, y): (i32, i32)) -> bool {
+        x >= 0 && y >= 0 && x < self.width && y < self.height
+    }
+
+    fn index(&self, position: (i32, i32)) -> Option<usize> {
+        self.contains(position)
+            .then(|| (position.1 * self.width + position.0) as usize)
+    }
+
+    pub fn get(&self, position: (i32, i32)) -> Option<TileVariant> {
+        self.index(position).and_then(|index| self.cells[index])
+    }
+
+    /// out of bounds writes are ignored
+    pub fn set(&mut self, position: (i32, i32), variant: Option<TileVariant>) {
+        if let Some(index) = self.index(position) {
+            self.cells[index] = variant;
+        }
+    }
+
+    /// blocks movement, empty cells and cells off the grid don't
+    pub fn is_solid(&self, position: (i32, i32)) -> bool {
+        self.get(position).is_some_and(TileVariant::is_solid)
+    }
+
+    /// every non-empty cell in row-major order
+    pub fn iter(&self) -> impl Iterator<Item = ((i32, i32), TileVariant)> + '_ {
+        let width = self.width.max(1);
+        self.cells
+            .iter()
+            .enumerate()
+            .filter_map(move |(index, cell)| {
+                cell.map(|variant| ((index as i32 % width, index as i32 / width), variant))
+            })
+    }
+
+    /// non-empty cells of the rectangle (x0, y0)..=(x1, y1), clipped to the grid
+    pub fn region(
+        &self,
+        (x0, y0): (i32, i32),
+        (x1, y1): (i32, i32),
+    ) -> impl Iterator<Item = ((i32, i32), TileVariant)> + '_ {
+        let (x0, x1) = (x0.max(0), x1.min(self.width - 1));
+        let (y0, y1) = (y0.max(0), y1.min(self.height - 1));
+
+        (y0..=y1)
+            .flat_map(move |y| (x0..=x1).map(move |x| (x, y)))
+            .filter_map(|position| self.get(position).map(|variant| (position, variant)))
+    }
+
+    /// the cell itself and every cell up to `radius` tiles around it
+    pub fn around(
+        &self,
+        (x, y): (i32, i32),
+        radius: i32,
+    ) -> impl Iterator<Item = ((i32, i32), TileVariant)> + '_ {
+        self.region((x - radius, y - radius), (x + radius, y + radius))
+    }
+
+    /// the up to 8 cells touching the given one
+    pub fn neighbours(
+        &self,
+        position: (i32, i32),
+    ) -> impl Iterator<Item = ((i32, i32), TileVariant)> + '_ {
+        self.around(position, 1)
+            .filter(move |(neighbour, _)| *neighbour != position)
+    }
+
+    /// the up to 4 cells sharing an edge with the given one
+    pub fn adjacent(
+        &self,
+        (x, y): (i32, i32),
+    ) -> impl Iterator<Item = ((i32, i32), TileVariant)> + '_ {
+        [(x, y - 1), (x + 1, y), (x, y + 1), (x - 1, y)]
+            .into_iter()
+            .filter_map(|position| self.get(position).map(|variant| (position, variant)))
+    }
+
+    /// whether a projectile could fly between the two world positions, checked every
+    /// quarter of a tile along the way
+    pub fn line_of_sight(&self, from: (f32, f32), to: (f32, f32)) -> bool {
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let steps = ((dx * dx + dy * dy).sqrt() / (WORLD_TILE_SIZE / 4.0)).ceil() as i32;
+
+        (0..=steps).all(|step| {
+            let t = step as f32 / steps.max(1) as f32;
+            let cell = Self::world_to_grid(from.0 + dx * t, from.1 + dy * t);
+            !self.get(cell).is_some_and(TileVariant::blocks_projectiles)
+        })
+    }
+
+    /// cell containing the world position
+    pub fn world_to_grid(x: f32, y: f32) -> (i32, i32) {
+        (
+            (x / WORLD_TILE_SIZE).floor() as i32,
+            (y / WORLD_TILE_SIZE).floor() as i32,
+        )
+    }
+
+    /// world position of the cell's top left corner
+    pub fn grid_to_world((x, y): (i32, i32)) -> (f32, f32) {
+        (x as f32 * WORLD_TILE_SIZE, y as f32 * WORLD_TILE_SIZE)
+    }
+
+    /// world rectangle covered by the cell
+    pub fn cell_rect(position: (i32, i32)) -> Rectangle {
+        let (x, y) = Self::grid_to_world(position);
+        Rectangle::new(x, y, WORLD_TILE_SIZE, WORLD_TILE_SIZE)
+    }
+
+    /// world size in pixels
+    pub fn bounds(&self) -> (f32, f32) {
+        Self::grid_to_world((self.width, self.height))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -20,7 +387,7 @@ pub struct Tile {
     pub size: f32,
     pub src_rect: Rectangle,
     pub dest_rect: Rectangle,
-    pub grid: Point2<u8>,
+    pub grid: Point2<i32>,
     pub position: Vector2<f32>,
 }
 
@@ -28,8 +395,8 @@ impl Tile {
     pub fn new(
         variant: TileVariant,
         texture: LTexture,
-        grid_x: u8,
-        grid_y: u8,
+        grid_x: i32,
+        grid_y: i32,
         width: f32,
         height: f32,
         size: f32,
@@ -52,3 +419,89 @@ impl Tile {
         Rectangle::new(x, y, self.size, self.size)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packed(width: i32, height: i32, cells: Vec<u8>) -> PackedTileGrid {
+        PackedTileGrid {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    #[test]
+    fn variant_ids_round_trip() {
+        for id in 1..=u8::MAX {
+            if let Some(variant) = TileVariant::from_id(id) {
+                assert_eq!(variant.id(), id);
+            }
+        }
+        assert_eq!(TileVariant::from_id(0), None);
+    }
+
+    #[test]
+    fn tiles_play_by_their_properties() {
+        assert!(TileVariant::CRATE.is_solid() && TileVariant::CRATE.blocks_projectiles());
+        // cover is shot over but not walked over
+        assert!(TileVariant::COVER.is_solid() && !TileVariant::COVER.blocks_projectiles());
+        assert!(!TileVariant::WATER.is_solid());
+        assert!(TileVariant::WATER.properties().speed < 1.0);
+        assert!(TileVariant::HAZARD.properties().damage > 0);
+        assert!(!TileVariant::CRATE.is_wall());
+    }
+
+    #[test]
+    fn packs_and_unpacks_through_serde() {
+        let mut grid = TileGrid::filled(4, 3, TileVariant::GROUND).unwrap();
+        grid.set((0, 0), Some(TileVariant::WALL_TOP));
+        grid.set((3, 2), Some(TileVariant::WALL_SIDE));
+        grid.set((1, 1), None);
+
+        let bytes = rmp_serde::to_vec(&grid).unwrap();
+        let unpacked = rmp_serde::from_slice::<TileGrid>(&bytes).unwrap();
+
+        assert_eq!(unpacked, grid);
+        assert_eq!(unpacked.get((1, 1)), None);
+        assert_eq!(unpacked.get((3, 2)), Some(TileVariant::WALL_SIDE));
+    }
+
+    #[test]
+    fn rejects_cells_that_do_not_fill_the_grid() {
+        assert!(TileGrid::try_from(packed(2, 2, vec![3; 3])).is_err());
+        assert!(TileGrid::try_from(packed(-2, -2, vec![3; 4])).is_err());
+        // wraps to 0 when multiplied unchecked
+        assert!(TileGrid::try_from(packed(65536, 65536, Vec::new())).is_err());
+        assert!(TileGrid::try_from(packed(i32::MAX, 2, Vec::new())).is_err());
+        assert!(TileGrid::try_from(packed(0, 0, Vec::new())).is_ok());
+    }
+
+    #[test]
+    fn refuses_grids_past_the_maximum_size() {
+        assert!(TileGrid::new(MAP_MAX_SIZE, 1).is_ok());
+        assert!(TileGrid::new(MAP_MAX_SIZE + 1, 1).is_err());
+        assert!(TileGrid::filled(100000, 100000, TileVariant::GROUND).is_err());
+        assert!(TileGrid::new(i32::MAX, 2).is_err());
+        assert_eq!(TileGrid::new(-3, 2).unwrap().size(), (0, 2));
+    }
+
+    #[test]
+    fn rejects_unknown_tile_ids() {
+        let err = TileGrid::try_from(packed(2, 1, vec![3, 200])).unwrap_err();
+        assert_eq!(err, "unknown tile id 200");
+    }
+
+    #[test]
+    fn ignores_positions_off_the_grid() {
+        let mut grid = TileGrid::filled(2, 2, TileVariant::GROUND).unwrap();
+        grid.set((2, 0), Some(TileVariant::WALL_TOP));
+        grid.set((-1, 0), Some(TileVariant::WALL_TOP));
+
+        assert_eq!(grid.get((2, 0)), None);
+        assert_eq!(grid.get((0, -1)), None);
+        assert!(!grid.is_solid((5, 5)));
+        assert_eq!(grid.iter().count(), 4);
+    }
+}
@@ -0,0 +1,147 @@
+use crate::prelude::*;
+
+/// bits of `wall_mask`, set when the neighbour on that side is a wall
+pub static WALL_NORTH: u8 = 1;
+pub static WALL_EAST: u8 = 2;
+pub static WALL_SOUTH: u8 = 4;
+pub static WALL_WEST: u8 = 8;
+
+/// resolves every generic `WALL` cell into a side or top wall
+pub fn autotile(tiles: &mut TileGrid) {
+    let walls = tiles
+        .iter()
+        .filter(|(_, tile)| *tile == TileVariant::WALL)
+        .map(|(position, _)| position)
+        .collect::<Vec<_>>();
+
+    // a wall below is a wall whether it was resolved yet or not, so order does not matter
+    for position in walls {
+        tiles.set(position, Some(wall_variant(tiles, position)));
+    }
+}
+
+/// re-resolves a wall cell and the wall above it after the cell changed, for editing
+pub fn autotile_at(tiles: &mut TileGrid, (x, y): (i32, i32)) {
+    for position in [(x, y), (x, y - 1)] {
+        if tiles.get(position).is_some_and(TileVariant::is_wall) {
+            tiles.set(position, Some(wall_variant(tiles, position)));
+        }
+    }
+}
+
+/// the face of a wall is only seen from below, anything else shows its top
+pub fn wall_variant(tiles: &TileGrid, (x, y): (i32, i32)) -> TileVariant {
+    match tiles.get((x, y + 1)) {
+        Some(below) if !below.is_wall() => TileVariant::WALL_SIDE,
+        _ => TileVariant::WALL_TOP,
+    }
+}
+
+/// which of the four neighbours are walls, cells outside the grid count as walls so
+/// the border of a map does not draw edges
+pub fn wall_mask(tiles: &TileGrid, (x, y): (i32, i32)) -> u8 {
+    [
+        ((x, y - 1), WALL_NORTH),
+        ((x + 1, y), WALL_EAST),
+        ((x, y + 1), WALL_SOUTH),
+        ((x - 1, y), WALL_WEST),
+    ]
+    .into_iter()
+    .filter(|(position, _)| {
+        !tiles.contains(*position) || tiles.get(*position).is_some_and(TileVariant::is_wall)
+    })
+    .fold(0, |mask, (_, bit)| mask | bit)
+}
+
+/// frame of a wall in its edge atlas, `TILE_WALL_TOP_EDGES` holds 4x4 frames indexed by
+/// the whole mask, `TILE_WALL_SIDE_EDGES` a row of 4 for walls continuing to the east (1)
+/// and west (2) since a face never joins anything above or below it
+pub fn wall_frame(variant: TileVariant, mask: u8) -> u8 {
+    match variant {
+        TileVariant::WALL_SIDE => {
+            (mask & WALL_EAST != 0) as u8 | ((mask & WALL_WEST != 0) as u8) << 1
+        }
+        _ => mask,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a grid of ground with walls wherever `rows` has a `W`
+    fn grid(rows: &[&str]) -> TileGrid {
+        let mut tiles =
+            TileGrid::filled(rows[0].len() as i32, rows.len() as i32, TileVariant::GROUND).unwrap();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, symbol) in row.chars().enumerate() {
+                if symbol == 'W' {
+                    tiles.set((x as i32, y as i32), Some(TileVariant::WALL));
+                }
+            }
+        }
+        tiles
+    }
+
+    #[test]
+    fn shows_the_face_of_walls_above_ground() {
+        let mut tiles = grid(&["WWW", "W.W", "..."]);
+        autotile(&mut tiles);
+
+        assert_eq!(tiles.get((0, 0)), Some(TileVariant::WALL_TOP));
+        assert_eq!(tiles.get((1, 0)), Some(TileVariant::WALL_SIDE));
+        assert_eq!(tiles.get((0, 1)), Some(TileVariant::WALL_SIDE));
+        // walls stand out from any other tile below them
+        let mut tiles = grid(&["W", "."]);
+        tiles.set((0, 1), Some(TileVariant::CRATE));
+        autotile(&mut tiles);
+        assert_eq!(tiles.get((0, 0)), Some(TileVariant::WALL_SIDE));
+
+        // the bottom row has nothing below it
+        let mut tiles = grid(&["...", ".W."]);
+        autotile(&mut tiles);
+        assert_eq!(tiles.get((1, 1)), Some(TileVariant::WALL_TOP));
+    }
+
+    #[test]
+    fn re_resolves_the_wall_above_an_edit() {
+        let mut tiles = grid(&["W", "W", "."]);
+        autotile(&mut tiles);
+        assert_eq!(tiles.get((0, 0)), Some(TileVariant::WALL_TOP));
+
+        tiles.set((0, 1), Some(TileVariant::GROUND));
+        autotile_at(&mut tiles, (0, 1));
+        assert_eq!(tiles.get((0, 0)), Some(TileVariant::WALL_SIDE));
+        assert_eq!(tiles.get((0, 1)), Some(TileVariant::GROUND));
+    }
+
+    #[test]
+    fn masks_walls_and_the_border() {
+        let mut tiles = grid(&["...", ".WW", ".W."]);
+        // solid tiles that are not walls do not join them
+        tiles.set((0, 1), Some(TileVariant::CRATE));
+
+        assert_eq!(wall_mask(&tiles, (1, 1)), WALL_EAST | WALL_SOUTH);
+        assert_eq!(wall_mask(&tiles, (1, 0)), WALL_NORTH | WALL_SOUTH);
+        assert_eq!(wall_mask(&tiles, (0, 0)), WALL_NORTH | WALL_WEST);
+        assert_eq!(
+            wall_mask(&tiles, (2, 2)),
+            WALL_NORTH | WALL_EAST | WALL_SOUTH | WALL_WEST
+        );
+    }
+
+    #[test]
+    fn maps_masks_to_atlas_frames() {
+        let all = WALL_NORTH | WALL_EAST | WALL_SOUTH | WALL_WEST;
+        for mask in 0..=all {
+            assert_eq!(wall_frame(TileVariant::WALL_TOP, mask), mask);
+        }
+
+        let side = |mask| wall_frame(TileVariant::WALL_SIDE, mask);
+        assert_eq!(side(0), 0);
+        assert_eq!(side(WALL_NORTH | WALL_SOUTH), 0);
+        assert_eq!(side(WALL_EAST), 1);
+        assert_eq!(side(WALL_WEST | WALL_NORTH), 2);
+        assert_eq!(side(all), 3);
+    }
+}
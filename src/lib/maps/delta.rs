@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::types::*;
+
+/// how many looks a damaged tile goes through before it breaks, 0 is undamaged
+pub static TILE_DAMAGE_STAGES: u8 = 3;
+
+/// a cell that changed since the map was loaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TileChange {
+    pub position: (i32, i32),
+    pub tile: TileVariant,
+    /// damage stage to draw it with, below `TILE_DAMAGE_STAGES`
+    pub damage: u8,
+}
+
+/// tile changes gathered over a tick, sent to clients instead of the whole map
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct MapDelta {
+    pub changes: Vec<TileChange>,
+}
+
+impl MapDelta {
+    /// a later change to the same cell replaces the earlier one
+    pub fn push(&mut self, change: TileChange) {
+        self.changes
+            .retain(|previous| previous.position != change.position);
+        self.changes.push(change);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// stage of a destructible tile with `health` out of `max` left
+pub fn damage_stage(health: Health, max: Health) -> u8 {
+    let lost = (max - health).max(0) as u32 * TILE_DAMAGE_STAGES as u32 / max.max(1) as u32;
+    (lost as u8).min(TILE_DAMAGE_STAGES - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stages_damage_in_even_steps() {
+        assert_eq!(damage_stage(120, 120), 0);
+        assert_eq!(damage_stage(81, 120), 0);
+        assert_eq!(damage_stage(80, 120), 1);
+        assert_eq!(damage_stage(41, 120), 1);
+        assert_eq!(damage_stage(40, 120), 2);
+        // the last stage lasts until the tile breaks
+        assert_eq!(damage_stage(1, 120), TILE_DAMAGE_STAGES - 1);
+        assert_eq!(damage_stage(0, 120), TILE_DAMAGE_STAGES - 1);
+        assert_eq!(damage_stage(-5, 120), TILE_DAMAGE_STAGES - 1);
+        // overhealed or without health at all
+        assert_eq!(damage_stage(127, 120), 0);
+        assert_eq!(damage_stage(0, 0), 0);
+    }
+
+    #[test]
+    fn keeps_the_latest_change_of_a_cell() {
+        let mut delta = MapDelta::default();
+        let change = |position, damage| TileChange {
+            position,
+            tile: TileVariant::BRICK,
+            damage,
+        };
+        delta.push(change((1, 1), 1));
+        delta.push(change((2, 1), 1));
+        delta.push(change((1, 1), 2));
+
+        assert_eq!(delta.changes, vec![change((2, 1), 1), change((1, 1), 2)]);
+    }
+}
@@ -0,0 +1,539 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+
+use crate::prelude::*;
+
+/// map name the server replaces with a freshly generated arena
+pub static RANDOM_MAP: &str = "random";
+/// shortest side of a generated map, the longest is `MAP_MAX_SIZE`
+pub static GENERATOR_MIN_SIZE: i32 = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Display, EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum Layout {
+    /// rectangular rooms joined by corridors
+    ROOMS,
+    /// smoothed noise, open and irregular
+    CAVES,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Display, EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum Symmetry {
+    NONE,
+    /// left half mirrored onto the right, red spawns left and blue right
+    MIRROR,
+    /// half turn around the center, red spawns top left and blue bottom right
+    ROTATE,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GeneratorSettings {
+    pub seed: u64,
+    pub width: i32,
+    pub height: i32,
+    pub layout: Layout,
+    pub symmetry: Symmetry,
+    /// spawn points per team, or in total for maps without symmetry
+    pub spawns: usize,
+    /// capture zone in the middle of the map
+    pub zone: bool,
+}
+
+impl Default for GeneratorSettings {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            width: 40,
+            height: 30,
+            layout: Layout::ROOMS,
+            symmetry: Symmetry::MIRROR,
+            spawns: 4,
+            zone: true,
+        }
+    }
+}
+
+/// open (walkable) cells of the map being generated
+struct Canvas {
+    width: i32,
+    height: i32,
+    symmetry: Symmetry,
+    open: Vec<bool>,
+}
+
+impl Canvas {
+    fn new(width: i32, height: i32, symmetry: Symmetry) -> Self {
+        Self {
+            width,
+            height,
+            symmetry,
+            open: vec![false; width as usize * height as usize],
+        }
+    }
+
+    /// the border always stays solid
+    fn inside(&self, (x, y): (i32, i32)) -> bool {
+        x > 0 && y > 0 && x < self.width - 1 && y < self.height - 1
+    }
+
+    fn is_open(&self, (x, y): (i32, i32)) -> bool {
+        self.inside((x, y)) && self.open[(y * self.width + x) as usize]
+    }
+
+    fn set(&mut self, (x, y): (i32, i32), open: bool) {
+        if self.inside((x, y)) {
+            self.open[(y * self.width + x) as usize] = open;
+        }
+    }
+
+    fn mirrored(&self, (x, y): (i32, i32)) -> (i32, i32) {
+        match self.symmetry {
+            Symmetry::NONE => (x, y),
+            Symmetry::MIRROR => (self.width - 1 - x, y),
+            Symmetry::ROTATE => (self.width - 1 - x, self.height - 1 - y),
+        }
+    }
+
+    /// opens a cell and its symmetric twin
+    fn carve(&mut self, cell: (i32, i32)) {
+        self.set(cell, true);
+        self.set(self.mirrored(cell), true);
+    }
+
+    /// copies the first half over the second one
+    fn symmetrize(&mut self) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let twin = self.mirrored((x, y));
+                let first_half = match self.symmetry {
+                    Symmetry::NONE => true,
+                    Symmetry::MIRROR => x <= twin.0,
+                    Symmetry::ROTATE => (y, x) <= (twin.1, twin.0),
+                };
+                if !first_half {
+                    let open = self.is_open(twin);
+                    self.set((x, y), open);
+                }
+            }
+        }
+    }
+
+    fn cells(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        (0..self.height).flat_map(move |y| (0..self.width).map(move |x| (x, y)))
+    }
+
+    fn adjacent((x, y): (i32, i32)) -> [(i32, i32); 4] {
+        [(x, y - 1), (x + 1, y), (x, y + 1), (x - 1, y)]
+    }
+
+    /// connected open areas, largest first
+    fn regions(&self) -> Vec<Vec<(i32, i32)>> {
+        let mut seen = HashSet::new();
+        let mut regions = Vec::new();
+
+        for start in self.cells().filter(|cell| self.is_open(*cell)) {
+            if !seen.insert(start) {
+                continue;
+            }
+
+            let mut region = vec![start];
+            let mut queue = VecDeque::from([start]);
+            while let Some(cell) = queue.pop_front() {
+                for next in Self::adjacent(cell) {
+                    if self.is_open(next) && seen.insert(next) {
+                        region.push(next);
+                        queue.push_back(next);
+                    }
+                }
+            }
+            regions.push(region);
+        }
+
+        regions.sort_by_key(|region| std::cmp::Reverse(region.len()));
+        regions
+    }
+
+    /// joins every stray area to the largest one along the shortest tunnel
+    fn connect(&mut self) {
+        loop {
+            let regions = self.regions();
+            let [main, stray, ..] = regions.as_slice() else {
+                return;
+            };
+            let main = main.iter().copied().collect::<HashSet<_>>();
+
+            // breadth first through the walls, starting from every cell of the stray area
+            let mut parents = HashMap::new();
+            let mut queue = stray.iter().copied().collect::<VecDeque<_>>();
+            for cell in stray {
+                parents.insert(*cell, *cell);
+            }
+
+            let mut end = None;
+            while let Some(cell) = queue.pop_front() {
+                if main.contains(&cell) {
+                    end = Some(cell);
+                    break;
+                }
+                for next in Self::adjacent(cell) {
+                    if self.inside(next) && !parents.contains_key(&next) {
+                        parents.insert(next, cell);
+                        queue.push_back(next);
+                    }
+                }
+            }
+
+            let Some(mut cell) = end else {
+                return;
+            };
+            // two tiles wide so players fit through comfortably
+            while parents[&cell] != cell {
+                self.carve(cell);
+                self.carve((cell.0 + 1, cell.1));
+                self.carve((cell.0, cell.1 + 1));
+                cell = parents[&cell];
+            }
+        }
+    }
+
+    fn open_neighbours(&self, (x, y): (i32, i32)) -> usize {
+        (-1..=1)
+            .flat_map(|dy| (-1..=1).map(move |dx| (x + dx, y + dy)))
+            .filter(|cell| *cell != (x, y) && self.is_open(*cell))
+            .count()
+    }
+}
+
+/// builds an arena from the settings, the same seed always gives the same map
+pub fn generate(settings: &GeneratorSettings) -> MapFile {
+    let mut rng = StdRng::seed_from_u64(settings.seed);
+    let size = |length: i32| length.clamp(GENERATOR_MIN_SIZE, MAP_MAX_SIZE);
+    let (width, height) = (size(settings.width), size(settings.height));
+    let mut canvas = Canvas::new(width, height, settings.symmetry);
+
+    match settings.layout {
+        Layout::ROOMS => rooms(&mut canvas, &mut rng),
+        Layout::CAVES => caves(&mut canvas, &mut rng),
+    }
+    canvas.symmetrize();
+
+    // the middle is always open, it holds the zone and leaves spawns somewhere to go
+    let center = (width / 2 - 1, height / 2 - 1);
+    for dy in 0..3 {
+        for dx in 0..3 {
+            canvas.carve((center.0 + dx, center.1 + dy));
+        }
+    }
+    canvas.connect();
+
+    let spawns = place_spawns(&canvas, settings, &mut rng);
+    let mut entities = Vec::new();
+    if settings.zone {
+        entities.push(MapEntity {
+            kind: MapEntityKind::ZONE(3, 3),
+            position: center,
+        });
+    }
+    for team in [Team::RED, Team::BLUE] {
+        if let Some(spawn) = spawns.iter().find(|spawn| spawn.team == Some(team)) {
+            entities.push(MapEntity {
+                kind: MapEntityKind::FLAG(Some(team)),
+                position: spawn.position,
+            });
+        }
+    }
+
+    let mut tiles = TileGrid::new(width, height).expect("the canvas has the same size");
+    for cell in canvas.cells() {
+        let tile = if canvas.is_open(cell) {
+            TileVariant::GROUND
+        } else {
+            TileVariant::WALL
+        };
+        tiles.set(cell, Some(tile));
+    }
+    autotile(&mut tiles);
+
+    let mut modes = vec![GameMode::DEATHMATCH, GameMode::LAST_MAN_STANDING];
+    if settings.zone {
+        modes.insert(1, GameMode::KING_OF_THE_HILL);
+    }
+
+    MapFile {
+        version: MAP_VERSION,
+        header: MapHeader {
+            name: format!("{}-{}", RANDOM_MAP, settings.seed),
+            author: String::from("generator"),
+            width,
+            height,
+            players: (2, spawns.len().max(2) as u32),
+            modes,
+        },
+        legend: MapFile::default_legend(),
+        tiles,
+        layers: BTreeMap::new(),
+        grid_line: 0,
+        spawns,
+        entities,
+    }
+}
+
+fn rooms(canvas: &mut Canvas, rng: &mut StdRng) {
+    let area = canvas.width * canvas.height;
+    let count = (area / 90).clamp(4, 40);
+    let mut rooms: Vec<(i32, i32, i32, i32)> = Vec::new();
+
+    for _ in 0..count * 4 {
+        if rooms.len() as i32 >= count {
+            break;
+        }
+
+        let (w, h) = (rng.gen_range(4..=9), rng.gen_range(4..=7));
+        let x = rng.gen_range(1..(canvas.width - w).max(2));
+        let y = rng.gen_range(1..(canvas.height - h).max(2));
+
+        // keep a wall between rooms
+        let overlaps = rooms.iter().any(|(rx, ry, rw, rh)| {
+            x <= rx + rw && rx <= &(x + w) && y <= ry + rh && ry <= &(y + h)
+        });
+        if !overlaps {
+            rooms.push((x, y, w, h));
+        }
+    }
+
+    for (x, y, w, h) in &rooms {
+        for dy in 0..*h {
+            for dx in 0..*w {
+                canvas.set((x + dx, y + dy), true);
+            }
+        }
+    }
+
+    rooms.sort_by_key(|(x, y, _, _)| (*x, *y));
+    for pair in rooms.windows(2) {
+        let center = |(x, y, w, h): (i32, i32, i32, i32)| (x + w / 2, y + h / 2);
+        let (from, to) = (center(pair[0]), center(pair[1]));
+        for x in from.0.min(to.0)..=from.0.max(to.0) {
+            canvas.set((x, from.1), true);
+            canvas.set((x, from.1 + 1), true);
+        }
+        for y in from.1.min(to.1)..=from.1.max(to.1) {
+            canvas.set((to.0, y), true);
+            canvas.set((to.0 + 1, y), true);
+        }
+    }
+}
+
+fn caves(canvas: &mut Canvas, rng: &mut StdRng) {
+    for cell in canvas.cells().collect::<Vec<_>>() {
+        canvas.set(cell, rng.gen_bool(0.55));
+    }
+
+    for _ in 0..5 {
+        let next = canvas
+            .cells()
+            .map(|cell| {
+                let neighbours = canvas.open_neighbours(cell);
+                if canvas.is_open(cell) {
+                    neighbours >= 3
+                } else {
+                    neighbours >= 5
+                }
+            })
+            .collect::<Vec<_>>();
+
+        for (cell, open) in canvas.cells().collect::<Vec<_>>().into_iter().zip(next) {
+            canvas.set(cell, open);
+        }
+    }
+}
+
+/// spreads spawns out, each one as far as possible from the ones already placed
+fn place_spawns(
+    canvas: &Canvas,
+    settings: &GeneratorSettings,
+    rng: &mut StdRng,
+) -> Vec<SpawnPoint> {
+    // cells with room to move around
+    let roomy = canvas
+        .cells()
+        .filter(|cell| canvas.is_open(*cell) && canvas.open_neighbours(*cell) == 8)
+        .collect::<Vec<_>>();
+    let candidates = if roomy.is_empty() {
+        canvas
+            .cells()
+            .filter(|cell| canvas.is_open(*cell))
+            .collect()
+    } else {
+        roomy
+    };
+
+    let (width, height) = (canvas.width, canvas.height);
+    let red_side = |(x, y): &(i32, i32)| match settings.symmetry {
+        Symmetry::NONE => true,
+        Symmetry::MIRROR => *x < width / 3,
+        Symmetry::ROTATE => x + y < (width + height) / 3,
+    };
+    let side = candidates
+        .iter()
+        .copied()
+        .filter(red_side)
+        .collect::<Vec<_>>();
+    let pool = if side.is_empty() { candidates } else { side };
+
+    let count = settings.spawns.max(1);
+    let mut chosen = vec![*pool.choose(rng).unwrap()];
+    while chosen.len() < count.min(pool.len()) {
+        let next = pool
+            .iter()
+            .filter(|cell| !chosen.contains(cell))
+            .max_by_key(|cell| {
+                chosen
+                    .iter()
+                    .map(|other| (cell.0 - other.0).pow(2) + (cell.1 - other.1).pow(2))
+                    .min()
+                    .unwrap()
+            })
+            .copied()
+            .unwrap();
+        chosen.push(next);
+    }
+
+    match settings.symmetry {
+        Symmetry::NONE => chosen
+            .into_iter()
+            .map(|position| SpawnPoint {
+                position,
+                team: None,
+            })
+            .collect(),
+        _ => {
+            let red = chosen.iter().map(|position| SpawnPoint {
+                position: *position,
+                team: Some(Team::RED),
+            });
+            let blue = chosen.iter().map(|position| SpawnPoint {
+                position: canvas.mirrored(*position),
+                team: Some(Team::BLUE),
+            });
+            red.chain(blue).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(seed: u64, layout: Layout, symmetry: Symmetry) -> GeneratorSettings {
+        GeneratorSettings {
+            seed,
+            layout,
+            symmetry,
+            ..Default::default()
+        }
+    }
+
+    fn every_setting() -> impl Iterator<Item = GeneratorSettings> {
+        (0..8).flat_map(|seed| {
+            [Layout::ROOMS, Layout::CAVES]
+                .into_iter()
+                .flat_map(move |layout| {
+                    [Symmetry::NONE, Symmetry::MIRROR, Symmetry::ROTATE]
+                        .into_iter()
+                        .map(move |symmetry| settings(seed, layout, symmetry))
+                })
+        })
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_map() {
+        for settings in every_setting() {
+            assert_eq!(generate(&settings), generate(&settings));
+        }
+
+        let a = generate(&settings(1, Layout::ROOMS, Symmetry::MIRROR));
+        let b = generate(&settings(2, Layout::ROOMS, Symmetry::MIRROR));
+        assert_ne!(a.tiles, b.tiles);
+    }
+
+    #[test]
+    fn keeps_sizes_within_bounds() {
+        let mut settings = settings(0, Layout::CAVES, Symmetry::NONE);
+        (settings.width, settings.height) = (-5, 0);
+        let map = generate(&settings);
+        assert_eq!(map.tiles.size(), (GENERATOR_MIN_SIZE, GENERATOR_MIN_SIZE));
+
+        (settings.width, settings.height) = (GENERATOR_MIN_SIZE, i32::MAX);
+        let map = generate(&settings);
+        assert_eq!(map.tiles.size(), (GENERATOR_MIN_SIZE, MAP_MAX_SIZE));
+    }
+
+    #[test]
+    fn every_ground_tile_is_reachable() {
+        for settings in every_setting() {
+            let map = generate(&settings);
+            let ground = map
+                .tiles
+                .iter()
+                .filter(|(_, tile)| !tile.is_solid())
+                .map(|(position, _)| position)
+                .collect::<HashSet<_>>();
+
+            let start = map.spawns[0].position;
+            let mut seen = HashSet::from([start]);
+            let mut queue = VecDeque::from([start]);
+            while let Some(cell) = queue.pop_front() {
+                for (next, tile) in map.tiles.adjacent(cell) {
+                    if !tile.is_solid() && seen.insert(next) {
+                        queue.push_back(next);
+                    }
+                }
+            }
+
+            assert_eq!(seen, ground, "{:?}", settings);
+        }
+    }
+
+    #[test]
+    fn generated_maps_pass_the_linter() {
+        for settings in every_setting() {
+            let map = generate(&settings);
+            let diagnostics = lint(&map.header.name, &map.to_source());
+            assert!(diagnostics.is_empty(), "{:?}: {:?}", settings, diagnostics);
+        }
+    }
+
+    #[test]
+    fn mirrored_maps_are_fair() {
+        let map = generate(&settings(3, Layout::CAVES, Symmetry::MIRROR));
+        let (width, height) = map.tiles.size();
+
+        for y in 0..height {
+            for x in 0..width {
+                assert_eq!(
+                    map.tiles.is_solid((x, y)),
+                    map.tiles.is_solid((width - 1 - x, y)),
+                    "({}, {})",
+                    x,
+                    y
+                );
+            }
+        }
+
+        let team_spawns = |team| {
+            map.spawns
+                .iter()
+                .filter(|spawn| spawn.team == Some(team))
+                .count()
+        };
+        assert_eq!(team_spawns(Team::RED), 4);
+        assert_eq!(team_spawns(Team::BLUE), 4);
+    }
+}
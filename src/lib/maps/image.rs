@@ -0,0 +1,297 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::str::FromStr;
+
+use crate::prelude::*;
+
+/// what a pixel color stands for in an image map
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelKind {
+    TILE(TileVariant),
+    /// spawn point on ground
+    SPAWN(Option<Team>),
+    /// flag base on ground
+    FLAG(Option<Team>),
+    /// ground inside a capture zone, touching zone pixels merge into one zone
+    ZONE,
+}
+
+impl FromStr for PixelKind {
+    type Err = String;
+
+    /// `GROUND`, `WALL_TOP`, `SPAWN`, `SPAWN_RED`, `FLAG_BLUE`, `ZONE` and so on
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.to_uppercase();
+        let (kind, team) = match text.rsplit_once('_') {
+            Some((kind, team)) if kind == "SPAWN" || kind == "FLAG" => {
+                (kind, Some(team.parse::<Team>().map_err(|_| text.clone())?))
+            }
+            _ => (text.as_str(), None),
+        };
+
+        match kind {
+            "SPAWN" => Ok(PixelKind::SPAWN(team)),
+            "FLAG" => Ok(PixelKind::FLAG(team)),
+            "ZONE" => Ok(PixelKind::ZONE),
+            _ => kind
+                .parse::<TileVariant>()
+                .map(PixelKind::TILE)
+                .map_err(|_| text.clone()),
+        }
+    }
+}
+
+/// colors designers paint with unless told otherwise
+pub fn default_palette() -> HashMap<[u8; 3], PixelKind> {
+    HashMap::from([
+        ([0xff, 0xff, 0xff], PixelKind::TILE(TileVariant::GROUND)),
+        ([0x00, 0x00, 0x00], PixelKind::TILE(TileVariant::WALL)),
+        ([0x80, 0x80, 0x80], PixelKind::TILE(TileVariant::WALL_SIDE)),
+        ([0x40, 0x40, 0x40], PixelKind::TILE(TileVariant::COVER)),
+        ([0x00, 0xff, 0xff], PixelKind::TILE(TileVariant::WATER)),
+        ([0xff, 0x80, 0x00], PixelKind::TILE(TileVariant::HAZARD)),
+        ([0x8b, 0x45, 0x13], PixelKind::TILE(TileVariant::CRATE)),
+        ([0xa0, 0x40, 0x30], PixelKind::TILE(TileVariant::BRICK)),
+        ([0x00, 0xff, 0x00], PixelKind::SPAWN(None)),
+        ([0xff, 0x00, 0x00], PixelKind::SPAWN(Some(Team::RED))),
+        ([0x00, 0x00, 0xff], PixelKind::SPAWN(Some(Team::BLUE))),
+        ([0x80, 0x00, 0x00], PixelKind::FLAG(Some(Team::RED))),
+        ([0x00, 0x00, 0x80], PixelKind::FLAG(Some(Team::BLUE))),
+        ([0xff, 0xff, 0x00], PixelKind::ZONE),
+    ])
+}
+
+/// converts a png pixel map into the server's map format, one pixel per tile
+///
+/// fully transparent pixels are ground, any color missing from `palette` is an error
+pub fn import_png(
+    name: &str,
+    bytes: &[u8],
+    palette: &HashMap<[u8; 3], PixelKind>,
+) -> Result<MapFile, MapError> {
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder
+        .read_info()
+        .map_err(|err| MapError::new(format!("malformed png: {}", err)))?;
+
+    // sized from the header before any pixels are decoded into memory
+    let size = |length: u32| i32::try_from(length).unwrap_or(i32::MAX);
+    let info = reader.info();
+    let mut tiles = TileGrid::filled(size(info.width), size(info.height), TileVariant::GROUND)
+        .map_err(MapError::new)?;
+    let (width, height) = tiles.size();
+
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let frame = reader
+        .next_frame(&mut buffer)
+        .map_err(|err| MapError::new(format!("malformed png: {}", err)))?;
+
+    let channels = frame.color_type.samples();
+    let pixel = |x: i32, y: i32| {
+        let start = y as usize * frame.line_size + x as usize * channels;
+        let sample = &buffer[start..start + channels];
+        match frame.color_type {
+            png::ColorType::Grayscale => ([sample[0]; 3], 0xff),
+            png::ColorType::GrayscaleAlpha => ([sample[0]; 3], sample[1]),
+            png::ColorType::Rgba => ([sample[0], sample[1], sample[2]], sample[3]),
+            _ => ([sample[0], sample[1], sample[2]], 0xff),
+        }
+    };
+
+    let mut spawns = Vec::new();
+    let mut entities = Vec::new();
+    let mut zone_cells = HashSet::new();
+
+    for y in 0..height {
+        for x in 0..width {
+            let (color, alpha) = pixel(x, y);
+            if alpha == 0 {
+                continue;
+            }
+
+            let kind = palette.get(&color).ok_or_else(|| {
+                MapError::new(format!(
+                    "pixel {},{} has color #{:02x}{:02x}{:02x} which is not in the palette",
+                    x, y, color[0], color[1], color[2]
+                ))
+            })?;
+            match *kind {
+                PixelKind::TILE(variant) => tiles.set((x, y), Some(variant)),
+                PixelKind::SPAWN(team) => spawns.push(SpawnPoint {
+                    position: (x, y),
+                    team,
+                }),
+                PixelKind::FLAG(team) => entities.push(MapEntity {
+                    kind: MapEntityKind::FLAG(team),
+                    position: (x, y),
+                }),
+                PixelKind::ZONE => {
+                    zone_cells.insert((x, y));
+                }
+            }
+        }
+    }
+
+    autotile(&mut tiles);
+
+    // every patch of zone pixels becomes the rectangle around it
+    let mut cells = zone_cells.iter().copied().collect::<Vec<_>>();
+    cells.sort_by_key(|(x, y)| (*y, *x));
+    let mut seen = HashSet::new();
+    for start in cells {
+        if !seen.insert(start) {
+            continue;
+        }
+
+        let (mut min, mut max) = (start, start);
+        let mut stack = vec![start];
+        while let Some((x, y)) = stack.pop() {
+            min = (min.0.min(x), min.1.min(y));
+            max = (max.0.max(x), max.1.max(y));
+            for next in [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)] {
+                if zone_cells.contains(&next) && seen.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+
+        entities.push(MapEntity {
+            kind: MapEntityKind::ZONE(max.0 - min.0 + 1, max.1 - min.1 + 1),
+            position: min,
+        });
+    }
+
+    Ok(MapFile {
+        version: MAP_VERSION,
+        header: MapHeader {
+            name: name.trim_end_matches(".png").to_string(),
+            author: String::new(),
+            width,
+            height,
+            players: (2, SERVER_MAX_CLIENTS as u32),
+            modes: Vec::new(),
+        },
+        legend: MapFile::default_legend(),
+        tiles,
+        layers: BTreeMap::new(),
+        grid_line: 0,
+        spawns,
+        entities,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(width: u32, height: u32, color: png::ColorType, data: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, width, height);
+            encoder.set_color(color);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(data).unwrap();
+        }
+        bytes
+    }
+
+    #[test]
+    fn maps_palette_colors_to_tiles_and_entities() {
+        #[rustfmt::skip]
+        let pixels = [
+            0xff, 0xff, 0xff, 0xff,   0x00, 0x00, 0x00, 0xff,   0xff, 0x00, 0x00, 0xff,   0xff, 0xff, 0x00, 0xff,
+            0x12, 0x34, 0x56, 0x00,   0x00, 0xff, 0xff, 0xff,   0x00, 0x00, 0x80, 0xff,   0xff, 0xff, 0x00, 0xff,
+        ];
+        let bytes = encode(4, 2, png::ColorType::Rgba, &pixels);
+        let map = import_png("pixels.png", &bytes, &default_palette()).unwrap();
+
+        assert_eq!(map.header.name, "pixels");
+        assert_eq!(map.tiles.size(), (4, 2));
+        assert_eq!(map.tiles.get((0, 0)), Some(TileVariant::GROUND));
+        // resolved from the water below it
+        assert_eq!(map.tiles.get((1, 0)), Some(TileVariant::WALL_SIDE));
+        assert_eq!(map.tiles.get((1, 1)), Some(TileVariant::WATER));
+        // transparent pixels are ground whatever their color
+        assert_eq!(map.tiles.get((0, 1)), Some(TileVariant::GROUND));
+        // spawns, flags and zones stand on ground
+        assert_eq!(map.tiles.get((2, 0)), Some(TileVariant::GROUND));
+
+        assert_eq!(
+            map.spawns,
+            vec![SpawnPoint {
+                position: (2, 0),
+                team: Some(Team::RED)
+            }]
+        );
+        assert!(map.entities.contains(&MapEntity {
+            kind: MapEntityKind::FLAG(Some(Team::BLUE)),
+            position: (2, 1),
+        }));
+        // touching zone pixels merge
+        assert!(map.entities.contains(&MapEntity {
+            kind: MapEntityKind::ZONE(1, 2),
+            position: (3, 0),
+        }));
+    }
+
+    #[test]
+    fn reads_images_without_alpha() {
+        let rgb = encode(
+            2,
+            1,
+            png::ColorType::Rgb,
+            &[0xff, 0xff, 0xff, 0x40, 0x40, 0x40],
+        );
+        let map = import_png("rgb", &rgb, &default_palette()).unwrap();
+        assert_eq!(map.tiles.get((1, 0)), Some(TileVariant::COVER));
+
+        let gray = encode(2, 1, png::ColorType::Grayscale, &[0xff, 0x00]);
+        let map = import_png("gray", &gray, &default_palette()).unwrap();
+        assert_eq!(map.tiles.get((0, 0)), Some(TileVariant::GROUND));
+        assert_eq!(map.tiles.get((1, 0)), Some(TileVariant::WALL_TOP));
+    }
+
+    #[test]
+    fn rejects_colors_missing_from_the_palette() {
+        let bytes = encode(
+            2,
+            1,
+            png::ColorType::Rgb,
+            &[0xff, 0xff, 0xff, 0x12, 0x34, 0x56],
+        );
+        let err = import_png("x", &bytes, &default_palette()).unwrap_err();
+        assert_eq!(
+            err.message,
+            "pixel 1,0 has color #123456 which is not in the palette"
+        );
+
+        let palette = HashMap::from([
+            ([0xff, 0xff, 0xff], PixelKind::TILE(TileVariant::GROUND)),
+            ([0x12, 0x34, 0x56], PixelKind::TILE(TileVariant::WALL_SIDE)),
+        ]);
+        let map = import_png("x", &bytes, &palette).unwrap();
+        assert_eq!(map.tiles.get((1, 0)), Some(TileVariant::WALL_SIDE));
+    }
+
+    #[test]
+    fn rejects_data_that_is_not_a_png() {
+        let err = import_png("x", b"not a png", &default_palette()).unwrap_err();
+        assert!(err.message.starts_with("malformed png"));
+    }
+
+    #[test]
+    fn parses_pixel_kinds() {
+        assert_eq!("ground".parse(), Ok(PixelKind::TILE(TileVariant::GROUND)));
+        assert_eq!(
+            "WALL_TOP".parse(),
+            Ok(PixelKind::TILE(TileVariant::WALL_TOP))
+        );
+        assert_eq!("spawn".parse(), Ok(PixelKind::SPAWN(None)));
+        assert_eq!("spawn_blue".parse(), Ok(PixelKind::SPAWN(Some(Team::BLUE))));
+        assert_eq!("FLAG_RED".parse(), Ok(PixelKind::FLAG(Some(Team::RED))));
+        assert_eq!("zone".parse(), Ok(PixelKind::ZONE));
+        assert!("flag_green".parse::<PixelKind>().is_err());
+        assert!("lava".parse::<PixelKind>().is_err());
+    }
+}
@@ -0,0 +1,54 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+
+use crate::prelude::*;
+
+/// marks a cell a visual layer leaves empty, the lower layers show through it
+pub static LAYER_EMPTY: char = '_';
+
+/// tile layers of a map in the order they are drawn, only `COLLISION` affects play
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Deserialize,
+    Serialize,
+    Display,
+    EnumString,
+)]
+pub enum MapLayer {
+    FLOOR,
+    DECORATION,
+    /// the `[grid]` of a map file, what players walk on and shoot at
+    COLLISION,
+    /// roofs and foliage, drawn over players
+    OVERHEAD,
+}
+
+impl MapLayer {
+    /// layers drawn for looks alone, each in its own optional section
+    pub const VISUAL: [MapLayer; 3] = [MapLayer::FLOOR, MapLayer::DECORATION, MapLayer::OVERHEAD];
+
+    /// name of the map file section holding the layer
+    pub fn section(self) -> String {
+        match self {
+            MapLayer::COLLISION => String::from("grid"),
+            layer => layer.to_string().to_lowercase(),
+        }
+    }
+}
+
+/// everything clients need to draw a map, what the map package carries
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+pub struct MapTiles {
+    pub collision: TileGrid,
+    /// visual layers the map has, never `COLLISION`
+    pub layers: BTreeMap<MapLayer, TileGrid>,
+}
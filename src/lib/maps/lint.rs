@@ -0,0 +1,553 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use serde::Serialize;
+use strum_macros::Display;
+
+use crate::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Display)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Severity {
+    ERROR,
+    WARNING,
+}
+
+/// a problem found in a map file, `code` is stable for tools to match on
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    /// 1 based, missing when the problem is not tied to a place in the file
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::ERROR,
+            code,
+            line: None,
+            column: None,
+            message: message.into(),
+        }
+    }
+
+    fn warning(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::WARNING,
+            ..Self::error(code, message)
+        }
+    }
+
+    /// points the diagnostic at a grid cell
+    fn at(mut self, map: &MapFile, (x, y): (i32, i32)) -> Self {
+        if map.tiles.contains((x, y)) {
+            self.line = Some(map.grid_line + y as usize);
+            self.column = Some(x as usize + 1);
+        }
+
+        self
+    }
+}
+
+impl From<MapError> for Diagnostic {
+    fn from(err: MapError) -> Self {
+        Self {
+            line: err.line,
+            column: err.column,
+            ..Self::error("parse", err.message)
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, "{}:{}: ", line, column)?,
+            (Some(line), None) => write!(f, "{}: ", line)?,
+            _ => {}
+        }
+
+        write!(f, "{}[{}]: {}", self.severity, self.code, self.message)
+    }
+}
+
+/// every problem found in a map, errors first
+pub fn lint(name: &str, source: &str) -> Vec<Diagnostic> {
+    let (map, errors) = match MapFile::parse_recovering(name, source) {
+        Ok(parsed) => parsed,
+        Err(err) => return vec![err.into()],
+    };
+
+    // v1 maps load unknown symbols as ground, so a typo there does not stop the map
+    let mut diagnostics = errors
+        .into_iter()
+        .map(|err| match map.version {
+            1 => Diagnostic {
+                severity: Severity::WARNING,
+                code: "unknown-symbol",
+                ..Diagnostic::from(err)
+            },
+            _ => Diagnostic::from(err),
+        })
+        .collect::<Vec<_>>();
+
+    let walkable = map
+        .tiles
+        .iter()
+        .filter(|(_, tile)| !tile.is_solid())
+        .map(|(position, _)| position)
+        .collect::<Vec<_>>();
+    if walkable.is_empty() {
+        diagnostics.push(Diagnostic::error(
+            "no-ground",
+            "the map has no ground tiles",
+        ));
+        return diagnostics;
+    }
+
+    check_spawns(&map, &mut diagnostics);
+    check_entities(&map, &mut diagnostics);
+    check_modes(&map, &mut diagnostics);
+    check_reachability(&map, &walkable, &mut diagnostics);
+
+    diagnostics.sort_by_key(|diagnostic| diagnostic.severity);
+    diagnostics
+}
+
+fn check_spawns(map: &MapFile, diagnostics: &mut Vec<Diagnostic>) {
+    if map.spawns.is_empty() {
+        diagnostics.push(Diagnostic::warning(
+            "no-spawns",
+            "no spawn points, players spawn on random ground",
+        ));
+        return;
+    }
+
+    let has_team = |team| map.spawns.iter().any(|spawn| spawn.team == Some(team));
+    let has_neutral = map.spawns.iter().any(|spawn| spawn.team.is_none());
+    for (team, other) in [(Team::RED, Team::BLUE), (Team::BLUE, Team::RED)] {
+        if has_team(other) && !has_team(team) && !has_neutral {
+            diagnostics.push(Diagnostic::warning(
+                "team-spawns",
+                format!("{} has spawn points but {} has none", other, team),
+            ));
+        }
+    }
+}
+
+fn check_entities(map: &MapFile, diagnostics: &mut Vec<Diagnostic>) {
+    for entity in &map.entities {
+        let cells = match entity.kind {
+            MapEntityKind::ZONE(w, h) => (0..w)
+                .flat_map(|dx| (0..h).map(move |dy| (dx, dy)))
+                .map(|(dx, dy)| (entity.position.0 + dx, entity.position.1 + dy))
+                .collect(),
+            _ => vec![entity.position],
+        };
+
+        if let Some(cell) = cells.iter().find(|cell| !map.tiles.contains(**cell)) {
+            diagnostics.push(Diagnostic::error(
+                "entity-placement",
+                format!("{:?} at {:?} reaches outside the grid", entity.kind, cell),
+            ));
+        } else if let Some(cell) = cells.iter().find(|cell| map.tiles.is_solid(**cell)) {
+            diagnostics.push(
+                Diagnostic::error(
+                    "entity-placement",
+                    format!(
+                        "{:?} at {:?} is on {}",
+                        entity.kind,
+                        cell,
+                        map.tiles.get(*cell).unwrap()
+                    ),
+                )
+                .at(map, *cell),
+            );
+        }
+    }
+
+    let mut teleporters: HashMap<&str, Vec<(i32, i32)>> = HashMap::new();
+    for entity in &map.entities {
+        let (x, y) = entity.position;
+        match entity.kind {
+            MapEntityKind::TELEPORTER(ref name) => {
+                teleporters.entry(name).or_default().push(entity.position)
+            }
+            MapEntityKind::PAD(dx, dy) => {
+                let target = (x + dx, y + dy);
+                if !map.tiles.contains(target) || map.tiles.is_solid(target) {
+                    diagnostics.push(
+                        Diagnostic::error(
+                            "pad-target",
+                            format!(
+                                "pad at {:?} throws players to {:?}, which is not open ground",
+                                entity.position, target
+                            ),
+                        )
+                        .at(map, entity.position),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+    for (name, positions) in teleporters {
+        if positions.len() != 2 {
+            diagnostics.push(
+                Diagnostic::error(
+                    "teleporters",
+                    format!(
+                        "teleporter `{}` needs exactly one partner, found {} teleporters",
+                        name,
+                        positions.len()
+                    ),
+                )
+                .at(map, positions[0]),
+            );
+        }
+    }
+
+    let flag_base = |team| {
+        map.entities
+            .iter()
+            .any(|entity| entity.kind == MapEntityKind::FLAG(Some(team)))
+    };
+    for (team, other) in [(Team::RED, Team::BLUE), (Team::BLUE, Team::RED)] {
+        if flag_base(other) && !flag_base(team) {
+            diagnostics.push(Diagnostic::warning(
+                "flag-bases",
+                format!("{} has a flag base but {} has none", other, team),
+            ));
+        }
+    }
+}
+
+fn check_modes(map: &MapFile, diagnostics: &mut Vec<Diagnostic>) {
+    for mode in &map.header.modes {
+        match mode {
+            GameMode::KING_OF_THE_HILL if map.zones().is_empty() => {
+                diagnostics.push(Diagnostic::error(
+                    "mode-requirements",
+                    format!("{} needs at least one capture zone", mode),
+                ))
+            }
+            GameMode::LAST_MAN_STANDING
+                if !map.spawns.is_empty() && map.spawns.len() < map.header.players.1 as usize =>
+            {
+                diagnostics.push(Diagnostic::warning(
+                    "mode-requirements",
+                    format!(
+                        "{} with up to {} players has only {} spawn points",
+                        mode,
+                        map.header.players.1,
+                        map.spawns.len()
+                    ),
+                ))
+            }
+            _ => {}
+        }
+    }
+}
+
+/// flood fills from the spawn points, or from the largest open area when there are none,
+/// and reports the ground that was never reached; pads and teleporters only lead one way
+fn check_reachability(map: &MapFile, walkable: &[(i32, i32)], diagnostics: &mut Vec<Diagnostic>) {
+    let starts = if map.spawns.is_empty() {
+        // walking leads both ways, so open areas are the same whichever cell they are filled from
+        let mut seen = HashSet::new();
+        walkable
+            .iter()
+            .map(|start| flood(map, &[*start], false, &mut seen))
+            .reduce(|largest, area| {
+                if area.len() > largest.len() {
+                    area
+                } else {
+                    largest
+                }
+            })
+            .unwrap_or_default()
+    } else {
+        map.spawns
+            .iter()
+            .map(|spawn| spawn.position)
+            .filter(|position| {
+                map.tiles
+                    .get(*position)
+                    .is_some_and(|tile| !tile.is_solid())
+            })
+            .collect()
+    };
+
+    let mut seen = HashSet::new();
+    flood(map, &starts, true, &mut seen);
+
+    // whatever is left is grouped into areas, one warning each
+    for start in walkable {
+        let region = flood(map, &[*start], false, &mut seen);
+        let Some(first) = region.iter().min_by_key(|(x, y)| (*y, *x)) else {
+            continue;
+        };
+
+        diagnostics.push(
+            Diagnostic::warning(
+                "unreachable",
+                format!(
+                    "{} ground tiles around {:?} cannot be reached from any spawn",
+                    region.len(),
+                    first
+                ),
+            )
+            .at(map, *first),
+        );
+    }
+}
+
+/// every walkable cell reached from `starts` that is not in `seen` yet, going through pads
+/// and teleporters when `jumps` is set
+fn flood(
+    map: &MapFile,
+    starts: &[(i32, i32)],
+    jumps: bool,
+    seen: &mut HashSet<(i32, i32)>,
+) -> Vec<(i32, i32)> {
+    let mut region = starts
+        .iter()
+        .copied()
+        .filter(|start| seen.insert(*start))
+        .collect::<Vec<_>>();
+    let mut queue = VecDeque::from(region.clone());
+
+    while let Some(cell) = queue.pop_front() {
+        // teleporters and pads lead further than the next cell
+        let exits = match jumps {
+            true => [
+                MapFile::teleporter_exit(&map.entities, cell),
+                MapFile::pad_target(&map.entities, cell),
+            ],
+            false => [None, None],
+        };
+        let exits = exits
+            .into_iter()
+            .flatten()
+            .filter_map(|next| Some((next, map.tiles.get(next)?)));
+
+        for (next, tile) in map.tiles.adjacent(cell).chain(exits) {
+            if !tile.is_solid() && seen.insert(next) {
+                region.push(next);
+                queue.push_back(next);
+            }
+        }
+    }
+
+    region
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a map with the given sections above a grid
+    fn map(sections: &str, grid: &str) -> String {
+        format!("piw-pew map 2\n{}\n[grid]\n{}\n", sections, grid)
+    }
+
+    fn codes(source: &str) -> Vec<(Severity, &'static str)> {
+        lint("test.map", source)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.severity, diagnostic.code))
+            .collect()
+    }
+
+    #[test]
+    fn accepts_a_clean_map() {
+        assert!(codes(&map("[spawns]\n0,0\n2,1", "...\n...")).is_empty());
+    }
+
+    #[test]
+    fn reports_parse_errors_alone() {
+        assert_eq!(
+            codes("piw-pew map 2\n[legend]\n. = NOPE\n[grid]\n."),
+            vec![(Severity::ERROR, "parse")]
+        );
+
+        let diagnostics = lint("test.map", &map("[spawns]\n0,0", "..?"));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, "parse");
+        assert_eq!(
+            (diagnostics[0].line, diagnostics[0].column),
+            (Some(5), Some(3))
+        );
+    }
+
+    #[test]
+    fn warns_about_unknown_symbols_in_v1_maps() {
+        let diagnostics = lint("old.map", "...\n.?.\n");
+
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|diagnostic| (diagnostic.severity, diagnostic.code))
+                .collect::<Vec<_>>(),
+            vec![
+                (Severity::WARNING, "unknown-symbol"),
+                (Severity::WARNING, "no-spawns")
+            ]
+        );
+        assert_eq!(
+            (diagnostics[0].line, diagnostics[0].column),
+            (Some(2), Some(2))
+        );
+    }
+
+    #[test]
+    fn reports_maps_without_ground() {
+        assert_eq!(codes(&map("", "ST")), vec![(Severity::ERROR, "no-ground")]);
+    }
+
+    #[test]
+    fn warns_about_missing_spawns() {
+        assert_eq!(
+            codes(&map("", "...")),
+            vec![(Severity::WARNING, "no-spawns")]
+        );
+        assert_eq!(
+            codes(&map("[spawns]\n0,0 RED", "...")),
+            vec![(Severity::WARNING, "team-spawns")]
+        );
+        // neutral spawns serve both teams
+        assert!(codes(&map("[spawns]\n0,0 RED\n2,0", "...")).is_empty());
+    }
+
+    #[test]
+    fn reports_entities_on_walls_or_off_the_grid() {
+        let diagnostics = lint(
+            "test.map",
+            &map(
+                "[spawns]\n0,0\n[entities]\nPICKUP 1,0 health\nPICKUP 9,9 ammo",
+                ".S.\n...",
+            ),
+        );
+        let placements = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.code == "entity-placement")
+            .collect::<Vec<_>>();
+
+        assert_eq!(placements.len(), 2);
+        assert!(placements
+            .iter()
+            .any(|diagnostic| (diagnostic.line, diagnostic.column) == (Some(8), Some(2))));
+    }
+
+    #[test]
+    fn reports_broken_pads_and_teleporters() {
+        assert_eq!(
+            codes(&map("[spawns]\n0,0\n[entities]\nPAD 0,0 5,0", "...")),
+            vec![(Severity::ERROR, "pad-target")]
+        );
+        assert_eq!(
+            codes(&map("[spawns]\n0,0\n[entities]\nPAD 0,0 1,0", ".S.")),
+            vec![
+                (Severity::ERROR, "pad-target"),
+                (Severity::WARNING, "unreachable")
+            ]
+        );
+        assert_eq!(
+            codes(&map("[spawns]\n0,0\n[entities]\nTELEPORTER 2,0 a", "...")),
+            vec![(Severity::ERROR, "teleporters")]
+        );
+    }
+
+    #[test]
+    fn checks_flag_bases_and_mode_requirements() {
+        assert_eq!(
+            codes(&map("[spawns]\n0,0\n[entities]\nFLAG 2,0 RED", "...")),
+            vec![(Severity::WARNING, "flag-bases")]
+        );
+        assert_eq!(
+            codes(&map("modes = KING_OF_THE_HILL\n[spawns]\n0,0", "...")),
+            vec![(Severity::ERROR, "mode-requirements")]
+        );
+        assert!(codes(&map("modes = KING_OF_THE_HILL\n[spawns]\n0,0", "..1")).is_empty());
+        assert_eq!(
+            codes(&map(
+                "players = 2-4\nmodes = LAST_MAN_STANDING\n[spawns]\n0,0\n2,0",
+                "..."
+            )),
+            vec![(Severity::WARNING, "mode-requirements")]
+        );
+    }
+
+    #[test]
+    fn warns_about_ground_cut_off_from_spawns() {
+        let diagnostics = lint("test.map", &map("[spawns]\n0,0", ".S."));
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, "unreachable");
+        assert_eq!(
+            (diagnostics[0].line, diagnostics[0].column),
+            (Some(5), Some(3))
+        );
+
+        // pads and teleporters lead across the wall
+        assert!(codes(&map("[spawns]\n0,0\n[entities]\nPAD 0,0 2,0", ".S.")).is_empty());
+        assert!(codes(&map(
+            "[spawns]\n0,0\n[entities]\nTELEPORTER 0,0 a\nTELEPORTER 2,0 a",
+            ".S."
+        ))
+        .is_empty());
+
+        // without spawns the largest area counts as reachable
+        let diagnostics = lint("test.map", &map("", "..S."));
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|diagnostic| (diagnostic.code, diagnostic.column))
+                .collect::<Vec<_>>(),
+            vec![("no-spawns", None), ("unreachable", Some(4))]
+        );
+    }
+
+    #[test]
+    fn reaches_ground_only_a_pad_leads_to() {
+        // the area above is scanned first but only entered from the spawn below
+        assert!(codes(&map(
+            "[spawns]\n0,2\n[entities]\nPAD 1,2 0,-2",
+            "...\nSSS\n..."
+        ))
+        .is_empty());
+    }
+
+    #[test]
+    fn warns_about_ground_that_pads_only_leave() {
+        // the closed area above throws players down, nobody gets back up
+        let diagnostics = lint(
+            "test.map",
+            &map("[spawns]\n0,2\n[entities]\nPAD 1,0 0,2", "...\nSSS\n..."),
+        );
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, "unreachable");
+        assert_eq!(
+            (diagnostics[0].line, diagnostics[0].column),
+            (Some(7), Some(1))
+        );
+    }
+
+    #[test]
+    fn sorts_errors_before_warnings() {
+        let diagnostics = codes(&map("[entities]\nPICKUP 5,0 ammo", "..."));
+
+        assert_eq!(
+            diagnostics,
+            vec![
+                (Severity::ERROR, "entity-placement"),
+                (Severity::WARNING, "no-spawns")
+            ]
+        );
+    }
+}
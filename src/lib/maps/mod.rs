@@ -0,0 +1,1013 @@
+mod autotile;
+mod delta;
+mod generator;
+mod image;
+mod layers;
+mod lint;
+mod tiled;
+mod transfer;
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+pub use autotile::*;
+pub use delta::*;
+pub use generator::*;
+pub use image::*;
+pub use layers::*;
+pub use lint::*;
+pub use tiled::*;
+pub use transfer::*;
+
+/// first line of every versioned map file
+pub static MAP_MAGIC: &str = "piw-pew map";
+pub static MAP_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct MapHeader {
+    pub name: String,
+    pub author: String,
+    /// grid size in tiles
+    pub width: i32,
+    pub height: i32,
+    /// recommended player count (min, max)
+    pub players: (u32, u32),
+    pub modes: Vec<GameMode>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct SpawnPoint {
+    pub position: (i32, i32),
+    pub team: Option<Team>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub enum MapEntityKind {
+    FLAG(Option<Team>),
+    PICKUP(String),
+    /// capture zone spanning (width, height) tiles
+    ZONE(i32, i32),
+    /// opened and closed by players next to it, starts open when `true`
+    DOOR(bool),
+    /// sends players to the other teleporter with the same name
+    TELEPORTER(String),
+    /// throws players stepping on it (x, y) tiles away, over walls and cover
+    PAD(i32, i32),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct MapEntity {
+    pub kind: MapEntityKind,
+    pub position: (i32, i32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapError {
+    /// 1 based line of the map file the error was found on
+    pub line: Option<usize>,
+    /// 1 based character column, only known for errors inside the grid
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl MapError {
+    fn at(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            line: Some(line),
+            column: None,
+            message: message.into(),
+        }
+    }
+
+    fn at_column(line: usize, column: usize, message: impl Into<String>) -> Self {
+        Self {
+            line: Some(line),
+            column: Some(column),
+            message: message.into(),
+        }
+    }
+
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            line: None,
+            column: None,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => {
+                write!(f, "line {}, column {}: {}", line, column, self.message)
+            }
+            (Some(line), None) => write!(f, "line {}: {}", line, self.message),
+            _ => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for MapError {}
+
+/// a parsed `.map` file, either a bare v1 grid or a v2 file with header and sections
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapFile {
+    pub version: u32,
+    pub header: MapHeader,
+    pub legend: BTreeMap<char, TileVariant>,
+    /// the collision layer
+    pub tiles: TileGrid,
+    /// visual layers drawn under and over it, the same size or missing
+    pub layers: BTreeMap<MapLayer, TileGrid>,
+    /// 1 based line of the first grid row
+    pub grid_line: usize,
+    pub spawns: Vec<SpawnPoint>,
+    pub entities: Vec<MapEntity>,
+}
+
+impl MapFile {
+    /// legend of v1 maps, any other character is ground as it always was
+    pub fn v1_legend() -> BTreeMap<char, TileVariant> {
+        BTreeMap::from([
+            ('.', TileVariant::GROUND),
+            ('S', TileVariant::WALL_SIDE),
+            ('T', TileVariant::WALL_TOP),
+        ])
+    }
+
+    /// legend of v2 maps that leave their `[legend]` out
+    pub fn default_legend() -> BTreeMap<char, TileVariant> {
+        BTreeMap::from([
+            ('.', TileVariant::GROUND),
+            ('W', TileVariant::WALL),
+            ('C', TileVariant::COVER),
+            ('~', TileVariant::WATER),
+            ('!', TileVariant::HAZARD),
+            ('X', TileVariant::CRATE),
+            ('B', TileVariant::BRICK),
+            ('S', TileVariant::WALL_SIDE),
+            ('T', TileVariant::WALL_TOP),
+            ('R', TileVariant::ROOF),
+            ('%', TileVariant::FOLIAGE),
+            (',', TileVariant::DEBRIS),
+        ])
+    }
+
+    pub fn parse(name: &str, source: &str) -> Result<Self, MapError> {
+        let (map, errors) = Self::parse_recovering(name, source)?;
+        // v1 maps never had a legend to check against, anything unknown loads as ground
+        if map.version == 1 {
+            return Ok(map);
+        }
+
+        match errors.into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok(map),
+        }
+    }
+
+    /// like `parse`, but problems that leave the rest of the file readable are collected
+    /// instead of stopping at the first one, for v1 maps the symbols read as ground
+    pub fn parse_recovering(name: &str, source: &str) -> Result<(Self, Vec<MapError>), MapError> {
+        match source.lines().next() {
+            Some(first) if first.starts_with(MAP_MAGIC) => Self::parse_v2(name, source),
+            _ => Self::parse_v1(name, source),
+        }
+    }
+
+    fn parse_v1(name: &str, source: &str) -> Result<(Self, Vec<MapError>), MapError> {
+        let legend = Self::v1_legend();
+        let (tiles, zones, errors) = parse_grid(source.lines().enumerate(), &legend)?;
+
+        let (width, height) = tiles.size();
+        let map = Self {
+            version: 1,
+            header: MapHeader {
+                name: name.trim_end_matches(".map").to_string(),
+                author: String::new(),
+                width,
+                height,
+                players: (2, SERVER_MAX_CLIENTS as u32),
+                modes: Vec::new(),
+            },
+            legend,
+            tiles,
+            layers: BTreeMap::new(),
+            grid_line: 1,
+            spawns: Vec::new(),
+            entities: zones,
+        };
+
+        Ok((map, errors))
+    }
+
+    fn parse_v2(name: &str, source: &str) -> Result<(Self, Vec<MapError>), MapError> {
+        let mut lines = source.lines().enumerate();
+
+        let (_, magic) = lines.next().unwrap();
+        let version = magic[MAP_MAGIC.len()..]
+            .trim()
+            .parse::<u32>()
+            .map_err(|_| MapError::at(1, "missing map version"))?;
+        if version != MAP_VERSION {
+            return Err(MapError::at(
+                1,
+                format!("unsupported map version {}", version),
+            ));
+        }
+
+        let mut header = MapHeader {
+            name: name.trim_end_matches(".map").to_string(),
+            author: String::new(),
+            width: 0,
+            height: 0,
+            players: (2, SERVER_MAX_CLIENTS as u32),
+            modes: Vec::new(),
+        };
+        let mut legend = BTreeMap::new();
+        let mut spawns = Vec::new();
+        // line each spawn was declared on
+        let mut spawn_lines = Vec::new();
+        let mut entities = Vec::new();
+        // rows of every visual layer section, parsed once the legend is known
+        let mut layer_rows: BTreeMap<MapLayer, Vec<(usize, &str)>> = BTreeMap::new();
+        let mut section = String::from("header");
+        let mut grid_line = 0;
+
+        for (index, line) in lines.by_ref() {
+            let number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if line.starts_with('[') && line.ends_with(']') {
+                section = line[1..line.len() - 1].to_lowercase();
+                if section == "grid" {
+                    grid_line = number + 1;
+                    break;
+                }
+
+                continue;
+            }
+
+            match section.as_str() {
+                "header" => parse_header(&mut header, line, number)?,
+                "legend" => {
+                    let (symbol, tile) = split_pair(line, '=', number)?;
+                    let mut chars = symbol.chars();
+                    let (Some(symbol), None) = (chars.next(), chars.next()) else {
+                        return Err(MapError::at(number, "legend symbols are single characters"));
+                    };
+                    if symbol.is_ascii_digit() {
+                        return Err(MapError::at(number, "digits are reserved for zones"));
+                    }
+                    if symbol == LAYER_EMPTY {
+                        return Err(MapError::at(
+                            number,
+                            format!("`{}` is reserved for empty layer cells", LAYER_EMPTY),
+                        ));
+                    }
+
+                    let tile = tile
+                        .parse::<TileVariant>()
+                        .map_err(|_| MapError::at(number, format!("unknown tile `{}`", tile)))?;
+                    legend.insert(symbol, tile);
+                }
+                "spawns" => {
+                    let mut parts = line.split_whitespace();
+                    let position = parse_point(parts.next().unwrap(), number)?;
+                    let team = parts
+                        .next()
+                        .map(|team| parse_team(team, number))
+                        .transpose()?;
+                    spawns.push(SpawnPoint { position, team });
+                    spawn_lines.push(number);
+                }
+                "entities" => entities.push(parse_entity(line, number)?),
+                section => match MapLayer::VISUAL
+                    .into_iter()
+                    .find(|layer| layer.section() == section)
+                {
+                    Some(layer) => layer_rows.entry(layer).or_default().push((index, line)),
+                    None => {
+                        return Err(MapError::at(
+                            number,
+                            format!("unknown section `{}`", section),
+                        ))
+                    }
+                },
+            }
+        }
+
+        if section != "grid" {
+            return Err(MapError::new("missing [grid] section"));
+        }
+        if legend.is_empty() {
+            legend = Self::default_legend();
+        }
+
+        let (tiles, zones, mut errors) = parse_grid(lines, &legend)?;
+        entities.extend(zones);
+
+        let (width, height) = tiles.size();
+        if (header.width, header.height) == (0, 0) {
+            (header.width, header.height) = (width, height);
+        } else if (header.width, header.height) != (width, height) {
+            errors.push(MapError::new(format!(
+                "header size {}x{} does not match the {}x{} grid",
+                header.width, header.height, width, height
+            )));
+        }
+
+        let mut layers = BTreeMap::new();
+        for (layer, rows) in layer_rows {
+            let grid_line = rows[0].0 + 1;
+            let (grid, layer_errors) = parse_layer(rows.into_iter(), &legend)?;
+            errors.extend(layer_errors);
+
+            if grid.size() != (width, height) {
+                errors.push(MapError::at(
+                    grid_line,
+                    format!(
+                        "{} layer is {}x{} but the grid is {}x{}",
+                        layer.section(),
+                        grid.width(),
+                        grid.height(),
+                        width,
+                        height
+                    ),
+                ));
+            }
+            layers.insert(layer, grid);
+        }
+
+        for (spawn, line) in spawns.iter().zip(spawn_lines) {
+            if tiles.get(spawn.position) != Some(TileVariant::GROUND) {
+                errors.push(MapError::at(
+                    line,
+                    format!("spawn {:?} is not on a ground tile", spawn.position),
+                ));
+            }
+        }
+
+        let map = Self {
+            version,
+            header,
+            legend,
+            tiles,
+            layers,
+            grid_line,
+            spawns,
+            entities,
+        };
+
+        Ok((map, errors))
+    }
+
+    /// writes the map back as a v2 file, zones drawn with digits come out as `ZONE` entities
+    pub fn to_source(&self) -> String {
+        let mut legend = self.legend.clone();
+        let layer_tiles = self.layers.values().flat_map(TileGrid::iter);
+        for (_, tile) in self.tiles.iter().chain(layer_tiles) {
+            if !legend.values().any(|variant| *variant == tile) {
+                let symbol = Self::default_legend()
+                    .into_iter()
+                    .find(|(symbol, variant)| *variant == tile && !legend.contains_key(symbol))
+                    .map(|(symbol, _)| symbol)
+                    .or_else(|| ('a'..='z').find(|symbol| !legend.contains_key(symbol)))
+                    .expect("ran out of legend symbols");
+                legend.insert(symbol, tile);
+            }
+        }
+        let symbol_of = |tile: TileVariant| {
+            legend
+                .iter()
+                .find(|(_, variant)| **variant == tile)
+                .map(|(symbol, _)| *symbol)
+        };
+        let ground = symbol_of(TileVariant::GROUND).unwrap_or('.');
+
+        let header = &self.header;
+        let mut lines = vec![
+            format!("{} {}", MAP_MAGIC, MAP_VERSION),
+            format!("name = {}", header.name),
+        ];
+        if !header.author.is_empty() {
+            lines.push(format!("author = {}", header.author));
+        }
+        lines.push(format!(
+            "size = {}x{}",
+            self.tiles.width(),
+            self.tiles.height()
+        ));
+        lines.push(match header.players {
+            (min, max) if min == max => format!("players = {}", min),
+            (min, max) => format!("players = {}-{}", min, max),
+        });
+        if !header.modes.is_empty() {
+            let modes = header.modes.iter().map(GameMode::to_string);
+            lines.push(format!("modes = {}", modes.collect::<Vec<_>>().join(", ")));
+        }
+
+        lines.push(String::from("\n[legend]"));
+        for (symbol, variant) in &legend {
+            lines.push(format!("{} = {}", symbol, variant));
+        }
+
+        if !self.spawns.is_empty() {
+            lines.push(String::from("\n[spawns]"));
+            for spawn in &self.spawns {
+                let (x, y) = spawn.position;
+                lines.push(match spawn.team {
+                    Some(team) => format!("{},{} {}", x, y, team),
+                    None => format!("{},{}", x, y),
+                });
+            }
+        }
+
+        if !self.entities.is_empty() {
+            lines.push(String::from("\n[entities]"));
+            for entity in &self.entities {
+                let (x, y) = entity.position;
+                lines.push(match &entity.kind {
+                    MapEntityKind::FLAG(Some(team)) => format!("FLAG {},{} {}", x, y, team),
+                    MapEntityKind::FLAG(None) => format!("FLAG {},{}", x, y),
+                    MapEntityKind::PICKUP(item) => format!("PICKUP {},{} {}", x, y, item),
+                    MapEntityKind::ZONE(w, h) => format!("ZONE {},{} {}x{}", x, y, w, h),
+                    MapEntityKind::DOOR(true) => format!("DOOR {},{} OPEN", x, y),
+                    MapEntityKind::DOOR(false) => format!("DOOR {},{}", x, y),
+                    MapEntityKind::TELEPORTER(name) => {
+                        format!("TELEPORTER {},{} {}", x, y, name)
+                    }
+                    MapEntityKind::PAD(dx, dy) => format!("PAD {},{} {},{}", x, y, dx, dy),
+                });
+            }
+        }
+
+        for (layer, grid) in &self.layers {
+            lines.push(format!("\n[{}]", layer.section()));
+            for y in 0..grid.height() {
+                lines.push(
+                    (0..grid.width())
+                        .map(|x| grid.get((x, y)).and_then(symbol_of).unwrap_or(LAYER_EMPTY))
+                        .collect(),
+                );
+            }
+        }
+
+        lines.push(String::from("\n[grid]"));
+        for y in 0..self.tiles.height() {
+            lines.push(
+                (0..self.tiles.width())
+                    .map(|x| self.tiles.get((x, y)).and_then(symbol_of).unwrap_or(ground))
+                    .collect(),
+            );
+        }
+
+        lines.join("\n") + "\n"
+    }
+
+    /// where a teleporter sends players, the other one sharing its name
+    pub fn teleporter_exit(entities: &[MapEntity], position: (i32, i32)) -> Option<(i32, i32)> {
+        let name = entities.iter().find_map(|entity| match &entity.kind {
+            MapEntityKind::TELEPORTER(name) if entity.position == position => Some(name),
+            _ => None,
+        })?;
+
+        entities
+            .iter()
+            .find(|entity| {
+                entity.kind == MapEntityKind::TELEPORTER(name.clone())
+                    && entity.position != position
+            })
+            .map(|entity| entity.position)
+    }
+
+    /// where a pad throws players
+    pub fn pad_target(entities: &[MapEntity], position: (i32, i32)) -> Option<(i32, i32)> {
+        entities.iter().find_map(|entity| match entity.kind {
+            MapEntityKind::PAD(dx, dy) if entity.position == position => {
+                Some((position.0 + dx, position.1 + dy))
+            }
+            _ => None,
+        })
+    }
+
+    /// capture zones as grid rectangles (x, y, width, height)
+    pub fn zones(&self) -> Vec<(i32, i32, i32, i32)> {
+        self.entities
+            .iter()
+            .filter_map(|entity| match entity.kind {
+                MapEntityKind::ZONE(w, h) => Some((entity.position.0, entity.position.1, w, h)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+fn parse_header(header: &mut MapHeader, line: &str, number: usize) -> Result<(), MapError> {
+    let (key, value) = split_pair(line, '=', number)?;
+
+    match key {
+        "name" => header.name = value.to_string(),
+        "author" => header.author = value.to_string(),
+        "size" => {
+            let (width, height) = split_pair(value, 'x', number)?;
+            header.width = parse_number(width, number)?;
+            header.height = parse_number(height, number)?;
+        }
+        "players" => {
+            header.players = match value.split_once('-') {
+                Some((min, max)) => (parse_number(min, number)?, parse_number(max, number)?),
+                None => {
+                    let count = parse_number(value, number)?;
+                    (count, count)
+                }
+            };
+        }
+        "modes" => {
+            header.modes = value
+                .split(',')
+                .map(|mode| {
+                    mode.trim().parse::<GameMode>().map_err(|_| {
+                        MapError::at(number, format!("unknown mode `{}`", mode.trim()))
+                    })
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        _ => {
+            return Err(MapError::at(
+                number,
+                format!("unknown header field `{}`", key),
+            ))
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_entity(line: &str, number: usize) -> Result<MapEntity, MapError> {
+    let mut parts = line.split_whitespace();
+    let kind = parts.next().unwrap();
+    let position = parse_point(
+        parts
+            .next()
+            .ok_or_else(|| MapError::at(number, "entities need a position"))?,
+        number,
+    )?;
+    let argument = parts.next();
+
+    let kind = match kind {
+        "FLAG" => MapEntityKind::FLAG(argument.map(|team| parse_team(team, number)).transpose()?),
+        "PICKUP" => MapEntityKind::PICKUP(
+            argument
+                .ok_or_else(|| MapError::at(number, "pickups need an item"))?
+                .to_string(),
+        ),
+        "ZONE" => {
+            let (w, h) = match argument {
+                Some(size) => {
+                    let (w, h) = split_pair(size, 'x', number)?;
+                    (parse_number(w, number)?, parse_number(h, number)?)
+                }
+                None => (1, 1),
+            };
+            MapEntityKind::ZONE(w, h)
+        }
+        "DOOR" => match argument {
+            Some("OPEN") => MapEntityKind::DOOR(true),
+            None => MapEntityKind::DOOR(false),
+            Some(state) => {
+                return Err(MapError::at(
+                    number,
+                    format!("doors are closed or `OPEN`, not `{}`", state),
+                ))
+            }
+        },
+        "TELEPORTER" => MapEntityKind::TELEPORTER(
+            argument
+                .ok_or_else(|| MapError::at(number, "teleporters need a name to pair them"))?
+                .to_string(),
+        ),
+        "PAD" => {
+            let (dx, dy) = parse_point(
+                argument.ok_or_else(|| MapError::at(number, "pads need a distance"))?,
+                number,
+            )?;
+            MapEntityKind::PAD(dx, dy)
+        }
+        _ => return Err(MapError::at(number, format!("unknown entity `{}`", kind))),
+    };
+
+    Ok(MapEntity { kind, position })
+}
+
+/// reads the character grid, digits mark the tiles of a capture zone, symbols missing from
+/// the legend are reported and read as ground
+fn parse_grid<'a>(
+    lines: impl Iterator<Item = (usize, &'a str)>,
+    legend: &BTreeMap<char, TileVariant>,
+) -> Result<(TileGrid, Vec<MapEntity>, Vec<MapError>), MapError> {
+    let mut errors = Vec::new();
+    let mut rows: Vec<Vec<TileVariant>> = Vec::new();
+    // zone digit -> (min x, min y, max x, max y)
+    let mut zones: BTreeMap<u32, (i32, i32, i32, i32)> = BTreeMap::new();
+
+    for (y, (index, line)) in lines.enumerate() {
+        let mut row = Vec::new();
+        for (x, symbol) in line.chars().enumerate() {
+            let (x, y) = (x as i32, y as i32);
+
+            let tile = if let Some(zone) = symbol.to_digit(10) {
+                let bounds = zones.entry(zone).or_insert((x, y, x, y));
+                *bounds = (
+                    bounds.0.min(x),
+                    bounds.1.min(y),
+                    bounds.2.max(x),
+                    bounds.3.max(y),
+                );
+                TileVariant::GROUND
+            } else if let Some(tile) = legend.get(&symbol) {
+                *tile
+            } else {
+                errors.push(MapError::at_column(
+                    index + 1,
+                    x as usize + 1,
+                    format!("`{}` is not in the legend", symbol),
+                ));
+                TileVariant::GROUND
+            };
+
+            row.push(tile);
+        }
+        rows.push(row);
+    }
+
+    // trailing blank lines are not part of the grid
+    while rows.last().is_some_and(Vec::is_empty) {
+        rows.pop();
+    }
+
+    let mut tiles = grid_for(&rows)?;
+    for (y, row) in rows.into_iter().enumerate() {
+        for (x, tile) in row.into_iter().enumerate() {
+            tiles.set((x as i32, y as i32), Some(tile));
+        }
+    }
+
+    autotile(&mut tiles);
+
+    let zones = zones
+        .into_values()
+        .map(|(x0, y0, x1, y1)| MapEntity {
+            kind: MapEntityKind::ZONE(x1 - x0 + 1, y1 - y0 + 1),
+            position: (x0, y0),
+        })
+        .collect();
+
+    Ok((tiles, zones, errors))
+}
+
+/// rows of a visual layer, `LAYER_EMPTY` leaves a cell empty
+fn parse_layer<'a>(
+    lines: impl Iterator<Item = (usize, &'a str)>,
+    legend: &BTreeMap<char, TileVariant>,
+) -> Result<(TileGrid, Vec<MapError>), MapError> {
+    let mut errors = Vec::new();
+    let mut rows: Vec<Vec<Option<TileVariant>>> = Vec::new();
+
+    for (index, line) in lines {
+        let row = line
+            .chars()
+            .enumerate()
+            .map(|(x, symbol)| match legend.get(&symbol) {
+                _ if symbol == LAYER_EMPTY => None,
+                Some(tile) => Some(*tile),
+                None => {
+                    errors.push(MapError::at_column(
+                        index + 1,
+                        x + 1,
+                        format!("`{}` is not in the legend", symbol),
+                    ));
+                    None
+                }
+            })
+            .collect();
+        rows.push(row);
+    }
+
+    let mut tiles = grid_for(&rows)?;
+    for (y, row) in rows.into_iter().enumerate() {
+        for (x, tile) in row.into_iter().enumerate() {
+            tiles.set((x as i32, y as i32), tile);
+        }
+    }
+
+    autotile(&mut tiles);
+    Ok((tiles, errors))
+}
+
+/// an empty grid as wide as the longest row
+fn grid_for<T>(rows: &[Vec<T>]) -> Result<TileGrid, MapError> {
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let size = |length: usize| i32::try_from(length).unwrap_or(i32::MAX);
+    TileGrid::new(size(width), size(rows.len())).map_err(MapError::new)
+}
+
+fn split_pair(text: &str, separator: char, number: usize) -> Result<(&str, &str), MapError> {
+    text.split_once(separator)
+        .map(|(a, b)| (a.trim(), b.trim()))
+        .ok_or_else(|| MapError::at(number, format!("expected `{}` in `{}`", separator, text)))
+}
+
+fn parse_number<T: std::str::FromStr>(text: &str, number: usize) -> Result<T, MapError> {
+    text.trim()
+        .parse::<T>()
+        .map_err(|_| MapError::at(number, format!("`{}` is not a number", text.trim())))
+}
+
+fn parse_point(text: &str, number: usize) -> Result<(i32, i32), MapError> {
+    let (x, y) = split_pair(text, ',', number)?;
+    Ok((parse_number(x, number)?, parse_number(y, number)?))
+}
+
+fn parse_team(text: &str, number: usize) -> Result<Team, MapError> {
+    text.parse::<Team>()
+        .map_err(|_| MapError::at(number, format!("unknown team `{}`", text)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static SOURCE: &str = "piw-pew map 2
+name = Test
+author = someone
+size = 5x4
+players = 2-4
+modes = DEATHMATCH, KING_OF_THE_HILL
+
+[legend]
+. = GROUND
+S = WALL_SIDE
+T = WALL_TOP
+, = DEBRIS
+
+[spawns]
+0,0 RED
+4,3
+
+[entities]
+PICKUP 4,0 health
+FLAG 0,3 BLUE
+DOOR 1,3 OPEN
+TELEPORTER 0,2 a
+TELEPORTER 4,2 a
+PAD 3,3 -2,-2
+
+[floor]
+,,___
+_____
+_____
+____,
+
+[grid]
+..T..
+.SS..
+..1..
+.....
+";
+
+    #[test]
+    fn parses_header_sections_and_grid() {
+        let map = MapFile::parse("test.map", SOURCE).unwrap();
+
+        assert_eq!(map.version, 2);
+        assert_eq!(map.header.name, "Test");
+        assert_eq!(map.header.author, "someone");
+        assert_eq!((map.header.width, map.header.height), (5, 4));
+        assert_eq!(map.header.players, (2, 4));
+        assert_eq!(
+            map.header.modes,
+            vec![GameMode::DEATHMATCH, GameMode::KING_OF_THE_HILL]
+        );
+
+        assert_eq!(map.tiles.get((2, 0)), Some(TileVariant::WALL_TOP));
+        assert_eq!(map.tiles.get((1, 1)), Some(TileVariant::WALL_SIDE));
+        // zone digits stand on ground
+        assert_eq!(map.tiles.get((2, 2)), Some(TileVariant::GROUND));
+
+        assert_eq!(
+            map.spawns,
+            vec![
+                SpawnPoint {
+                    position: (0, 0),
+                    team: Some(Team::RED)
+                },
+                SpawnPoint {
+                    position: (4, 3),
+                    team: None
+                },
+            ]
+        );
+        assert_eq!(
+            map.entities,
+            vec![
+                MapEntity {
+                    kind: MapEntityKind::PICKUP(String::from("health")),
+                    position: (4, 0),
+                },
+                MapEntity {
+                    kind: MapEntityKind::FLAG(Some(Team::BLUE)),
+                    position: (0, 3),
+                },
+                MapEntity {
+                    kind: MapEntityKind::DOOR(true),
+                    position: (1, 3),
+                },
+                MapEntity {
+                    kind: MapEntityKind::TELEPORTER(String::from("a")),
+                    position: (0, 2),
+                },
+                MapEntity {
+                    kind: MapEntityKind::TELEPORTER(String::from("a")),
+                    position: (4, 2),
+                },
+                MapEntity {
+                    kind: MapEntityKind::PAD(-2, -2),
+                    position: (3, 3),
+                },
+                MapEntity {
+                    kind: MapEntityKind::ZONE(1, 1),
+                    position: (2, 2),
+                },
+            ]
+        );
+        assert_eq!(map.zones(), vec![(2, 2, 1, 1)]);
+
+        let floor = &map.layers[&MapLayer::FLOOR];
+        assert_eq!(map.layers.len(), 1);
+        assert_eq!(floor.get((0, 0)), Some(TileVariant::DEBRIS));
+        assert_eq!(floor.get((2, 0)), None);
+        assert_eq!(floor.get((4, 3)), Some(TileVariant::DEBRIS));
+    }
+
+    #[test]
+    fn finds_teleporter_exits_and_pad_targets() {
+        let map = MapFile::parse("test.map", SOURCE).unwrap();
+
+        assert_eq!(
+            MapFile::teleporter_exit(&map.entities, (0, 2)),
+            Some((4, 2))
+        );
+        assert_eq!(
+            MapFile::teleporter_exit(&map.entities, (4, 2)),
+            Some((0, 2))
+        );
+        assert_eq!(MapFile::teleporter_exit(&map.entities, (1, 2)), None);
+        assert_eq!(MapFile::pad_target(&map.entities, (3, 3)), Some((1, 1)));
+        assert_eq!(MapFile::pad_target(&map.entities, (0, 2)), None);
+    }
+
+    #[test]
+    fn round_trips_through_source() {
+        let map = MapFile::parse("test.map", SOURCE).unwrap();
+        let source = map.to_source();
+        let reparsed = MapFile::parse("test.map", &source).unwrap();
+
+        assert_eq!(reparsed.header, map.header);
+        assert_eq!(reparsed.tiles, map.tiles);
+        assert_eq!(reparsed.layers, map.layers);
+        assert_eq!(reparsed.spawns, map.spawns);
+        assert_eq!(reparsed.entities, map.entities);
+        assert_eq!(reparsed.to_source(), source);
+    }
+
+    #[test]
+    fn reads_v1_grids_with_the_old_legend() {
+        let map = MapFile::parse("old.map", "..S\nT~W\n").unwrap();
+
+        assert_eq!(map.version, 1);
+        assert_eq!(map.header.name, "old");
+        assert_eq!((map.header.width, map.header.height), (3, 2));
+        assert_eq!(map.tiles.get((2, 0)), Some(TileVariant::WALL_SIDE));
+        assert_eq!(map.tiles.get((0, 1)), Some(TileVariant::WALL_TOP));
+        // symbols v1 never knew stay ground
+        assert_eq!(map.tiles.get((1, 1)), Some(TileVariant::GROUND));
+        assert_eq!(map.tiles.get((2, 1)), Some(TileVariant::GROUND));
+    }
+
+    #[test]
+    fn reports_symbols_v1_grids_do_not_know() {
+        let (map, errors) = MapFile::parse_recovering("old.map", "...\n.W.\n").unwrap();
+
+        assert_eq!(map.tiles.get((1, 1)), Some(TileVariant::GROUND));
+        assert_eq!(
+            errors.iter().map(|err| err.to_string()).collect::<Vec<_>>(),
+            vec!["line 2, column 2: `W` is not in the legend"]
+        );
+    }
+
+    #[test]
+    fn merges_zone_digits_into_rectangles() {
+        let map = MapFile::parse("zones", "11.\n1.2\n..2\n").unwrap();
+        assert_eq!(map.zones(), vec![(0, 0, 2, 2), (2, 1, 1, 2)]);
+    }
+
+    #[test]
+    fn rejects_broken_files() {
+        let err = MapFile::parse("x", "piw-pew map 9\n[grid]\n.").unwrap_err();
+        assert_eq!(err.line, Some(1));
+
+        let err = MapFile::parse("x", "piw-pew map 2\n[nope]\nx\n[grid]\n.").unwrap_err();
+        assert_eq!(err.line, Some(3));
+
+        let err = MapFile::parse("x", "piw-pew map 2\n[grid]\n..\n.?").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 4, column 2: `?` is not in the legend"
+        );
+
+        let source = "piw-pew map 2\n[entities]\nDOOR 0,0 SHUT\n[grid]\n.";
+        let err = MapFile::parse("x", source).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 3: doors are closed or `OPEN`, not `SHUT`"
+        );
+
+        let err = MapFile::parse("x", "piw-pew map 2\nname = x\n").unwrap_err();
+        assert_eq!(err.message, "missing [grid] section");
+
+        let err = MapFile::parse("x", "piw-pew map 2\nsize = 3x3\n[grid]\n..\n").unwrap_err();
+        assert_eq!(err.message, "header size 3x3 does not match the 2x1 grid");
+
+        let source = format!("piw-pew map 2\n[grid]\n{}\n", ".".repeat(5000));
+        let err = MapFile::parse("x", &source).unwrap_err();
+        assert_eq!(
+            err.message,
+            "a 5000x1 map is larger than the maximum of 4096x4096"
+        );
+    }
+
+    #[test]
+    fn rejects_spawns_off_the_ground() {
+        let source = "piw-pew map 2\n[spawns]\n1,0\n[grid]\n.S.\n";
+        let err = MapFile::parse("x", source).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 3: spawn (1, 0) is not on a ground tile"
+        );
+    }
+
+    #[test]
+    fn collects_recoverable_errors() {
+        let source = "piw-pew map 2
+size = 3x3
+
+[spawns]
+1,0
+
+[grid]
+.S.
+..?
+";
+        let (map, errors) = MapFile::parse_recovering("x", source).unwrap();
+
+        assert_eq!(map.grid_line, 8);
+        assert_eq!(map.tiles.size(), (3, 2));
+        assert_eq!(map.tiles.get((2, 1)), Some(TileVariant::GROUND));
+        assert_eq!(
+            errors.iter().map(|err| err.to_string()).collect::<Vec<_>>(),
+            vec![
+                "line 9, column 3: `?` is not in the legend",
+                "header size 3x3 does not match the 3x2 grid",
+                "line 5: spawn (1, 0) is not on a ground tile",
+            ]
+        );
+    }
+
+    #[test]
+    fn reserves_digits_and_the_empty_layer_symbol() {
+        for symbol in ['1', LAYER_EMPTY] {
+            let source = format!("piw-pew map 2\n[legend]\n{} = WALL\n[grid]\n.", symbol);
+            let err = MapFile::parse("x", &source).unwrap_err();
+            assert_eq!(err.line, Some(3));
+        }
+    }
+
+    #[test]
+    fn rejects_layers_that_do_not_cover_the_grid() {
+        let source = "piw-pew map 2\n[overhead]\nR\n[grid]\n..\n";
+        let err = MapFile::parse("x", source).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 3: overhead layer is 1x1 but the grid is 2x1"
+        );
+    }
+}
@@ -0,0 +1,732 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use base64::Engine;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use serde_json::Value;
+
+use crate::prelude::*;
+
+/// flip and rotation flags Tiled keeps in the high bits of a gid
+static GID_MASK: u32 = 0x0fff_ffff;
+
+/// everything the importer needs from a Tiled map, whichever format it was saved in
+#[derive(Debug, Default)]
+struct TiledMap {
+    width: i32,
+    height: i32,
+    tile_width: f32,
+    tile_height: f32,
+    properties: HashMap<String, String>,
+    /// gid to variant, from the `variant` property of tileset tiles
+    variants: HashMap<u32, TileVariant>,
+    /// gids of every tile layer and the map layer its name or class points to,
+    /// the collision grid when it points to none
+    tile_layers: Vec<(Option<MapLayer>, Vec<u32>)>,
+    objects: Vec<TiledObject>,
+}
+
+#[derive(Debug, Default)]
+struct TiledObject {
+    /// class (type in older Tiled versions), the name when there is none
+    kind: String,
+    name: String,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    gid: Option<u32>,
+    properties: HashMap<String, String>,
+}
+
+/// converts a Tiled map (`.tmx`, or `.json`/`.tmj`) into the server's map format
+///
+/// tiles pick their variant from a `variant` property set on the tileset, `overrides`
+/// maps gids directly and takes precedence; objects classed `spawn`, `flag`, `zone`,
+/// `pickup`, `door`, `teleporter` or `pad` become spawn points and entities, with optional
+/// `team`, `item`, `open`, `pair` and `dx`/`dy` properties; tile layers named or classed
+/// `floor`, `decoration` or `overhead` become visual layers, the others the collision grid
+pub fn import_tiled(
+    path: &Path,
+    overrides: &HashMap<u32, TileVariant>,
+) -> Result<MapFile, MapError> {
+    let source = fs::read_to_string(path)
+        .map_err(|err| MapError::new(format!("failed to read {}: {}", path.display(), err)))?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+
+    let mut tiled = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("tmx") => read_tmx(&source, dir)?,
+        _ => read_json(&source, dir)?,
+    };
+    tiled.variants.extend(overrides);
+
+    if tiled.width <= 0 || tiled.height <= 0 {
+        return Err(MapError::new(format!(
+            "map size {}x{} has no cells",
+            tiled.width, tiled.height
+        )));
+    }
+    if tiled.width > MAP_MAX_SIZE || tiled.height > MAP_MAX_SIZE {
+        return Err(MapError::new(format!(
+            "map size {}x{} is larger than the maximum of {}x{}",
+            tiled.width, tiled.height, MAP_MAX_SIZE, MAP_MAX_SIZE
+        )));
+    }
+    if tiled.tile_width <= 0.0 || tiled.tile_height <= 0.0 {
+        return Err(MapError::new(format!(
+            "tile size {}x{} is not positive",
+            tiled.tile_width, tiled.tile_height
+        )));
+    }
+
+    let mut tiles =
+        TileGrid::filled(tiled.width, tiled.height, TileVariant::GROUND).map_err(MapError::new)?;
+    let empty = TileGrid::new(tiled.width, tiled.height).map_err(MapError::new)?;
+    let mut layers = BTreeMap::new();
+    for (layer, gids) in &tiled.tile_layers {
+        let grid = match layer {
+            Some(layer) => layers.entry(*layer).or_insert_with(|| empty.clone()),
+            None => &mut tiles,
+        };
+
+        for (index, gid) in gids.iter().enumerate() {
+            let gid = gid & GID_MASK;
+            if gid == 0 {
+                continue;
+            }
+
+            let variant = tiled.variants.get(&gid).copied().ok_or_else(|| {
+                MapError::new(format!(
+                    "tile {} has no `variant` property, map it with --tile {}=<VARIANT>",
+                    gid, gid
+                ))
+            })?;
+            let index = index as i32;
+            grid.set((index % tiled.width, index / tiled.width), Some(variant));
+        }
+    }
+
+    autotile(&mut tiles);
+    layers.values_mut().for_each(autotile);
+
+    let mut spawns = Vec::new();
+    let mut entities = Vec::new();
+    for object in &tiled.objects {
+        // tile objects are anchored at their bottom left corner
+        let top = if object.gid.is_some() {
+            object.y - object.height
+        } else {
+            object.y
+        };
+        let position = (
+            (object.x / tiled.tile_width).floor() as i32,
+            (top / tiled.tile_height).floor() as i32,
+        );
+        let team = object
+            .properties
+            .get("team")
+            .map(|team| {
+                team.to_uppercase()
+                    .parse::<Team>()
+                    .map_err(|_| MapError::new(format!("unknown team `{}`", team)))
+            })
+            .transpose()?;
+
+        match object.kind.to_lowercase().as_str() {
+            "spawn" => spawns.push(SpawnPoint { position, team }),
+            "flag" => entities.push(MapEntity {
+                kind: MapEntityKind::FLAG(team),
+                position,
+            }),
+            "zone" => entities.push(MapEntity {
+                kind: MapEntityKind::ZONE(
+                    ((object.width / tiled.tile_width).round() as i32).max(1),
+                    ((object.height / tiled.tile_height).round() as i32).max(1),
+                ),
+                position,
+            }),
+            "pickup" => entities.push(MapEntity {
+                kind: MapEntityKind::PICKUP(
+                    object
+                        .properties
+                        .get("item")
+                        .cloned()
+                        .unwrap_or_else(|| object.name.clone()),
+                ),
+                position,
+            }),
+            "door" => entities.push(MapEntity {
+                kind: MapEntityKind::DOOR(
+                    object
+                        .properties
+                        .get("open")
+                        .is_some_and(|open| open == "true"),
+                ),
+                position,
+            }),
+            "teleporter" => entities.push(MapEntity {
+                kind: MapEntityKind::TELEPORTER(
+                    object
+                        .properties
+                        .get("pair")
+                        .cloned()
+                        .unwrap_or_else(|| object.name.clone()),
+                ),
+                position,
+            }),
+            "pad" => {
+                let offset = |axis: &str| {
+                    let value = object.properties.get(axis).map_or("0", String::as_str);
+                    value.parse::<i32>().map_err(|_| {
+                        MapError::new(format!(
+                            "pad `{}` has an invalid {} of `{}`",
+                            object.name, axis, value
+                        ))
+                    })
+                };
+                entities.push(MapEntity {
+                    kind: MapEntityKind::PAD(offset("dx")?, offset("dy")?),
+                    position,
+                });
+            }
+            kind => log::warn!(
+                "skipping object `{}` of unknown class `{}`",
+                object.name,
+                kind
+            ),
+        }
+    }
+
+    let properties = &tiled.properties;
+    let mut header = MapHeader {
+        name: properties.get("name").cloned().unwrap_or_else(|| {
+            path.file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string()
+        }),
+        author: properties.get("author").cloned().unwrap_or_default(),
+        width: tiled.width,
+        height: tiled.height,
+        players: (2, SERVER_MAX_CLIENTS as u32),
+        modes: Vec::new(),
+    };
+    if let Some(modes) = properties.get("modes") {
+        header.modes = modes
+            .split(',')
+            .map(|mode| {
+                mode.trim()
+                    .parse::<GameMode>()
+                    .map_err(|_| MapError::new(format!("unknown mode `{}`", mode.trim())))
+            })
+            .collect::<Result<_, _>>()?;
+    }
+    if let Some(players) = properties.get("players") {
+        let count = |text: &str| {
+            text.trim()
+                .parse::<u32>()
+                .map_err(|_| MapError::new(format!("`{}` is not a player count", players)))
+        };
+        header.players = match players.split_once('-') {
+            Some((min, max)) => (count(min)?, count(max)?),
+            None => (count(players)?, count(players)?),
+        };
+    }
+
+    Ok(MapFile {
+        version: MAP_VERSION,
+        header,
+        legend: MapFile::default_legend(),
+        tiles,
+        layers,
+        grid_line: 0,
+        spawns,
+        entities,
+    })
+}
+
+fn read_json(source: &str, dir: &Path) -> Result<TiledMap, MapError> {
+    let map: Value = serde_json::from_str(source)
+        .map_err(|err| MapError::new(format!("malformed Tiled json: {}", err)))?;
+    if map["infinite"].as_bool() == Some(true) {
+        return Err(MapError::new("infinite Tiled maps are not supported"));
+    }
+
+    // saturated rather than truncated, so huge sizes are refused instead of wrapping around
+    let size = |value: &Value| value.as_i64().unwrap_or(0).clamp(0, i32::MAX as i64) as i32;
+    let mut tiled = TiledMap {
+        width: size(&map["width"]),
+        height: size(&map["height"]),
+        tile_width: map["tilewidth"].as_f64().unwrap_or(1.0) as f32,
+        tile_height: map["tileheight"].as_f64().unwrap_or(1.0) as f32,
+        properties: json_properties(&map["properties"]),
+        ..Default::default()
+    };
+
+    for tileset in map["tilesets"].as_array().into_iter().flatten() {
+        let first_gid = tileset["firstgid"].as_u64().unwrap_or(1) as u32;
+        match tileset["source"].as_str() {
+            Some(source) => read_tileset_file(&dir.join(source), first_gid, &mut tiled.variants)?,
+            None => json_tileset(tileset, first_gid, &mut tiled.variants)?,
+        }
+    }
+
+    json_layers(&map["layers"], &mut tiled)?;
+
+    Ok(tiled)
+}
+
+/// visual layer a Tiled layer stands for, `floor`, `decoration` or `overhead`
+fn map_layer(name: &str) -> Option<MapLayer> {
+    MapLayer::VISUAL
+        .into_iter()
+        .find(|layer| layer.section().eq_ignore_ascii_case(name.trim()))
+}
+
+/// layers in paint order, groups nest more layers
+fn json_layers(layers: &Value, tiled: &mut TiledMap) -> Result<(), MapError> {
+    for layer in layers.as_array().into_iter().flatten() {
+        match layer["type"].as_str() {
+            Some("tilelayer") => {
+                let data = match &layer["data"] {
+                    Value::Array(gids) => gids
+                        .iter()
+                        .map(|gid| gid.as_u64().unwrap_or(0) as u32)
+                        .collect(),
+                    Value::String(encoded) => {
+                        decode_base64(encoded, layer["compression"].as_str().unwrap_or(""))?
+                    }
+                    _ => return Err(MapError::new("tile layer without data")),
+                };
+                let names = [&layer["class"], &layer["name"]];
+                let layer = names
+                    .into_iter()
+                    .filter_map(Value::as_str)
+                    .find_map(map_layer);
+                tiled.tile_layers.push((layer, data));
+            }
+            Some("objectgroup") => {
+                for object in layer["objects"].as_array().into_iter().flatten() {
+                    let kind = [&object["class"], &object["type"], &object["name"]]
+                        .into_iter()
+                        .filter_map(Value::as_str)
+                        .find(|kind| !kind.is_empty())
+                        .unwrap_or_default();
+
+                    tiled.objects.push(TiledObject {
+                        kind: kind.to_string(),
+                        name: object["name"].as_str().unwrap_or_default().to_string(),
+                        x: object["x"].as_f64().unwrap_or(0.0) as f32,
+                        y: object["y"].as_f64().unwrap_or(0.0) as f32,
+                        width: object["width"].as_f64().unwrap_or(0.0) as f32,
+                        height: object["height"].as_f64().unwrap_or(0.0) as f32,
+                        gid: object["gid"].as_u64().map(|gid| gid as u32),
+                        properties: json_properties(&object["properties"]),
+                    });
+                }
+            }
+            Some("group") => json_layers(&layer["layers"], tiled)?,
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn json_properties(properties: &Value) -> HashMap<String, String> {
+    properties
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|property| {
+            let name = property["name"].as_str()?;
+            let value = match &property["value"] {
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            Some((name.to_string(), value))
+        })
+        .collect()
+}
+
+fn json_tileset(
+    tileset: &Value,
+    first_gid: u32,
+    variants: &mut HashMap<u32, TileVariant>,
+) -> Result<(), MapError> {
+    for tile in tileset["tiles"].as_array().into_iter().flatten() {
+        let id = tile["id"].as_u64().unwrap_or(0) as u32;
+        if let Some(variant) = json_properties(&tile["properties"]).get("variant") {
+            variants.insert(first_gid + id, parse_variant(variant)?);
+        }
+    }
+
+    Ok(())
+}
+
+/// external tilesets, `.tsx` or `.json`/`.tsj`
+fn read_tileset_file(
+    path: &Path,
+    first_gid: u32,
+    variants: &mut HashMap<u32, TileVariant>,
+) -> Result<(), MapError> {
+    let source = fs::read_to_string(path).map_err(|err| {
+        MapError::new(format!(
+            "failed to read tileset {}: {}",
+            path.display(),
+            err
+        ))
+    })?;
+
+    if path.extension().and_then(|ext| ext.to_str()) == Some("tsx") {
+        let document = roxmltree::Document::parse(&source).map_err(|err| {
+            MapError::new(format!("malformed tileset {}: {}", path.display(), err))
+        })?;
+        xml_tileset(document.root_element(), first_gid, variants)
+    } else {
+        let tileset: Value = serde_json::from_str(&source).map_err(|err| {
+            MapError::new(format!("malformed tileset {}: {}", path.display(), err))
+        })?;
+        json_tileset(&tileset, first_gid, variants)
+    }
+}
+
+fn read_tmx(source: &str, dir: &Path) -> Result<TiledMap, MapError> {
+    let document = roxmltree::Document::parse(source)
+        .map_err(|err| MapError::new(format!("malformed tmx: {}", err)))?;
+    let map = document.root_element();
+    if map.attribute("infinite") == Some("1") {
+        return Err(MapError::new("infinite Tiled maps are not supported"));
+    }
+
+    let number = |node: roxmltree::Node, name: &str| {
+        node.attribute(name)
+            .and_then(|value| value.parse::<f32>().ok())
+            .unwrap_or(0.0)
+    };
+    let mut tiled = TiledMap {
+        width: number(map, "width") as i32,
+        height: number(map, "height") as i32,
+        tile_width: number(map, "tilewidth").max(1.0),
+        tile_height: number(map, "tileheight").max(1.0),
+        properties: xml_properties(map),
+        ..Default::default()
+    };
+
+    for node in map.descendants().filter(|node| node.is_element()) {
+        match node.tag_name().name() {
+            "tileset" => {
+                let first_gid = number(node, "firstgid") as u32;
+                match node.attribute("source") {
+                    Some(source) => {
+                        read_tileset_file(&dir.join(source), first_gid, &mut tiled.variants)?
+                    }
+                    None => xml_tileset(node, first_gid, &mut tiled.variants)?,
+                }
+            }
+            "data" => {
+                let text = node.text().unwrap_or_default();
+                let data = match node.attribute("encoding") {
+                    Some("csv") => text
+                        .split(',')
+                        .map(|gid| gid.trim().parse::<u32>().unwrap_or(0))
+                        .collect(),
+                    Some("base64") => {
+                        decode_base64(text.trim(), node.attribute("compression").unwrap_or(""))?
+                    }
+                    _ => node
+                        .children()
+                        .filter(|child| child.has_tag_name("tile"))
+                        .map(|tile| number(tile, "gid") as u32)
+                        .collect(),
+                };
+                let names = node
+                    .parent()
+                    .into_iter()
+                    .flat_map(|layer| [layer.attribute("class"), layer.attribute("name")]);
+                let layer = names.flatten().find_map(map_layer);
+                tiled.tile_layers.push((layer, data));
+            }
+            "object" => {
+                // collision shapes drawn on tileset tiles are not map objects
+                if node
+                    .ancestors()
+                    .any(|parent| parent.has_tag_name("tileset"))
+                {
+                    continue;
+                }
+                let kind = ["class", "type", "name"]
+                    .into_iter()
+                    .filter_map(|name| node.attribute(name))
+                    .find(|kind| !kind.is_empty())
+                    .unwrap_or_default();
+
+                tiled.objects.push(TiledObject {
+                    kind: kind.to_string(),
+                    name: node.attribute("name").unwrap_or_default().to_string(),
+                    x: number(node, "x"),
+                    y: number(node, "y"),
+                    width: number(node, "width"),
+                    height: number(node, "height"),
+                    gid: node.attribute("gid").and_then(|gid| gid.parse().ok()),
+                    properties: xml_properties(node),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    Ok(tiled)
+}
+
+/// direct `<properties>` of a node, not those of its children
+fn xml_properties(node: roxmltree::Node) -> HashMap<String, String> {
+    node.children()
+        .filter(|child| child.has_tag_name("properties"))
+        .flat_map(|properties| properties.children())
+        .filter(|property| property.has_tag_name("property"))
+        .filter_map(|property| {
+            let value = property
+                .attribute("value")
+                .or(property.text())
+                .unwrap_or_default();
+            Some((property.attribute("name")?.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+fn xml_tileset(
+    tileset: roxmltree::Node,
+    first_gid: u32,
+    variants: &mut HashMap<u32, TileVariant>,
+) -> Result<(), MapError> {
+    for tile in tileset
+        .children()
+        .filter(|child| child.has_tag_name("tile"))
+    {
+        let id = tile
+            .attribute("id")
+            .and_then(|id| id.parse::<u32>().ok())
+            .unwrap_or(0);
+        if let Some(variant) = xml_properties(tile).get("variant") {
+            variants.insert(first_gid + id, parse_variant(variant)?);
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_variant(text: &str) -> Result<TileVariant, MapError> {
+    text.to_uppercase()
+        .parse::<TileVariant>()
+        .map_err(|_| MapError::new(format!("unknown tile variant `{}`", text)))
+}
+
+/// base64 layer data, little endian gids after optional zlib or gzip compression
+fn decode_base64(text: &str, compression: &str) -> Result<Vec<u32>, MapError> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(text)
+        .map_err(|err| MapError::new(format!("malformed layer data: {}", err)))?;
+
+    let mut raw = Vec::new();
+    let inflated = match compression {
+        "" => {
+            raw = bytes;
+            Ok(0)
+        }
+        "zlib" => ZlibDecoder::new(bytes.as_slice()).read_to_end(&mut raw),
+        "gzip" => GzDecoder::new(bytes.as_slice()).read_to_end(&mut raw),
+        other => {
+            return Err(MapError::new(format!(
+                "`{}` compressed layers are not supported",
+                other
+            )))
+        }
+    };
+    inflated.map_err(|err| MapError::new(format!("corrupted layer data: {}", err)))?;
+
+    Ok(raw
+        .chunks_exact(4)
+        .map(|gid| u32::from_le_bytes([gid[0], gid[1], gid[2], gid[3]]))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// writes `source` where the importer can read it
+    fn write(name: &str, source: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("piw-pew-tiled-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, source).unwrap();
+        path
+    }
+
+    static JSON: &str = r#"{
+        "width": 3, "height": 2, "tilewidth": 32, "tileheight": 32,
+        "properties": [{ "name": "name", "type": "string", "value": "Yard" }],
+        "tilesets": [{
+            "firstgid": 1,
+            "tiles": [
+                { "id": 0, "properties": [{ "name": "variant", "value": "GROUND" }] },
+                { "id": 1, "properties": [{ "name": "variant", "value": "wall_top" }] },
+                { "id": 2, "properties": [{ "name": "variant", "value": "DEBRIS" }] }
+            ]
+        }],
+        "layers": [
+            { "type": "tilelayer", "name": "ground", "data": [1, 2, 1, 1, 1, 7] },
+            { "type": "tilelayer", "name": "Floor", "data": [3, 0, 0, 0, 0, 0] },
+            { "type": "objectgroup", "objects": [
+                { "class": "spawn", "x": 0, "y": 40,
+                  "properties": [{ "name": "team", "value": "red" }] },
+                { "type": "zone", "x": 64, "y": 0, "width": 32, "height": 64 },
+                { "name": "pickup", "x": 40, "y": 40,
+                  "properties": [{ "name": "item", "value": "ammo" }] },
+                { "class": "door", "x": 0, "y": 0,
+                  "properties": [{ "name": "open", "type": "bool", "value": true }] },
+                { "class": "teleporter", "name": "west", "x": 0, "y": 32,
+                  "properties": [{ "name": "pair", "value": "a" }] },
+                { "class": "pad", "name": "jump", "x": 70, "y": 40,
+                  "properties": [{ "name": "dx", "type": "int", "value": -2 }] }
+            ]}
+        ]
+    }"#;
+
+    #[test]
+    fn imports_json_tiles_and_objects() {
+        let path = write("yard.json", JSON);
+        let overrides = HashMap::from([(7, TileVariant::WALL_SIDE)]);
+        let map = import_tiled(&path, &overrides).unwrap();
+
+        assert_eq!(map.header.name, "Yard");
+        assert_eq!(map.tiles.size(), (3, 2));
+        assert_eq!(map.tiles.get((0, 0)), Some(TileVariant::GROUND));
+        assert_eq!(map.tiles.get((1, 0)), Some(TileVariant::WALL_TOP));
+        assert_eq!(map.tiles.get((2, 1)), Some(TileVariant::WALL_SIDE));
+        assert_eq!(
+            map.layers[&MapLayer::FLOOR].get((0, 0)),
+            Some(TileVariant::DEBRIS)
+        );
+        assert_eq!(map.layers[&MapLayer::FLOOR].get((1, 0)), None);
+        assert_eq!(
+            map.spawns,
+            vec![SpawnPoint {
+                position: (0, 1),
+                team: Some(Team::RED)
+            }]
+        );
+        assert_eq!(
+            map.entities,
+            vec![
+                MapEntity {
+                    kind: MapEntityKind::ZONE(1, 2),
+                    position: (2, 0),
+                },
+                MapEntity {
+                    kind: MapEntityKind::PICKUP(String::from("ammo")),
+                    position: (1, 1),
+                },
+                MapEntity {
+                    kind: MapEntityKind::DOOR(true),
+                    position: (0, 0),
+                },
+                MapEntity {
+                    kind: MapEntityKind::TELEPORTER(String::from("a")),
+                    position: (0, 1),
+                },
+                MapEntity {
+                    kind: MapEntityKind::PAD(-2, 0),
+                    position: (2, 1),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn imports_tmx_with_csv_data() {
+        let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="2" height="2" tilewidth="16" tileheight="16">
+ <tileset firstgid="1" name="tiles">
+  <tile id="0"><properties><property name="variant" value="GROUND"/></properties></tile>
+  <tile id="1"><properties><property name="variant" value="WALL_SIDE"/></properties></tile>
+ </tileset>
+ <layer id="1" name="ground" width="2" height="2">
+  <data encoding="csv">1,2,
+1,1</data>
+ </layer>
+ <objectgroup id="2">
+  <object id="1" type="flag" gid="1" x="16" y="32" width="16" height="16">
+   <properties><property name="team" value="BLUE"/></properties>
+  </object>
+ </objectgroup>
+</map>"#;
+        let map = import_tiled(&write("csv.tmx", source), &HashMap::new()).unwrap();
+
+        assert_eq!(map.header.name, "csv");
+        assert_eq!(map.tiles.get((1, 0)), Some(TileVariant::WALL_SIDE));
+        // tile objects hang from their bottom left corner
+        assert_eq!(
+            map.entities,
+            vec![MapEntity {
+                kind: MapEntityKind::FLAG(Some(Team::BLUE)),
+                position: (1, 1),
+            }]
+        );
+    }
+
+    #[test]
+    fn rejects_pads_with_a_broken_distance() {
+        let source = r#"{
+            "width": 1, "height": 1, "tilewidth": 32, "tileheight": 32,
+            "layers": [{ "type": "objectgroup", "objects": [
+                { "class": "pad", "name": "jump", "x": 0, "y": 0,
+                  "properties": [{ "name": "dy", "value": "up" }] }
+            ]}]
+        }"#;
+        let err = import_tiled(&write("pad.json", source), &HashMap::new()).unwrap_err();
+        assert_eq!(err.message, "pad `jump` has an invalid dy of `up`");
+    }
+
+    #[test]
+    fn rejects_maps_without_a_size() {
+        let path = write("empty.json", r#"{ "layers": [] }"#);
+        let err = import_tiled(&path, &HashMap::new()).unwrap_err();
+        assert_eq!(err.message, "map size 0x0 has no cells");
+
+        let source = r#"{ "width": 2, "height": 2, "tilewidth": 0, "tileheight": 16 }"#;
+        let err = import_tiled(&write("flat.json", source), &HashMap::new()).unwrap_err();
+        assert_eq!(err.message, "tile size 0x16 is not positive");
+    }
+
+    #[test]
+    fn rejects_maps_past_the_maximum_size() {
+        let source = r#"{ "width": 100000, "height": 100000, "layers": [] }"#;
+        let err = import_tiled(&write("huge.json", source), &HashMap::new()).unwrap_err();
+        assert_eq!(
+            err.message,
+            "map size 100000x100000 is larger than the maximum of 4096x4096"
+        );
+
+        // would wrap to a small size when truncated to 32 bits
+        let source = r#"{ "width": 4294967298, "height": 2, "layers": [] }"#;
+        let err = import_tiled(&write("wrapped.json", source), &HashMap::new()).unwrap_err();
+        assert!(err.message.contains("larger than the maximum"));
+
+        let source = r#"<map width="5000" height="2" tilewidth="16" tileheight="16"></map>"#;
+        let err = import_tiled(&write("huge.tmx", source), &HashMap::new()).unwrap_err();
+        assert!(err.message.contains("larger than the maximum"));
+    }
+
+    #[test]
+    fn rejects_tiles_without_a_variant() {
+        let path = write("unmapped.json", JSON);
+        let err = import_tiled(&path, &HashMap::new()).unwrap_err();
+        assert!(err.message.starts_with("tile 7 has no `variant` property"));
+    }
+}
@@ -0,0 +1,132 @@
+use std::io::{Read, Write};
+
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::prelude::*;
+
+/// announced to clients so they can look the map up before downloading it
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MapInfo {
+    pub name: String,
+    /// sha256 of the compressed map, hex encoded
+    pub hash: String,
+    /// compressed size in bytes
+    pub size: u32,
+    pub chunks: u32,
+}
+
+/// map tiles compressed for the wire, identified by the hash of its bytes
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapPackage {
+    pub info: MapInfo,
+    data: Vec<u8>,
+}
+
+impl MapPackage {
+    pub fn new(name: &str, tiles: &MapTiles) -> Self {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+        encoder
+            .write_all(&rmp_serde::to_vec(tiles).unwrap())
+            .unwrap();
+        let data = encoder.finish().unwrap();
+
+        Self {
+            info: MapInfo {
+                name: name.to_string(),
+                hash: map_hash(&data),
+                size: data.len() as u32,
+                chunks: data.len().div_ceil(MAP_CHUNK_SIZE).max(1) as u32,
+            },
+            data,
+        }
+    }
+
+    /// (index, bytes) pieces of at most `MAP_CHUNK_SIZE` bytes
+    pub fn chunks(&self) -> impl Iterator<Item = (u32, &[u8])> {
+        self.data
+            .chunks(MAP_CHUNK_SIZE)
+            .enumerate()
+            .map(|(index, chunk)| (index as u32, chunk))
+    }
+}
+
+pub fn map_hash(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// inflates a compressed map, the data is expected to match `hash`
+pub fn unpack_map(hash: &str, data: &[u8]) -> Result<MapTiles, MapError> {
+    if map_hash(data) != hash {
+        return Err(MapError::new("map data does not match its hash"));
+    }
+
+    let mut buffer = Vec::new();
+    ZlibDecoder::new(data)
+        .read_to_end(&mut buffer)
+        .map_err(|err| MapError::new(format!("corrupted map data: {}", err)))?;
+
+    rmp_serde::from_slice(&buffer)
+        .map_err(|err| MapError::new(format!("malformed map data: {}", err)))
+}
+
+/// chunks of a map received so far, they may arrive in any order
+#[derive(Debug, Clone)]
+pub struct MapDownload {
+    pub info: MapInfo,
+    chunks: Vec<Option<Vec<u8>>>,
+    received: u32,
+}
+
+impl MapDownload {
+    pub fn new(info: MapInfo) -> Self {
+        Self {
+            chunks: vec![None; info.chunks as usize],
+            received: 0,
+            info,
+        }
+    }
+
+    /// stores a chunk, duplicates and chunks out of range are ignored
+    pub fn insert(&mut self, index: u32, data: Vec<u8>) {
+        if let Some(chunk @ None) = self.chunks.get_mut(index as usize) {
+            *chunk = Some(data);
+            self.received += 1;
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.received == self.info.chunks
+    }
+
+    /// between 0 and 1
+    pub fn progress(&self) -> f32 {
+        self.received as f32 / self.info.chunks.max(1) as f32
+    }
+
+    /// the compressed map and its tiles once every chunk is in
+    pub fn finish(self) -> Result<(Vec<u8>, MapTiles), MapError> {
+        if !self.is_complete() {
+            return Err(MapError::new(format!(
+                "{} of {} chunks received",
+                self.received, self.info.chunks
+            )));
+        }
+
+        let data = self
+            .chunks
+            .into_iter()
+            .flatten()
+            .flatten()
+            .collect::<Vec<u8>>();
+        let tiles = unpack_map(&self.info.hash, &data)?;
+
+        Ok((data, tiles))
+    }
+}
@@ -3,6 +3,8 @@
 pub mod configs;
 pub mod core;
 pub mod entities;
+pub mod maps;
+pub mod modes;
 pub mod network;
 pub mod utils;
 
@@ -10,6 +12,8 @@ pub mod prelude {
     pub use crate::configs::*;
     pub use crate::core::*;
     pub use crate::entities::*;
+    pub use crate::maps::*;
+    pub use crate::modes::*;
     pub use crate::network::*;
 
     pub use crate::utils::logging::*;
@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+
+use crate::configs::*;
+use crate::types::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize, Display, EnumString)]
+pub enum GameMode {
+    DEATHMATCH,
+    KING_OF_THE_HILL,
+    LAST_MAN_STANDING,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize, Display, EnumString)]
+pub enum Team {
+    RED,
+    BLUE,
+}
+
+/// whoever is standing on a zone, a single player in free for all or a whole team
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum ZoneOwner {
+    PLAYER(RawClientId),
+    TEAM(Team),
+}
+
+impl ZoneOwner {
+    pub fn of(id: RawClientId, team: Option<Team>) -> Self {
+        match team {
+            Some(team) => Self::TEAM(team),
+            None => Self::PLAYER(id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum ZoneStatus {
+    IDLE,
+    CAPTURING(ZoneOwner),
+    CONTESTED,
+    SCORING,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ZoneData {
+    pub id: u8,
+    /// grid rectangle (x, y, width, height)
+    pub area: (i32, i32, i32, i32),
+    pub owner: Option<ZoneOwner>,
+    pub status: ZoneStatus,
+    /// capture progress in [0, 1]
+    pub progress: f32,
+}
+
+impl ZoneData {
+    pub fn new(id: u8, area: (i32, i32, i32, i32)) -> Self {
+        Self {
+            id,
+            area,
+            owner: None,
+            status: ZoneStatus::IDLE,
+            progress: 0.0,
+        }
+    }
+
+    /// world space rectangle (x, y, width, height)
+    pub fn world_area(&self) -> (f32, f32, f32, f32) {
+        let (x, y, w, h) = self.area;
+        (
+            x as f32 * WORLD_TILE_SIZE,
+            y as f32 * WORLD_TILE_SIZE,
+            w as f32 * WORLD_TILE_SIZE,
+            h as f32 * WORLD_TILE_SIZE,
+        )
+    }
+
+    /// checks whether a world point lies inside the zone
+    pub fn contains(&self, (px, py): (f32, f32)) -> bool {
+        let (x, y, w, h) = self.world_area();
+        px >= x && px < x + w && py >= y && py < y + h
+    }
+}
@@ -1,5 +1,9 @@
 extern crate rmp_serde as rmps;
 
+mod rcon;
+
+pub use rcon::*;
+
 pub use renet::transport::ClientAuthentication;
 pub use renet::transport::NetcodeClientTransport;
 pub use renet::transport::NetcodeError;
@@ -33,6 +37,25 @@ pub struct PlayerData {
     pub weapon: WeaponVariant,
     pub health: Health,
     pub cash: Cash,
+    pub team: Option<Team>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+pub struct PlayerStats {
+    pub kills: u32,
+    pub deaths: u32,
+    pub assists: u32,
+    pub score: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ScoreboardEntry {
+    pub id: RawClientId,
+    pub name: String,
+    pub team: Option<Team>,
+    pub stats: PlayerStats,
+    /// round trip time in milliseconds
+    pub ping: u32,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
@@ -43,16 +66,78 @@ pub struct ProjectileData {
     pub grid: (i32, i32),
     pub orientation: Orientation,
     pub shooter: RawClientId,
+    pub weapon: WeaponVariant,
     pub damage: u8,
 }
 
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct KillEvent {
+    /// `None` when the victim died to the environment
+    pub killer: Option<RawClientId>,
+    pub victim: RawClientId,
+    pub weapon: Option<WeaponVariant>,
+    pub assisters: Vec<RawClientId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct DamageEvent {
+    pub attacker: RawClientId,
+    pub victim: RawClientId,
+    pub damage: Damage,
+    pub lethal: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ChatChannel {
+    ALL,
+    TEAM,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ChatMessage {
+    /// `None` for messages coming from the server itself
+    pub sender: Option<RawClientId>,
+    pub name: String,
+    pub channel: ChatChannel,
+    pub text: String,
+}
+
+impl ChatMessage {
+    /// announcement from the server itself
+    pub fn system(text: String) -> Self {
+        Self {
+            sender: None,
+            name: String::from("server"),
+            channel: ChatChannel::ALL,
+            text,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub enum GameNetworkPacket {
-    NET_WORLD_MAP(HashMap<(i32, i32), TileVariant>),
+    /// the map being played, clients request it unless they have it cached
+    NET_MAP_INFO(MapInfo),
+    /// client asking for the map with the given hash
+    NET_MAP_REQUEST(String),
+    /// map hash, chunk index and compressed bytes
+    NET_MAP_CHUNK(String, u32, #[serde(with = "serde_bytes")] Vec<u8>),
+    /// tiles damaged or broken since the last delta
+    NET_MAP_DELTA(MapDelta),
+    /// doors, teleporters and pads of the map being played
+    NET_MAP_ENTITIES(Vec<MapEntity>),
+    /// client opening or closing the door next to it
+    NET_PLAYER_USE(),
+    /// world position a teleporter moved the player to
+    NET_PLAYER_TELEPORT((f32, f32)),
+    /// world position a pad throws the player to and seconds in the air
+    NET_PLAYER_LAUNCH((f32, f32), f32),
     NET_WORLD_PLAYERS(HashMap<u64, PlayerData>),
     NET_PLAYER_JOINED(PlayerData),
     NET_PLAYER_DIED(RawClientId),
     NET_PLAYER_RESPAWN(RawClientId, PlayerData),
+    /// seconds of spawn protection the player has left, 0 once firing ended it
+    NET_PLAYER_PROTECTED(RawClientId, f32),
     NET_PLAYER_KILL_REWARD(PlayerData),
     NET_PLAYER_GRID_POSITION(RawClientId, (i32, i32)),
     NET_PLAYER_WORLD_POSITION(RawClientId, (f32, f32)),
@@ -64,6 +149,25 @@ pub enum GameNetworkPacket {
     NET_PROJECTILE_IMPACT(RawProjectileId, Option<RawClientId>, Damage),
     NET_PLAYER_HEAL(RawClientId),
     NET_PLAYER_AMMO(),
+    NET_ZONE_STATE(Vec<ZoneData>, HashMap<ZoneOwner, u32>),
+    NET_PLAYER_DAMAGE(RawClientId, Damage),
+    NET_SAFE_ZONE((f32, f32), f32),
+    NET_ROUND_START(u32),
+    NET_ROUND_END(Option<(RawClientId, String)>),
+    NET_SCOREBOARD(Vec<ScoreboardEntry>),
+    NET_KILL_EVENT(KillEvent),
+    NET_DAMAGE_EVENT(DamageEvent),
+    NET_CHAT_SEND(ChatChannel, String),
+    NET_CHAT_MESSAGE(ChatMessage),
+    /// why the server is about to drop the connection
+    NET_DISCONNECT_REASON(String),
+    /// candidate maps and seconds left to vote
+    NET_MAP_VOTE_START(Vec<String>, f32),
+    /// index of the candidate a client votes for
+    NET_MAP_VOTE(u8),
+    /// votes per candidate
+    NET_MAP_VOTE_STATE(Vec<u32>),
+    NET_MAP_VOTE_END(String),
 }
 
 impl GameNetworkPacket {
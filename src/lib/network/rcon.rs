@@ -0,0 +1,77 @@
+use hmac::{Hmac, Mac};
+use rmp_serde::Serializer;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+/// largest datagram either side of the rcon protocol will send
+pub const RCON_MAX_PACKET_SIZE: usize = 8192;
+
+/// remote administration protocol, every command is signed with the shared password
+/// over a single use nonce handed out by the server, so a captured packet can not be replayed
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub enum RconPacket {
+    /// asks the server for a fresh nonce
+    RCON_CHALLENGE_REQUEST,
+    RCON_CHALLENGE(u64),
+    /// nonce, console command line and its signature
+    RCON_COMMAND(u64, String, Vec<u8>),
+    RCON_RESPONSE(Result<String, String>),
+}
+
+impl RconPacket {
+    pub fn serialized(&self) -> Result<Vec<u8>, String> {
+        let mut buffer: Vec<u8> = Vec::new();
+        match self.serialize(&mut Serializer::new(&mut buffer)) {
+            Ok(_) => Ok(buffer),
+            Err(_) => Err(String::from("failed to serialize rcon packet")),
+        }
+    }
+}
+
+fn rcon_hmac(password: &str, nonce: u64, command: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(password.as_bytes())
+        .expect("hmac accepts keys of any length");
+    mac.update(&nonce.to_le_bytes());
+    mac.update(command.as_bytes());
+    mac
+}
+
+/// signature of a command line for the given nonce
+pub fn rcon_sign(password: &str, nonce: u64, command: &str) -> Vec<u8> {
+    rcon_hmac(password, nonce, command)
+        .finalize()
+        .into_bytes()
+        .to_vec()
+}
+
+/// checks a signature in constant time
+pub fn rcon_verify(password: &str, nonce: u64, command: &str, signature: &[u8]) -> bool {
+    rcon_hmac(password, nonce, command)
+        .verify_slice(signature)
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signatures_verify_only_what_was_signed() {
+        let signature = rcon_sign("hunter2", 7, "kick 3");
+        assert!(rcon_verify("hunter2", 7, "kick 3", &signature));
+
+        assert!(!rcon_verify("hunter3", 7, "kick 3", &signature));
+        assert!(!rcon_verify("hunter2", 8, "kick 3", &signature));
+        assert!(!rcon_verify("hunter2", 7, "kick 4", &signature));
+        assert!(!rcon_verify("hunter2", 7, "kick 3", &signature[1..]));
+        assert!(!rcon_verify("hunter2", 7, "kick 3", &[]));
+    }
+
+    #[test]
+    fn packets_round_trip() {
+        let packet =
+            RconPacket::RCON_COMMAND(7, String::from("status"), rcon_sign("pw", 7, "status"));
+        let bytes = packet.serialized().unwrap();
+        assert_eq!(rmp_serde::from_slice::<RconPacket>(&bytes).unwrap(), packet);
+    }
+}
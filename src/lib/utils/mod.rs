@@ -15,17 +15,6 @@ pub fn raw_uuid() -> u64 {
     u64::from_le_bytes(Uuid::new_v4().as_bytes()[..8].try_into().unwrap())
 }
 
-pub static POINT_OFFSETS: [(i8, i8); 8] = [
-    (0, 1),
-    (1, 1),
-    (1, 0),
-    (1, -1),
-    (0, -1),
-    (-1, -1),
-    (-1, 0),
-    (-1, 1),
-];
-
 /// env logger stuff
 pub mod logging {
     use env_logger::{self, Env};
@@ -0,0 +1,87 @@
+use std::fs;
+
+use lib::prelude::*;
+
+static USAGE: &str = "\
+usage: mapgen [options] [output.map]
+
+prints the map when no output file is given
+  --seed <n>                      random when left out
+  --size <width>x<height>         defaults to 40x30
+  --layout <rooms|caves>
+  --symmetry <none|mirror|rotate> team maps want mirror or rotate
+  --spawns <n>                    per team, or in total without symmetry
+  --no-zone                       leave out the capture zone";
+
+fn main() {
+    let mut settings = GeneratorSettings {
+        seed: rand::random(),
+        ..Default::default()
+    };
+    let mut output = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--seed" => settings.seed = parse(&value()),
+            "--size" => {
+                let size = value();
+                let (width, height) = size.split_once('x').unwrap_or_else(|| usage());
+                settings.width = parse(width);
+                settings.height = parse(height);
+                let range = GENERATOR_MIN_SIZE..=MAP_MAX_SIZE;
+                if !range.contains(&settings.width) || !range.contains(&settings.height) {
+                    eprintln!(
+                        "map sides must be between {} and {} tiles, not {}",
+                        GENERATOR_MIN_SIZE, MAP_MAX_SIZE, size
+                    );
+                    std::process::exit(2);
+                }
+            }
+            "--layout" => settings.layout = parse(&value()),
+            "--symmetry" => settings.symmetry = parse(&value()),
+            "--spawns" => settings.spawns = parse(&value()),
+            "--no-zone" => settings.zone = false,
+            "-h" | "--help" => usage(),
+            _ if arg.starts_with('-') => {
+                eprintln!("unknown option `{}`", arg);
+                usage();
+            }
+            _ => output = Some(arg),
+        }
+    }
+
+    let map = generate(&settings);
+    let source = map.to_source();
+
+    for diagnostic in lint(&map.header.name, &source) {
+        eprintln!("{}", diagnostic);
+    }
+
+    match output {
+        Some(path) => {
+            if let Err(err) = fs::write(&path, source) {
+                eprintln!("failed to write {}: {}", path, err);
+                std::process::exit(1);
+            }
+            eprintln!(
+                "wrote {} ({}x{}, seed {})",
+                path, map.header.width, map.header.height, settings.seed
+            );
+        }
+        None => print!("{}", source),
+    }
+}
+
+fn parse<T: std::str::FromStr>(text: &str) -> T {
+    text.parse().unwrap_or_else(|_| {
+        eprintln!("invalid value `{}`", text);
+        usage();
+    })
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    std::process::exit(2);
+}
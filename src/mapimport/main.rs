@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use lib::prelude::*;
+
+static USAGE: &str = "\
+usage: mapimport [options] <input> [output.map]
+
+converts a Tiled map (.tmx, .json, .tmj) or a png pixel map into a .map file,
+prints the map when no output file is given
+  --tile <gid>=<variant>         tiled: variant of a tile without a `variant` property
+  --color <rrggbb>=<kind>        png: adds or replaces a palette color, kind is a tile
+                                 variant, SPAWN, SPAWN_<team>, FLAG_<team> or ZONE
+
+default png palette:
+  ffffff GROUND   000000 WALL        808080 WALL_SIDE   ffff00 ZONE
+  404040 COVER    00ffff WATER       ff8000 HAZARD      8b4513 CRATE
+  a04030 BRICK
+  00ff00 SPAWN    ff0000 SPAWN_RED   0000ff SPAWN_BLUE
+  800000 FLAG_RED 000080 FLAG_BLUE";
+
+fn main() {
+    let mut tiles = HashMap::new();
+    let mut palette = default_palette();
+    let mut paths = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--tile" => {
+                let mapping = value();
+                let (gid, variant) = mapping.split_once('=').unwrap_or_else(|| usage());
+                tiles.insert(parse(gid), parse(&variant.to_uppercase()));
+            }
+            "--color" => {
+                let mapping = value();
+                let (color, kind) = mapping.split_once('=').unwrap_or_else(|| usage());
+                palette.insert(parse_color(color), parse(kind));
+            }
+            "-h" | "--help" => usage(),
+            _ if arg.starts_with('-') => {
+                eprintln!("unknown option `{}`", arg);
+                usage();
+            }
+            _ => paths.push(arg),
+        }
+    }
+
+    let (input, output) = match paths.as_slice() {
+        [input] => (Path::new(input), None),
+        [input, output] => (Path::new(input), Some(output)),
+        _ => usage(),
+    };
+
+    let imported = match input.extension().and_then(|ext| ext.to_str()) {
+        Some("png") => fs::read(input)
+            .map_err(|err| format!("failed to read {}: {}", input.display(), err))
+            .and_then(|bytes| {
+                let name = input.file_name().unwrap_or_default().to_string_lossy();
+                import_png(&name, &bytes, &palette).map_err(|err| err.to_string())
+            }),
+        Some("tmx" | "json" | "tmj") => import_tiled(input, &tiles).map_err(|err| err.to_string()),
+        _ => {
+            eprintln!("unsupported input {}", input.display());
+            usage();
+        }
+    };
+    let map = imported.unwrap_or_else(|err| {
+        eprintln!("{}: {}", input.display(), err);
+        std::process::exit(1);
+    });
+    let source = map.to_source();
+
+    for diagnostic in lint(&map.header.name, &source) {
+        eprintln!("{}", diagnostic);
+    }
+
+    match output {
+        Some(path) => {
+            if let Err(err) = fs::write(path, source) {
+                eprintln!("failed to write {}: {}", path, err);
+                std::process::exit(1);
+            }
+            eprintln!(
+                "wrote {} ({}x{}, {} spawns, {} entities)",
+                path,
+                map.header.width,
+                map.header.height,
+                map.spawns.len(),
+                map.entities.len()
+            );
+        }
+        None => print!("{}", source),
+    }
+}
+
+fn parse<T: std::str::FromStr>(text: &str) -> T {
+    text.parse().unwrap_or_else(|_| {
+        eprintln!("invalid value `{}`", text);
+        usage();
+    })
+}
+
+fn parse_color(text: &str) -> [u8; 3] {
+    let text = text.trim_start_matches('#');
+    let channel = |index: usize| {
+        text.get(index * 2..index * 2 + 2)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+    };
+
+    match (text.len(), channel(0), channel(1), channel(2)) {
+        (6, Some(r), Some(g), Some(b)) => [r, g, b],
+        _ => {
+            eprintln!("invalid color `{}`", text);
+            usage();
+        }
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    std::process::exit(2);
+}
@@ -0,0 +1,101 @@
+use std::fs;
+use std::path::PathBuf;
+
+use lib::prelude::*;
+
+static USAGE: &str = "\
+usage: maplint [--json] [--strict] [file.map...]
+
+checks every map in `maps/` when no file is given
+  --json     one json object per diagnostic and line
+  --strict   fail on warnings too";
+
+fn main() {
+    let mut json = false;
+    let mut strict = false;
+    let mut files = Vec::new();
+
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--strict" => strict = true,
+            "-h" | "--help" => usage(),
+            _ if arg.starts_with('-') => {
+                eprintln!("unknown option `{}`", arg);
+                usage();
+            }
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
+
+    if files.is_empty() {
+        files = maps_dir();
+    }
+
+    let (mut errors, mut warnings) = (0, 0);
+    for path in &files {
+        let diagnostics = match fs::read_to_string(path) {
+            Ok(source) => {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                lint(&name, &source)
+            }
+            Err(err) => vec![Diagnostic {
+                severity: Severity::ERROR,
+                code: "io",
+                line: None,
+                column: None,
+                message: err.to_string(),
+            }],
+        };
+
+        for diagnostic in &diagnostics {
+            match diagnostic.severity {
+                Severity::ERROR => errors += 1,
+                Severity::WARNING => warnings += 1,
+            }
+
+            if json {
+                let mut value = serde_json::to_value(diagnostic).unwrap();
+                value["file"] = serde_json::Value::from(path.to_string_lossy());
+                println!("{}", value);
+            } else if diagnostic.line.is_some() {
+                println!("{}:{}", path.display(), diagnostic);
+            } else {
+                println!("{}: {}", path.display(), diagnostic);
+            }
+        }
+    }
+
+    if !json {
+        eprintln!(
+            "{} maps checked, {} errors, {} warnings",
+            files.len(),
+            errors,
+            warnings
+        );
+    }
+
+    if errors > 0 || (strict && warnings > 0) {
+        std::process::exit(1);
+    }
+}
+
+fn maps_dir() -> Vec<PathBuf> {
+    let mut maps = fs::read_dir("maps")
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "map"))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    maps.sort();
+    maps
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    std::process::exit(2);
+}
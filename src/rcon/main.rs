@@ -0,0 +1,137 @@
+use std::io::{self, BufRead, Write};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::Duration;
+
+use lib::prelude::*;
+
+static USAGE: &str = "\
+usage: rcon [--host <addr:port>] [--password <password>] [command...]
+
+runs a single command when one is given, otherwise reads commands from stdin
+the password can also be passed through the `RCON_PASSWORD` variable";
+
+static TIMEOUT: Duration = Duration::from_secs(3);
+
+fn main() {
+    let mut host = String::from("127.0.0.1:6970");
+    let mut password = std::env::var("RCON_PASSWORD").ok();
+    let mut command = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-H" | "--host" => host = args.next().unwrap_or_else(|| usage()),
+            "-p" | "--password" => password = Some(args.next().unwrap_or_else(|| usage())),
+            "-h" | "--help" => usage(),
+            _ => command.push(arg),
+        }
+    }
+
+    let Some(password) = password else {
+        eprintln!("no password given");
+        usage();
+    };
+
+    let Some(addr) = host
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+    else {
+        eprintln!("invalid host `{}`", host);
+        std::process::exit(2);
+    };
+
+    let socket = UdpSocket::bind("0.0.0.0:0").expect("failed to bind a local socket");
+    socket
+        .set_read_timeout(Some(TIMEOUT))
+        .expect("failed to set socket timeout");
+
+    if !command.is_empty() {
+        let ok = execute(&socket, addr, &password, &command.join(" "));
+        std::process::exit(if ok { 0 } else { 1 });
+    }
+
+    let stdin = io::stdin();
+    loop {
+        print!("rcon> ");
+        io::stdout().flush().ok();
+
+        let mut line = String::new();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) if line.trim().is_empty() => continue,
+            Ok(_) => {
+                execute(&socket, addr, &password, line.trim());
+            }
+        }
+    }
+}
+
+/// runs one command on the server and prints its answer
+fn execute(socket: &UdpSocket, addr: SocketAddr, password: &str, command: &str) -> bool {
+    match request(socket, addr, password, command) {
+        Ok(Ok(output)) => {
+            println!("{}", output);
+            true
+        }
+        Ok(Err(err)) => {
+            eprintln!("error: {}", err);
+            false
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            false
+        }
+    }
+}
+
+fn request(
+    socket: &UdpSocket,
+    addr: SocketAddr,
+    password: &str,
+    command: &str,
+) -> Result<Result<String, String>, String> {
+    let nonce = match exchange(socket, addr, RconPacket::RCON_CHALLENGE_REQUEST)? {
+        RconPacket::RCON_CHALLENGE(nonce) => nonce,
+        RconPacket::RCON_RESPONSE(result) => return Ok(result),
+        _ => return Err(String::from("unexpected answer from the server")),
+    };
+
+    let signature = rcon_sign(password, nonce, command);
+    match exchange(
+        socket,
+        addr,
+        RconPacket::RCON_COMMAND(nonce, command.to_string(), signature),
+    )? {
+        RconPacket::RCON_RESPONSE(result) => Ok(result),
+        _ => Err(String::from("unexpected answer from the server")),
+    }
+}
+
+fn exchange(
+    socket: &UdpSocket,
+    addr: SocketAddr,
+    packet: RconPacket,
+) -> Result<RconPacket, String> {
+    socket
+        .send_to(&packet.serialized()?, addr)
+        .map_err(|err| format!("failed to reach {}: {}", addr, err))?;
+
+    let mut buffer = [0; RCON_MAX_PACKET_SIZE];
+    loop {
+        let (size, from) = socket
+            .recv_from(&mut buffer)
+            .map_err(|_| format!("no answer from {}", addr))?;
+
+        // stray datagrams from anyone else are ignored
+        if from == addr {
+            return rmp_serde::from_slice(&buffer[..size])
+                .map_err(|_| String::from("malformed answer from the server"));
+        }
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    std::process::exit(2);
+}
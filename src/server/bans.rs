@@ -0,0 +1,258 @@
+use std::fs::File;
+use std::io;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ban {
+    pub ip: Option<IpAddr>,
+    /// player name at the time of the ban, only matched against when the address is unknown
+    pub name: Option<String>,
+    pub reason: String,
+    /// unix timestamps in seconds
+    pub since: u64,
+    pub until: Option<u64>,
+}
+
+impl Ban {
+    pub fn new(
+        ip: Option<IpAddr>,
+        name: Option<String>,
+        reason: String,
+        duration: Option<Duration>,
+    ) -> Self {
+        let since = unix_time();
+        Self {
+            ip,
+            name,
+            reason,
+            since,
+            until: duration.map(|duration| since.saturating_add(duration.as_secs())),
+        }
+    }
+
+    fn expired(&self) -> bool {
+        self.until.is_some_and(|until| until <= unix_time())
+    }
+
+    fn matches(&self, ip: IpAddr, name: &str) -> bool {
+        match (self.ip, &self.name) {
+            (Some(banned), _) => banned == ip,
+            (None, Some(banned)) => banned.eq_ignore_ascii_case(name),
+            (None, None) => false,
+        }
+    }
+
+    /// what the banned player is told when refused
+    pub fn message(&self) -> String {
+        match self.until {
+            Some(until) => format!(
+                "banned for another {}: {}",
+                format_duration(Duration::from_secs(until.saturating_sub(unix_time()))),
+                self.reason
+            ),
+            None => format!("banned: {}", self.reason),
+        }
+    }
+}
+
+impl std::fmt::Display for Ban {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let target = match (&self.ip, &self.name) {
+            (Some(ip), Some(name)) => format!("{} ({})", ip, name),
+            (Some(ip), None) => ip.to_string(),
+            (None, Some(name)) => name.clone(),
+            (None, None) => String::from("?"),
+        };
+        let expires = match self.until {
+            Some(until) => format!(
+                "{} left",
+                format_duration(Duration::from_secs(until.saturating_sub(unix_time())))
+            ),
+            None => String::from("permanent"),
+        };
+
+        write!(f, "{:<32} {:<12} {}", target, expires, self.reason)
+    }
+}
+
+/// bans persisted to `bans.json`, expired entries are dropped as they are found
+#[derive(Debug, Default)]
+pub struct BanList {
+    path: PathBuf,
+    bans: Vec<Ban>,
+}
+
+impl BanList {
+    pub fn load(path: PathBuf) -> Self {
+        let bans = match File::open(&path) {
+            Ok(file) => match serde_json::from_reader::<_, Vec<Ban>>(io::BufReader::new(file)) {
+                Ok(bans) => {
+                    log::info!("loaded {} bans", bans.len());
+                    bans
+                }
+                Err(err) => {
+                    log::warn!("malformed ban list: {}", err);
+                    Vec::new()
+                }
+            },
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => {
+                log::error!("failed to open ban list {:?}: {}", path, err);
+                Vec::new()
+            }
+        };
+
+        let mut list = Self { path, bans };
+        list.prune();
+        list
+    }
+
+    pub fn add(&mut self, ban: Ban) {
+        log::warn!("ban added: {}", ban);
+        self.bans.push(ban);
+        self.save();
+    }
+
+    /// lifts every ban on the given address or name, returns how many were removed
+    pub fn remove(&mut self, target: &str) -> usize {
+        let ip = target.parse::<IpAddr>().ok();
+        let before = self.bans.len();
+
+        self.bans.retain(|ban| {
+            let by_ip = ip.is_some() && ban.ip == ip;
+            let by_name = ban
+                .name
+                .as_ref()
+                .is_some_and(|name| name.eq_ignore_ascii_case(target));
+            !(by_ip || by_name)
+        });
+
+        let removed = before - self.bans.len();
+        if removed > 0 {
+            self.save();
+        }
+
+        removed
+    }
+
+    pub fn find(&mut self, ip: IpAddr, name: &str) -> Option<&Ban> {
+        self.prune();
+        self.bans.iter().find(|ban| ban.matches(ip, name))
+    }
+
+    pub fn list(&mut self) -> &[Ban] {
+        self.prune();
+        &self.bans
+    }
+
+    fn prune(&mut self) {
+        let before = self.bans.len();
+        self.bans.retain(|ban| !ban.expired());
+
+        if self.bans.len() != before {
+            self.save();
+        }
+    }
+
+    fn save(&self) {
+        match File::create(&self.path) {
+            Ok(mut file) => {
+                if let Err(err) = serde_json::to_writer_pretty(&mut file, &self.bans) {
+                    log::error!("failed to write ban list: {}", err);
+                }
+            }
+            Err(err) => log::error!("failed to create ban list {:?}: {}", self.path, err),
+        }
+    }
+}
+
+/// parses durations such as `90s`, `30m`, `12h` or `7d`, none for zero or for
+/// durations that would end past the range of a unix timestamp
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let split = text.find(|c: char| !c.is_ascii_digit())?;
+    let (amount, unit) = text.split_at(split);
+    let amount = amount.parse::<u64>().ok().filter(|amount| *amount > 0)?;
+
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        "w" => 60 * 60 * 24 * 7,
+        _ => return None,
+    };
+
+    let seconds = amount.checked_mul(seconds)?;
+    unix_time().checked_add(seconds)?;
+    Some(Duration::from_secs(seconds))
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m", seconds / 60),
+        3600..=86399 => format!("{}h", seconds / 3600),
+        _ => format!("{}d", seconds / 86400),
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_unit() {
+        assert_eq!(parse_duration("90s"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("30m"), Some(Duration::from_secs(30 * 60)));
+        assert_eq!(parse_duration("12h"), Some(Duration::from_secs(12 * 3600)));
+        assert_eq!(parse_duration("7d"), Some(Duration::from_secs(7 * 86400)));
+        assert_eq!(parse_duration("2w"), Some(Duration::from_secs(14 * 86400)));
+    }
+
+    #[test]
+    fn rejects_malformed_durations() {
+        assert_eq!(parse_duration("30"), None);
+        assert_eq!(parse_duration("m"), None);
+        assert_eq!(parse_duration("30y"), None);
+        assert_eq!(parse_duration("30mm"), None);
+        assert_eq!(parse_duration("-5m"), None);
+    }
+
+    #[test]
+    fn rejects_zero() {
+        assert_eq!(parse_duration("0s"), None);
+        assert_eq!(parse_duration("0d"), None);
+    }
+
+    #[test]
+    fn rejects_durations_that_overflow() {
+        assert_eq!(parse_duration("99999999999999999999w"), None);
+        assert_eq!(parse_duration("9999999999999999w"), None);
+        assert_eq!(parse_duration(&format!("{}s", u64::MAX)), None);
+        assert!(parse_duration("1000w").is_some());
+    }
+
+    #[test]
+    fn bans_never_end_before_they_start() {
+        let ban = Ban::new(
+            None,
+            Some(String::from("someone")),
+            String::from("test"),
+            Some(Duration::from_secs(u64::MAX)),
+        );
+        assert_eq!(ban.until, Some(u64::MAX));
+        assert!(!ban.expired());
+    }
+}
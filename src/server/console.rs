@@ -0,0 +1,348 @@
+use std::io::BufRead;
+use std::net::IpAddr;
+use std::sync::mpsc::{self, Receiver};
+
+use lib::prelude::*;
+use renet::{transport::NetcodeServerTransport, RenetServer};
+
+use crate::bans::{self, Ban};
+use crate::{Match, ServerState, Shutdown};
+
+static HELP: &str = "\
+status                   map, mode and player count
+players                  list connected players
+kick <player> [reason]   disconnect a player
+ban <player|ip> [time] [reason]
+                         refuse a player, name or address, for e.g. `30m` or `7d`
+unban <ip|name>          lift a ban
+bans                     list active bans
+say <message>            send a chat message as the server
+changemap <map>          switch to another map from `maps/`
+set [rule] [value]       show or change a server rule
+restart                  reset scores and respawn everyone
+endmatch                 end the match and move on to the next map
+quit [reason]            shut the server down
+reboot [reason]          shut down and tell players the server comes back";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Help,
+    Status,
+    Players,
+    Kick(String, Option<String>),
+    Ban(String, Option<Duration>, Option<String>),
+    Unban(String),
+    Bans,
+    Say(String),
+    ChangeMap(String),
+    Set(Option<String>, Option<String>),
+    Restart,
+    EndMatch,
+    Quit(Option<String>),
+    Reboot(Option<String>),
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim();
+        let (name, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let args = args.trim();
+        let rest = Some(args.to_string()).filter(|args| !args.is_empty());
+
+        // `<player> [reason...]`
+        let target = || -> Result<(String, Option<String>), String> {
+            let (player, reason) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+            if player.is_empty() {
+                return Err(format!("usage: {} <player> [reason]", name));
+            }
+
+            let reason = Some(reason.trim().to_string()).filter(|reason| !reason.is_empty());
+            Ok((player.to_string(), reason))
+        };
+
+        match name.to_lowercase().as_str() {
+            "help" | "?" => Ok(Self::Help),
+            "status" => Ok(Self::Status),
+            "players" => Ok(Self::Players),
+            "kick" => target().map(|(player, reason)| Self::Kick(player, reason)),
+            "ban" => {
+                let (player, rest) = target()?;
+                let rest = rest.unwrap_or_default();
+                let (time, reason) = rest.split_once(char::is_whitespace).unwrap_or((&rest, ""));
+
+                // the time is optional, anything else not starting with a digit starts the reason
+                let (duration, reason) = match time {
+                    "perm" | "permanent" => (None, reason),
+                    _ => match bans::parse_duration(time) {
+                        Some(duration) => (Some(duration), reason),
+                        None if time.starts_with(|c: char| c.is_ascii_digit()) => {
+                            return Err(format!("invalid ban time `{}`", time))
+                        }
+                        None => (None, rest.as_str()),
+                    },
+                };
+
+                let reason = Some(reason.trim().to_string()).filter(|reason| !reason.is_empty());
+                Ok(Self::Ban(player, duration, reason))
+            }
+            "unban" if !args.is_empty() => Ok(Self::Unban(args.to_string())),
+            "unban" => Err(String::from("usage: unban <ip|name>")),
+            "bans" => Ok(Self::Bans),
+            "say" if !args.is_empty() => Ok(Self::Say(args.to_string())),
+            "say" => Err(String::from("usage: say <message>")),
+            "changemap" if !args.is_empty() => Ok(Self::ChangeMap(args.to_string())),
+            "changemap" => Err(String::from("usage: changemap <map>")),
+            "set" => {
+                let (rule, value) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+                let rule = Some(rule.to_string()).filter(|rule| !rule.is_empty());
+                let value = Some(value.trim().to_string()).filter(|value| !value.is_empty());
+                Ok(Self::Set(rule, value))
+            }
+            "restart" => Ok(Self::Restart),
+            "endmatch" => Ok(Self::EndMatch),
+            "quit" | "exit" => Ok(Self::Quit(rest)),
+            "reboot" => Ok(Self::Reboot(rest)),
+            "" => Err(String::new()),
+            _ => Err(format!("unknown command `{}`, try `help`", name)),
+        }
+    }
+}
+
+/// reads commands from stdin on its own thread so the game loop never blocks
+pub struct Console {
+    receiver: Receiver<String>,
+}
+
+impl Console {
+    pub fn spawn() -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else {
+                    log::error!("failed to read from stdin");
+                    break;
+                };
+
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self { receiver }
+    }
+
+    pub fn poll(&self) -> Option<String> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+/// parses and runs a command line, shared by the console and rcon
+pub fn run(
+    line: &str,
+    server: &mut RenetServer,
+    transport: &NetcodeServerTransport,
+    state: &mut ServerState,
+    game: &mut Match,
+) -> Result<String, String> {
+    Command::parse(line).and_then(|command| execute(command, server, transport, state, game))
+}
+
+/// runs an admin command between two ticks and returns what should be printed back
+pub fn execute(
+    command: Command,
+    server: &mut RenetServer,
+    transport: &NetcodeServerTransport,
+    state: &mut ServerState,
+    game: &mut Match,
+) -> Result<String, String> {
+    match command {
+        Command::Help => Ok(HELP.to_string()),
+        Command::Status => Ok(format!(
+            "map {} ({}) | mode {:?} | teams {} | players {}/{} | match time {}s",
+            game.settings.map,
+            game.map.header.name,
+            game.settings.mode,
+            game.settings.teams,
+            state.players.len(),
+            transport.max_clients(),
+            game.started.elapsed().as_secs(),
+        )),
+        Command::Players => {
+            let mut lines = state
+                .players
+                .values()
+                .map(|client| {
+                    format!(
+                        "{:<20} {:<16} {:<6} {:>4}ms {}",
+                        client.id.raw(),
+                        client.name,
+                        client
+                            .data
+                            .team
+                            .map(|team| format!("{:?}", team))
+                            .unwrap_or(String::from("-")),
+                        (server.rtt(client.id) * 1000.0) as u32,
+                        transport
+                            .client_addr(client.id)
+                            .map(|addr| addr.to_string())
+                            .unwrap_or(String::from("?")),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            if lines.is_empty() {
+                return Ok(String::from("no players connected"));
+            }
+
+            lines.sort();
+            Ok(lines.join("\n"))
+        }
+        Command::Kick(target, reason) => {
+            let client_id = state
+                .find_player(&target)
+                .ok_or_else(|| format!("no player matches `{}`", target))?;
+            let reason = reason.unwrap_or(String::from("kicked by an admin"));
+
+            state.kick(server, client_id, &reason);
+            Ok(format!("kicked {} ({})", target, reason))
+        }
+        Command::Ban(target, duration, reason) => {
+            let reason = reason.unwrap_or(String::from("banned by an admin"));
+
+            let ban = if let Ok(ip) = target.parse::<IpAddr>() {
+                Ban::new(Some(ip), None, reason, duration)
+            } else if let Some(client_id) = state.find_player(&target) {
+                let ip = transport.client_addr(client_id).map(|addr| addr.ip());
+                let name = state
+                    .players
+                    .get(&client_id)
+                    .map(|client| client.name.clone());
+
+                Ban::new(ip, name, reason, duration)
+            } else if target.starts_with('#') {
+                return Err(format!("no player matches `{}`", target));
+            } else {
+                // players who already left can still be banned by name
+                Ban::new(None, Some(target), reason, duration)
+            };
+
+            // everyone already playing from a banned address goes too
+            let banned = state
+                .players
+                .values()
+                .filter(|client| {
+                    let ip = transport.client_addr(client.id).map(|addr| addr.ip());
+                    match ban.ip {
+                        Some(banned) => ip == Some(banned),
+                        None => ban.name.as_ref() == Some(&client.name),
+                    }
+                })
+                .map(|client| client.id)
+                .collect::<Vec<_>>();
+
+            for client_id in banned {
+                state.kick(server, client_id, &ban.message());
+            }
+
+            let output = format!("banned {}", ban);
+            state.bans.add(ban);
+            Ok(output)
+        }
+        Command::Unban(target) => match state.bans.remove(&target) {
+            0 => Err(format!("no ban matches `{}`", target)),
+            removed => Ok(format!("lifted {} ban(s)", removed)),
+        },
+        Command::Bans => {
+            let bans = state.bans.list();
+            if bans.is_empty() {
+                return Ok(String::from("no active bans"));
+            }
+
+            Ok(bans
+                .iter()
+                .map(|ban| ban.to_string())
+                .collect::<Vec<_>>()
+                .join("\n"))
+        }
+        Command::Say(text) => {
+            state.broadcast_chat(server, ChatMessage::system(text));
+            Ok(String::from("sent"))
+        }
+        Command::ChangeMap(name) => {
+            game.change_map(server, state, &name)
+                .map_err(|err| format!("failed to load {}: {}", name, err))?;
+            Ok(format!("changed map to {}", name))
+        }
+        Command::Set(None, _) => serde_json::to_string_pretty(&game.settings.rules()?)
+            .map_err(|err| format!("failed to list rules: {}", err)),
+        Command::Set(Some(rule), None) => {
+            let rules = game.settings.rules()?;
+            rules
+                .get(&rule)
+                .map(|value| format!("{} = {}", rule, value))
+                .ok_or_else(|| format!("unknown rule `{}`", rule))
+        }
+        Command::Set(Some(rule), Some(value)) => {
+            game.settings.set(&rule, &value)?;
+            Ok(format!(
+                "{} = {} (mode and teams apply on restart)",
+                rule, value
+            ))
+        }
+        Command::Restart => {
+            game.restart(server, state);
+            Ok(String::from("match restarted"))
+        }
+        Command::EndMatch => {
+            game.end(server, state);
+            Ok(String::from("match ended"))
+        }
+        Command::Quit(reason) => {
+            game.shutdown = Some(Shutdown {
+                restart: false,
+                reason: reason.unwrap_or(String::from("stopped by an admin")),
+            });
+            Ok(String::from("shutting down"))
+        }
+        Command::Reboot(reason) => {
+            game.shutdown = Some(Shutdown {
+                restart: true,
+                reason: reason.unwrap_or(String::from("restarted by an admin")),
+            });
+            Ok(String::from("restarting"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ban_times() {
+        assert_eq!(
+            Command::parse("ban cheater 7d aimbot"),
+            Ok(Command::Ban(
+                String::from("cheater"),
+                Some(Duration::from_secs(7 * 86400)),
+                Some(String::from("aimbot"))
+            ))
+        );
+        // anything that is not a time starts the reason
+        assert_eq!(
+            Command::parse("ban cheater aimbot"),
+            Ok(Command::Ban(
+                String::from("cheater"),
+                None,
+                Some(String::from("aimbot"))
+            ))
+        );
+        assert_eq!(
+            Command::parse("ban cheater 99999999999999999999w"),
+            Err(String::from("invalid ban time `99999999999999999999w`"))
+        );
+        assert!(Command::parse("ban cheater 0m").is_err());
+    }
+}
@@ -5,9 +5,14 @@ extern crate rmp_serde as rmps;
 extern crate serde;
 extern crate serde_derive;
 
+mod bans;
+mod console;
+mod modes;
+mod rcon;
+mod rotation;
+
 use rand::prelude::*;
 use raylib::math::{self, Rectangle};
-use rmps::Serializer;
 use serde::{Deserialize, Serialize};
 
 use lib::prelude::*;
@@ -19,24 +24,46 @@ use renet::{
 };
 
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
     env::current_dir,
     fs::File,
     io::{self, Read},
     net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     time::SystemTime,
 };
 
+use bans::BanList;
+use console::Console;
+use modes::ServerMode;
+use rcon::Rcon;
+use rotation::MapVote;
+
+static SCOREBOARD_INTERVAL: Duration = Duration::from_secs(1);
+/// how long a kicked player gets to receive the reason before being dropped
+static KICK_DELAY: Duration = Duration::from_millis(200);
+
+/// exit code asking whatever supervises the server to start it again
+static EXIT_RESTART: i32 = 75;
+/// exit code when a second signal cut the graceful shutdown short
+static EXIT_INTERRUPTED: i32 = 130;
+
 fn main() {
     env_logger::init_from_env(Logger::env());
 
+    let settings = ServerSettings::load(&current_dir().unwrap().join("server.json"));
+
     let addr = Ipv4Addr::new(0, 0, 0, 0);
-    let port = 6969;
+    let port = settings.port;
 
     let public_addr = SocketAddr::new(IpAddr::V4(addr), port);
     let connection_config = ConnectionConfig::default();
 
-    let map = match Map::load("default.map") {
+    let map = match Map::load(&settings.map) {
         Ok(map) => {
             log::info!("map loaded successfuly");
             map
@@ -47,10 +74,24 @@ fn main() {
         }
     };
 
-    let map_buffer = map.serialized();
-
     let mut server: RenetServer = RenetServer::new(connection_config);
-    let mut state = ServerState::default();
+    let mut state = ServerState {
+        // the ban list lives next to the server binary
+        bans: BanList::load(
+            std::env::current_exe()
+                .ok()
+                .and_then(|exe| exe.parent().map(|dir| dir.join("bans.json")))
+                .unwrap_or_else(|| current_dir().unwrap().join("bans.json")),
+        ),
+        ..Default::default()
+    };
+    let mut game = Match::new(settings, map);
+    let mut scoreboard_sent = Instant::now();
+    log::info!(
+        "running {:?} (teams: {})",
+        game.settings.mode,
+        game.settings.teams
+    );
 
     let server_config = ServerConfig {
         current_time: SystemTime::now()
@@ -74,11 +115,76 @@ fn main() {
         }
     };
 
-    loop {
+    // first signal asks for a clean shutdown, a second one stops right away
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let handler_interrupted = Arc::clone(&interrupted);
+    if let Err(err) = ctrlc::set_handler(move || {
+        if handler_interrupted.swap(true, Ordering::SeqCst) {
+            std::process::exit(EXIT_INTERRUPTED);
+        }
+    }) {
+        log::error!("failed to install signal handler: {}", err);
+    }
+
+    let console = Console::spawn();
+    log::info!("admin console ready, type `help` for commands");
+
+    let mut rcon = match game.settings.rcon_password.as_str() {
+        "" => {
+            log::info!("rcon disabled, set `rcon_password` to enable it");
+            None
+        }
+        password => match Rcon::bind(game.settings.rcon_port, password.to_string()) {
+            Ok(rcon) => {
+                log::info!("rcon listening on port {}", game.settings.rcon_port);
+                Some(rcon)
+            }
+            Err(err) => {
+                log::error!("failed to bind rcon port: {}", err);
+                None
+            }
+        },
+    };
+
+    while game.shutdown.is_none() {
         let delta_time = DELTA_TIME;
+
+        if interrupted.load(Ordering::SeqCst) {
+            game.shutdown = Some(Shutdown {
+                restart: false,
+                reason: String::from("stopped by the host"),
+            });
+            break;
+        }
+
         server.update(delta_time);
         transport.update(delta_time, &mut server).unwrap();
 
+        while let Some(line) = console.poll() {
+            match console::run(&line, &mut server, &transport, &mut state, &mut game) {
+                Ok(output) => println!("{}", output),
+                Err(err) if err.is_empty() => {}
+                Err(err) => println!("error: {}", err),
+            }
+        }
+
+        if let Some(rcon) = &mut rcon {
+            for (addr, line) in rcon.poll() {
+                log::info!("rcon {}: {}", addr, line);
+                let result = console::run(&line, &mut server, &transport, &mut state, &mut game);
+                rcon.reply(addr, result);
+            }
+        }
+
+        state.kicks.retain(|(client_id, kicked)| {
+            if kicked.elapsed() < KICK_DELAY {
+                return true;
+            }
+
+            server.disconnect(*client_id);
+            false
+        });
+
         while let Some(event) = server.get_event() {
             match event {
                 ServerEvent::ClientConnected { client_id } => {
@@ -86,15 +192,27 @@ fn main() {
                         // get joined player gender identification
                         let stop_index = user_data.iter().position(|&byte| byte == 0).unwrap();
                         let name = String::from_utf8_lossy(&user_data[0..stop_index]).to_string();
-                        let rnd_spwn = map.get_random_spawn_position();
 
-                        let player = Client::new(
-                            client_id,
-                            (
-                                rnd_spwn.0 as f32 * WORLD_TILE_SIZE,
-                                rnd_spwn.1 as f32 * WORLD_TILE_SIZE,
-                            ),
-                        );
+                        if let Some(addr) = transport.client_addr(client_id) {
+                            if let Some(ban) = state.bans.find(addr.ip(), &name) {
+                                log::warn!("refused banned client {} {} ({})", name, addr, ban);
+                                let message = ban.message();
+                                state.refuse(&mut server, client_id, message);
+                                continue;
+                            }
+                        }
+
+                        let team = if game.settings.teams {
+                            Some(state.smallest_team())
+                        } else {
+                            None
+                        };
+                        let threats = state.threats(&[client_id], team);
+                        let rnd_spwn = game.map.spawn_position(team, &threats, &[]);
+
+                        let mut player =
+                            Client::new(client_id, name, TileGrid::grid_to_world(rnd_spwn), team);
+                        game.mode.on_player_joined(&mut player);
 
                         state.players_count += 1;
                         state.players.insert(client_id, player.clone());
@@ -109,8 +227,27 @@ fn main() {
                         server.send_message(
                             client_id,
                             DefaultChannel::ReliableOrdered,
-                            map_buffer.clone(),
+                            GameNetworkPacket::NET_MAP_INFO(game.package.info.clone())
+                                .serialized()
+                                .unwrap(),
+                        );
+                        server.send_message(
+                            client_id,
+                            DefaultChannel::ReliableOrdered,
+                            GameNetworkPacket::NET_MAP_ENTITIES(game.map.entities.clone())
+                                .serialized()
+                                .unwrap(),
                         );
+                        let changes = game.map.changes();
+                        if !changes.is_empty() {
+                            server.send_message(
+                                client_id,
+                                DefaultChannel::ReliableOrdered,
+                                GameNetworkPacket::NET_MAP_DELTA(changes)
+                                    .serialized()
+                                    .unwrap(),
+                            );
+                        }
 
                         let enemies_buffer = GameNetworkPacket::NET_WORLD_PLAYERS(
                             state
@@ -133,11 +270,65 @@ fn main() {
                             .serialized()
                             .unwrap();
                         server.broadcast_message(DefaultChannel::ReliableOrdered, rng_buffer);
+
+                        for other in state.players.values() {
+                            if !other.is_protected() {
+                                continue;
+                            }
+                            let left = other.protected_until.map_or(0.0, |until| {
+                                until
+                                    .saturating_duration_since(Instant::now())
+                                    .as_secs_f32()
+                            });
+
+                            server.send_message(
+                                client_id,
+                                DefaultChannel::ReliableOrdered,
+                                GameNetworkPacket::NET_PLAYER_PROTECTED(other.id.raw(), left)
+                                    .serialized()
+                                    .unwrap(),
+                            );
+                        }
+                        if let Some(player) = state.players.get_mut(&client_id) {
+                            player.protect(&mut server);
+                        }
+
+                        // latecomers still get a say in the next map
+                        if let Some(vote) = &game.vote {
+                            for packet in [
+                                GameNetworkPacket::NET_MAP_VOTE_START(
+                                    vote.candidates.clone(),
+                                    vote.remaining(),
+                                ),
+                                GameNetworkPacket::NET_MAP_VOTE_STATE(vote.tally()),
+                            ] {
+                                server.send_message(
+                                    client_id,
+                                    DefaultChannel::ReliableOrdered,
+                                    packet.serialized().unwrap(),
+                                );
+                            }
+                        }
                     };
                 }
                 ServerEvent::ClientDisconnected { client_id, reason } => {
+                    // refused clients never made it into the game
+                    if state.players.remove(&client_id).is_none() {
+                        continue;
+                    }
+
                     state.players_count -= 1;
-                    state.players.remove(&client_id);
+                    game.mode.on_player_left(client_id.raw());
+                    if let Some(vote) = &mut game.vote {
+                        if vote.remove(client_id.raw()) {
+                            server.broadcast_message(
+                                DefaultChannel::ReliableOrdered,
+                                GameNetworkPacket::NET_MAP_VOTE_STATE(vote.tally())
+                                    .serialized()
+                                    .unwrap(),
+                            );
+                        }
+                    }
                     server.broadcast_message(
                         DefaultChannel::ReliableUnordered,
                         GameNetworkPacket::NET_PLAYER_LEFT(client_id.raw())
@@ -165,51 +356,14 @@ fn main() {
                 ) {
                     match packet {
                         GameNetworkPacket::NET_PROJECTILE_CREATE(projectile) => {
+                            player.unprotect(&mut server);
                             state.projectiles.insert(projectile.id, projectile);
                             server.broadcast_message(DefaultChannel::ReliableUnordered, message);
                         }
 
-                        GameNetworkPacket::NET_PLAYER_DIED(id) => {
-                            if player.id.raw() == id {
-                                let rnd_spwn = map.get_random_spawn_position();
-
-                                player.data.cash -= 500;
-                                player.data.cash = nalgebra::clamp(player.data.cash, 0, 16000);
-                                player.data.position = (
-                                    rnd_spwn.0 as f32 * WORLD_TILE_SIZE,
-                                    rnd_spwn.1 as f32 * WORLD_TILE_SIZE,
-                                );
-                                player.data.weapon = WeaponVariant::AKA_69;
-
-                                let killer_id = player.data._last.clone();
-                                player.data._last = None;
-
-                                server.broadcast_message(
-                                    DefaultChannel::ReliableUnordered,
-                                    GameNetworkPacket::NET_PLAYER_RESPAWN(id, player.data)
-                                        .serialized()
-                                        .unwrap(),
-                                );
-
-                                if let Some(id) = killer_id {
-                                    if let Some(player) =
-                                        state.players.get_mut(&ClientId::from_raw(id))
-                                    {
-                                        player.data.cash += PLAYER_KILL_REWARD;
-                                        player.data.cash =
-                                            nalgebra::clamp(player.data.cash, 0, 16000);
-
-                                        server.send_message(
-                                            player.id,
-                                            DefaultChannel::ReliableUnordered,
-                                            GameNetworkPacket::NET_PLAYER_KILL_REWARD(player.data)
-                                                .serialized()
-                                                .unwrap(),
-                                        );
-                                    }
-                                }
-                            }
-                        }
+                        // deaths are settled once the server runs the health out,
+                        // a client claiming otherwise has no say
+                        GameNetworkPacket::NET_PLAYER_DIED(_) => {}
                         GameNetworkPacket::NET_PLAYER_WEAPON(variant) => {
                             let wpn = variant.weapon_instance();
                             let price = *wpn.stats.price() as i64;
@@ -250,6 +404,88 @@ fn main() {
                                 }
                             }
                         }
+                        GameNetworkPacket::NET_CHAT_SEND(channel, text) => {
+                            let text = text
+                                .chars()
+                                .filter(|c| !c.is_control())
+                                .take(CHAT_MAX_LENGTH)
+                                .collect::<String>();
+                            let text = text.trim();
+
+                            if text.is_empty() {
+                                continue;
+                            }
+
+                            if !player.may_chat() {
+                                server.send_message(
+                                    client_id,
+                                    DefaultChannel::ReliableOrdered,
+                                    GameNetworkPacket::NET_CHAT_MESSAGE(ChatMessage::system(
+                                        String::from("you are sending messages too fast"),
+                                    ))
+                                    .serialized()
+                                    .unwrap(),
+                                );
+                                continue;
+                            }
+
+                            let message = ChatMessage {
+                                sender: Some(client_id.raw()),
+                                name: player.name.clone(),
+                                channel,
+                                text: text.to_string(),
+                            };
+                            state.broadcast_chat(&mut server, message);
+                        }
+                        GameNetworkPacket::NET_MAP_VOTE(choice) => {
+                            if let Some(vote) = &mut game.vote {
+                                if vote.cast(client_id.raw(), choice as usize) {
+                                    server.broadcast_message(
+                                        DefaultChannel::ReliableOrdered,
+                                        GameNetworkPacket::NET_MAP_VOTE_STATE(vote.tally())
+                                            .serialized()
+                                            .unwrap(),
+                                    );
+                                }
+                            }
+                        }
+                        GameNetworkPacket::NET_MAP_REQUEST(hash) => {
+                            // a request for a map that was already swapped out gets no answer
+                            if hash != game.package.info.hash {
+                                continue;
+                            }
+
+                            // asking again does not restart a download under way
+                            if player
+                                .map_download
+                                .as_ref()
+                                .is_some_and(|(pending, _)| *pending == hash)
+                            {
+                                continue;
+                            }
+
+                            log::debug!(
+                                "sending map {} to {} ({} bytes)",
+                                game.package.info.name,
+                                client_id,
+                                game.package.info.size
+                            );
+                            player.map_download = Some((hash, 0));
+                        }
+                        GameNetworkPacket::NET_PLAYER_USE() => {
+                            if player.dead {
+                                continue;
+                            }
+
+                            let center = player.center();
+                            let occupied = state
+                                .players
+                                .values()
+                                .filter(|player| !player.dead)
+                                .map(Client::rectangle)
+                                .collect::<Vec<_>>();
+                            game.map.use_door(center, &occupied);
+                        }
                         GameNetworkPacket::NET_PLAYER_AMMO() => {
                             #[cfg(debug_assertions)]
                             {
@@ -300,43 +536,43 @@ fn main() {
             projectile.position.1 += projectile.velocity.1;
 
             let (px, py) = projectile.position;
-            let (gx, gy) = (
-                (px / WORLD_TILE_SIZE).round() as i32,
-                (py / WORLD_TILE_SIZE).round() as i32,
-            );
 
-            let t_offsets = POINT_OFFSETS
-                .into_iter()
-                .map(|(dx, dy)| (gx + dx as i32, gy + dy as i32))
-                .collect::<Vec<_>>();
-
-            for (x, y) in t_offsets {
-                if let Some(t) = map.tiles.get(&(x, y)) {
-                    let tile = Rectangle::new(
-                        (x as f32) * WORLD_TILE_SIZE,
-                        (y as f32) * WORLD_TILE_SIZE,
-                        WORLD_TILE_SIZE,
-                        WORLD_TILE_SIZE,
-                    );
+            // nothing left to hit past the map edges
+            if !game.map.in_of_bounds(px, py, 0.0, 0.0) {
+                hits.push(*id);
+                continue;
+            }
 
-                    if tile.check_collision_circle_rec(
-                        math::Vector2::new(px, py),
-                        ENTITY_PROJECTILE_RADIUS,
-                    ) && *t != TileVariant::GROUND
-                    {
-                        hits.push(*id);
-                        server.broadcast_message(
-                            DefaultChannel::ReliableUnordered,
-                            GameNetworkPacket::NET_PROJECTILE_IMPACT(*id, None, projectile.damage)
-                                .serialized()
-                                .unwrap(),
-                        );
-                        continue;
-                    }
-                }
+            let hit_tile = game
+                .map
+                .tiles
+                .around(TileGrid::world_to_grid(px, py), 1)
+                .find(|(position, tile)| {
+                    tile.blocks_projectiles()
+                        && TileGrid::cell_rect(*position).check_collision_circle_rec(
+                            math::Vector2::new(px, py),
+                            ENTITY_PROJECTILE_RADIUS,
+                        )
+                })
+                .map(|(position, _)| position);
+
+            if let Some(position) = hit_tile {
+                hits.push(*id);
+                server.broadcast_message(
+                    DefaultChannel::ReliableUnordered,
+                    GameNetworkPacket::NET_PROJECTILE_IMPACT(*id, None, projectile.damage)
+                        .serialized()
+                        .unwrap(),
+                );
+                game.map.damage_tile(position, projectile.damage);
+                continue;
             }
 
             for (_, player) in &mut state.players {
+                if player.dead {
+                    continue;
+                }
+
                 let (x, y) = (player.data.position.0, player.data.position.1);
                 let prect = Rectangle::new(x, y, ENTITY_PLAYER_SIZE, ENTITY_PLAYER_SIZE);
 
@@ -344,14 +580,34 @@ fn main() {
                     math::Vector2::new(px, py),
                     ENTITY_PROJECTILE_RADIUS,
                 ) {
-                    player.data._last = Some(projectile.shooter);
+                    // spawn protection soaks the projectile up
+                    let damage = player.hurt(
+                        projectile.damage,
+                        Some((projectile.shooter, projectile.weapon)),
+                    );
                     hits.push(*id);
+
+                    // hit marker feedback for the shooter
+                    if damage > 0 {
+                        server.send_message(
+                            ClientId::from_raw(projectile.shooter),
+                            DefaultChannel::ReliableUnordered,
+                            GameNetworkPacket::NET_DAMAGE_EVENT(DamageEvent {
+                                attacker: projectile.shooter,
+                                victim: player.id.raw(),
+                                damage,
+                                lethal: player.data.health == 0,
+                            })
+                            .serialized()
+                            .unwrap(),
+                        );
+                    }
                     server.broadcast_message(
                         DefaultChannel::ReliableUnordered,
                         GameNetworkPacket::NET_PROJECTILE_IMPACT(
                             *id,
                             Some(player.id.raw()),
-                            projectile.damage,
+                            damage,
                         )
                         .serialized()
                         .unwrap(),
@@ -364,23 +620,539 @@ fn main() {
         hits.iter().for_each(|i| {
             state.projectiles.remove(i);
         });
+        game.settle_deaths(&mut server, &mut state);
+
+        game.send_map_chunks(&mut server, &mut state);
+
+        let delta = game.map.take_delta();
+        if !delta.is_empty() {
+            server.broadcast_message(
+                DefaultChannel::ReliableOrdered,
+                GameNetworkPacket::NET_MAP_DELTA(delta)
+                    .serialized()
+                    .unwrap(),
+            );
+        }
+
+        game.mode
+            .update(&mut server, &mut state, &game.map, delta_time);
+        game.update(&mut server, &mut state);
+
+        if scoreboard_sent.elapsed() >= SCOREBOARD_INTERVAL {
+            scoreboard_sent = Instant::now();
+            server.broadcast_message(
+                DefaultChannel::Unreliable,
+                GameNetworkPacket::NET_SCOREBOARD(state.scoreboard(&server))
+                    .serialized()
+                    .unwrap(),
+            );
+        }
 
         transport.send_packets(&mut server);
         std::thread::sleep(delta_time);
     }
+
+    let shutdown = game.shutdown.take().unwrap();
+    shutdown.run(&mut server, &mut transport, &state);
+    std::process::exit(if shutdown.restart { EXIT_RESTART } else { 0 });
+}
+
+/// why and how the server is going down
+pub struct Shutdown {
+    /// players are told to come back in a moment
+    restart: bool,
+    reason: String,
+}
+
+impl Shutdown {
+    /// tells everyone why, gives the message time to arrive and closes every connection
+    fn run(
+        &self,
+        server: &mut RenetServer,
+        transport: &mut NetcodeServerTransport,
+        state: &ServerState,
+    ) {
+        let message = if self.restart {
+            format!("server is restarting: {}", self.reason)
+        } else {
+            format!("server is shutting down: {}", self.reason)
+        };
+        log::warn!("{}", message);
+
+        let mut scoreboard = state.scoreboard(server);
+        scoreboard.sort_by_key(|entry| std::cmp::Reverse(entry.stats.score));
+        for entry in scoreboard {
+            log::info!(
+                "final score {}: {} ({}/{}/{})",
+                entry.name,
+                entry.stats.score,
+                entry.stats.kills,
+                entry.stats.deaths,
+                entry.stats.assists
+            );
+        }
+
+        server.broadcast_message(
+            DefaultChannel::ReliableOrdered,
+            GameNetworkPacket::NET_DISCONNECT_REASON(message)
+                .serialized()
+                .unwrap(),
+        );
+
+        let started = Instant::now();
+        while started.elapsed() < KICK_DELAY {
+            server.update(DELTA_TIME);
+            if let Err(err) = transport.update(DELTA_TIME, server) {
+                log::error!("{}", err);
+                break;
+            }
+
+            transport.send_packets(server);
+            std::thread::sleep(DELTA_TIME);
+        }
+
+        server.disconnect_all();
+        transport.send_packets(server);
+        transport.disconnect_all(server);
+
+        log::info!("server stopped");
+        log::logger().flush();
+    }
+}
+
+/// everything about the match currently being played
+pub struct Match {
+    settings: ServerSettings,
+    map: Map,
+    /// compressed copy of the map handed to clients
+    package: MapPackage,
+    mode: ServerMode,
+    started: Instant,
+    last_hazard_damage: Instant,
+    /// next map poll, running while the match is over
+    vote: Option<MapVote>,
+    /// set once the server should stop after the current tick
+    shutdown: Option<Shutdown>,
+}
+
+impl Match {
+    fn new(settings: ServerSettings, map: Map) -> Self {
+        Self {
+            package: map.package(),
+            mode: ServerMode::new(settings.mode, &map),
+            started: Instant::now(),
+            last_hazard_damage: Instant::now(),
+            vote: None,
+            shutdown: None,
+            settings,
+            map,
+        }
+    }
+
+    /// ends the match once the time limit is up
+    fn update(&mut self, server: &mut RenetServer, state: &mut ServerState) {
+        if let Some(vote) = &self.vote {
+            if vote.is_over() {
+                let winner = vote.winner();
+                self.vote = None;
+
+                server.broadcast_message(
+                    DefaultChannel::ReliableOrdered,
+                    GameNetworkPacket::NET_MAP_VOTE_END(winner.clone())
+                        .serialized()
+                        .unwrap(),
+                );
+                self.next_map(server, state, &winner);
+            }
+
+            return;
+        }
+
+        self.trigger_entities(server, state);
+
+        if self.last_hazard_damage.elapsed() >= Duration::from_secs(1) {
+            self.last_hazard_damage = Instant::now();
+            self.damage_hazards(server, state);
+        }
+        self.settle_deaths(server, state);
+
+        let limit = self.settings.time_limit;
+        if limit > 0 && self.started.elapsed() >= Duration::from_secs(limit as u64) {
+            self.end(server, state);
+        }
+    }
+
+    /// teleports and launches players stepping onto teleporters and pads
+    fn trigger_entities(&self, server: &mut RenetServer, state: &mut ServerState) {
+        for player in state.players.values_mut().filter(|player| !player.dead) {
+            let (x, y) = player.center();
+            let position = TileGrid::world_to_grid(x, y);
+            let entered = player.standing_on != Some(position);
+            player.standing_on = Some(position);
+
+            // fresh off a teleporter or still in the air
+            if !entered || player.triggers_after > Instant::now() {
+                continue;
+            }
+
+            if let Some(exit) = MapFile::teleporter_exit(&self.map.entities, position) {
+                let (x, y) = Client::world_position(exit);
+                player.data.position = (x, y);
+                player.standing_on = Some(exit);
+                player.triggers_after =
+                    Instant::now() + Duration::from_secs_f32(TELEPORTER_COOLDOWN);
+
+                server.send_message(
+                    player.id,
+                    DefaultChannel::ReliableOrdered,
+                    GameNetworkPacket::NET_PLAYER_TELEPORT((x, y))
+                        .serialized()
+                        .unwrap(),
+                );
+            } else if let Some(target) = MapFile::pad_target(&self.map.entities, position) {
+                player.triggers_after = Instant::now() + Duration::from_secs_f32(PAD_FLIGHT_TIME);
+
+                server.send_message(
+                    player.id,
+                    DefaultChannel::ReliableOrdered,
+                    GameNetworkPacket::NET_PLAYER_LAUNCH(
+                        Client::world_position(target),
+                        PAD_FLIGHT_TIME,
+                    )
+                    .serialized()
+                    .unwrap(),
+                );
+            }
+        }
+    }
+
+    /// settles the death of everyone whose health ran out, the server has the final say so
+    /// a client that never reports its death still dies
+    fn settle_deaths(&self, server: &mut RenetServer, state: &mut ServerState) {
+        let mut dying = state
+            .players
+            .values()
+            .filter(|player| !player.dead && player.data.health <= 0)
+            .map(|player| player.id)
+            .collect::<Vec<_>>();
+        dying.sort_by_key(|id| id.raw());
+
+        for id in dying {
+            self.kill(server, state, id);
+        }
+    }
+
+    /// credits the killer and assisters, announces the kill and respawns the victim
+    fn kill(&self, server: &mut RenetServer, state: &mut ServerState, client_id: ClientId) {
+        let Some(player) = state.players.get_mut(&client_id) else {
+            return;
+        };
+        let id = client_id.raw();
+
+        player.dead = true;
+        player.data.health = 0;
+        player.data.cash -= 500;
+        player.data.cash = nalgebra::clamp(player.data.cash, 0, 16000);
+
+        let killer_id = player.data._last.clone();
+        let assisters = player
+            .contributors
+            .drain()
+            .filter(|contributor| Some(*contributor) != killer_id)
+            .filter(|contributor| *contributor != id)
+            .collect::<Vec<_>>();
+        let kill_event = KillEvent {
+            killer: killer_id,
+            victim: id,
+            weapon: player.last_weapon.take(),
+            assisters: assisters.clone(),
+        };
+        player.data._last = None;
+        player.stats.deaths += 1;
+
+        if self.mode.respawns() {
+            state.respawn(server, &self.map, &[client_id]);
+        }
+
+        if let Some(id) = killer_id {
+            if let Some(player) = state.players.get_mut(&ClientId::from_raw(id)) {
+                player.data.cash += PLAYER_KILL_REWARD;
+                player.data.cash = nalgebra::clamp(player.data.cash, 0, 16000);
+                player.stats.kills += 1;
+                player.stats.score += SCORE_KILL;
+
+                server.send_message(
+                    player.id,
+                    DefaultChannel::ReliableUnordered,
+                    GameNetworkPacket::NET_PLAYER_KILL_REWARD(player.data)
+                        .serialized()
+                        .unwrap(),
+                );
+            }
+        }
+
+        for id in assisters {
+            if let Some(player) = state.players.get_mut(&ClientId::from_raw(id)) {
+                player.stats.assists += 1;
+                player.stats.score += SCORE_ASSIST;
+            }
+        }
+
+        server.broadcast_message(
+            DefaultChannel::ReliableUnordered,
+            GameNetworkPacket::NET_KILL_EVENT(kill_event)
+                .serialized()
+                .unwrap(),
+        );
+    }
+
+    /// feeds map downloads a few chunks per tick, a large map neither overruns the channel
+    /// nor holds up the game traffic queued behind it
+    fn send_map_chunks(&self, server: &mut RenetServer, state: &mut ServerState) {
+        for player in state.players.values_mut() {
+            let Some((hash, next)) = &mut player.map_download else {
+                continue;
+            };
+            // the map changed under the download, the client asks for the new one
+            if *hash != self.package.info.hash {
+                player.map_download = None;
+                continue;
+            }
+
+            for (index, chunk) in self
+                .package
+                .chunks()
+                .skip(*next as usize)
+                .take(MAP_CHUNKS_PER_TICK)
+            {
+                let message = GameNetworkPacket::NET_MAP_CHUNK(hash.clone(), index, chunk.to_vec())
+                    .serialized()
+                    .unwrap();
+                if !server.can_send_message(
+                    player.id,
+                    DefaultChannel::ReliableOrdered,
+                    message.len(),
+                ) {
+                    break;
+                }
+
+                server.send_message(player.id, DefaultChannel::ReliableOrdered, message);
+                *next = index + 1;
+            }
+
+            if *next >= self.package.info.chunks {
+                player.map_download = None;
+            }
+        }
+    }
+
+    /// hurts everyone standing on a tile that deals damage
+    fn damage_hazards(&self, server: &mut RenetServer, state: &mut ServerState) {
+        for player in state.players.values_mut().filter(|player| !player.dead) {
+            let (x, y) = player.data.position;
+            let position =
+                TileGrid::world_to_grid(x + ENTITY_PLAYER_SIZE / 2.0, y + ENTITY_PLAYER_SIZE / 2.0);
+            let Some(tile) = self.map.tiles.get(position) else {
+                continue;
+            };
+
+            let damage = player.hurt(tile.properties().damage, None);
+            if damage > 0 {
+                server.broadcast_message(
+                    DefaultChannel::ReliableUnordered,
+                    GameNetworkPacket::NET_PLAYER_DAMAGE(player.id.raw(), damage)
+                        .serialized()
+                        .unwrap(),
+                );
+            }
+        }
+    }
+
+    /// announces the winner and moves on to the next map, through a vote when enabled
+    fn end(&mut self, server: &mut RenetServer, state: &mut ServerState) {
+        if self.vote.is_some() {
+            return;
+        }
+
+        let text = match state
+            .players
+            .values()
+            .max_by_key(|client| client.stats.score)
+        {
+            Some(winner) => format!(
+                "{} won the match with {} points",
+                winner.name, winner.stats.score
+            ),
+            None => String::from("the match is over"),
+        };
+        log::info!("{}", text);
+        state.broadcast_chat(server, ChatMessage::system(text));
+
+        let candidates = rotation::candidates(&self.settings.maps, &self.settings.map);
+        if !self.settings.map_vote || candidates.len() < 2 {
+            self.next_map(server, state, &candidates[0]);
+            return;
+        }
+
+        log::info!("voting for the next map between {:?}", candidates);
+        let vote = MapVote::new(candidates);
+        server.broadcast_message(
+            DefaultChannel::ReliableOrdered,
+            GameNetworkPacket::NET_MAP_VOTE_START(vote.candidates.clone(), vote.remaining())
+                .serialized()
+                .unwrap(),
+        );
+        self.vote = Some(vote);
+    }
+
+    /// moves on to the given map, replaying the current one if it fails to load
+    fn next_map(&mut self, server: &mut RenetServer, state: &mut ServerState, name: &str) {
+        if let Err(err) = self.change_map(server, state, name) {
+            log::error!("failed to load {}: {}", name, err);
+            self.restart(server, state);
+        }
+    }
+
+    /// swaps the map under everyone's feet, the match starts over on it
+    fn change_map(
+        &mut self,
+        server: &mut RenetServer,
+        state: &mut ServerState,
+        name: &str,
+    ) -> Result<(), io::Error> {
+        let map = Map::load(name)?;
+        log::info!("changing map to {}", name);
+
+        self.settings.map = name.to_string();
+        self.package = map.package();
+        self.map = map;
+        server.broadcast_message(
+            DefaultChannel::ReliableOrdered,
+            GameNetworkPacket::NET_MAP_INFO(self.package.info.clone())
+                .serialized()
+                .unwrap(),
+        );
+        server.broadcast_message(
+            DefaultChannel::ReliableOrdered,
+            GameNetworkPacket::NET_MAP_ENTITIES(self.map.entities.clone())
+                .serialized()
+                .unwrap(),
+        );
+
+        self.reset(server, state);
+        state.broadcast_chat(server, ChatMessage::system(format!("now playing {}", name)));
+        Ok(())
+    }
+
+    fn restart(&mut self, server: &mut RenetServer, state: &mut ServerState) {
+        self.reset(server, state);
+        state.broadcast_chat(
+            server,
+            ChatMessage::system(String::from("the match has been restarted")),
+        );
+    }
+
+    /// resets scores and the game mode, then respawns everyone
+    fn reset(&mut self, server: &mut RenetServer, state: &mut ServerState) {
+        log::info!(
+            "starting match as {:?} (teams: {})",
+            self.settings.mode,
+            self.settings.teams
+        );
+
+        if !self.map.header.modes.is_empty() && !self.map.header.modes.contains(&self.settings.mode)
+        {
+            log::warn!(
+                "{} is not made for {:?}, it supports {:?}",
+                self.map.header.name,
+                self.settings.mode,
+                self.map.header.modes
+            );
+        }
+
+        self.mode = ServerMode::new(self.settings.mode, &self.map);
+        self.started = Instant::now();
+        state.projectiles.clear();
+
+        let restored = self.map.restore();
+        if !restored.is_empty() {
+            server.broadcast_message(
+                DefaultChannel::ReliableOrdered,
+                GameNetworkPacket::NET_MAP_DELTA(restored)
+                    .serialized()
+                    .unwrap(),
+            );
+        }
+
+        // an admin may cut a running vote short
+        if self.vote.take().is_some() {
+            server.broadcast_message(
+                DefaultChannel::ReliableOrdered,
+                GameNetworkPacket::NET_MAP_VOTE_END(self.settings.map.clone())
+                    .serialized()
+                    .unwrap(),
+            );
+        }
+
+        let mut ids = state.players.keys().copied().collect::<Vec<_>>();
+        ids.sort_by_key(|id| id.raw());
+
+        for (index, id) in ids.iter().enumerate() {
+            let Some(player) = state.players.get_mut(id) else {
+                continue;
+            };
+
+            player.stats = PlayerStats::default();
+            player.data.cash = 200;
+            player.data.team = match (self.settings.teams, index % 2) {
+                (false, _) => None,
+                (true, 0) => Some(Team::RED),
+                (true, _) => Some(Team::BLUE),
+            };
+        }
+
+        state.respawn(server, &self.map, &ids);
+    }
 }
 
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct Client {
     id: ClientId,
+    name: String,
     data: PlayerData,
+    stats: PlayerStats,
+    /// everyone who damaged the player since their last respawn
+    contributors: HashSet<RawClientId>,
+    /// weapon of the last hit taken
+    last_weapon: Option<WeaponVariant>,
+    /// when the recent chat messages were sent, used for rate limiting
+    chat_times: VecDeque<Instant>,
+    /// cell under the player's center, teleporters and pads trigger when it changes
+    standing_on: Option<(i32, i32)>,
+    /// teleporters and pads ignore the player until then
+    triggers_after: Instant,
+    /// hash of the map being sent to the player and the next chunk to go out
+    map_download: Option<(String, u32)>,
+    /// no damage is taken until then, firing ends it early
+    protected_until: Option<Instant>,
+    dead: bool,
 }
 
 impl Client {
-    fn new(id: ClientId, (x, y): (f32, f32)) -> Self {
+    fn new(id: ClientId, name: String, (x, y): (f32, f32), team: Option<Team>) -> Self {
         Self {
             id,
+            name,
+            dead: false,
+            stats: PlayerStats::default(),
+            contributors: HashSet::new(),
+            last_weapon: None,
+            chat_times: VecDeque::new(),
+            standing_on: None,
+            triggers_after: Instant::now(),
+            map_download: None,
+            protected_until: None,
             data: PlayerData {
                 _id: id.raw(),
                 _last: None,
@@ -389,9 +1161,120 @@ impl Client {
                 health: 100,
                 weapon: WeaponVariant::DEAN_1911,
                 cash: 200,
+                team,
             },
         }
     }
+
+    fn rectangle(&self) -> Rectangle {
+        let (x, y) = self.data.position;
+        Rectangle::new(x, y, ENTITY_PLAYER_SIZE, ENTITY_PLAYER_SIZE)
+    }
+
+    fn center(&self) -> (f32, f32) {
+        let (x, y) = self.data.position;
+        (x + ENTITY_PLAYER_SIZE / 2.0, y + ENTITY_PLAYER_SIZE / 2.0)
+    }
+
+    /// top left corner that puts a player in the middle of the cell
+    fn world_position(cell: (i32, i32)) -> (f32, f32) {
+        let (x, y) = TileGrid::grid_to_world(cell);
+        let margin = (WORLD_TILE_SIZE - ENTITY_PLAYER_SIZE) / 2.0;
+        (x + margin, y + margin)
+    }
+
+    fn is_protected(&self) -> bool {
+        self.protected_until
+            .is_some_and(|until| Instant::now() < until)
+    }
+
+    /// starts the spawn protection window and shows it to everyone
+    fn protect(&mut self, server: &mut RenetServer) {
+        self.protected_until =
+            Some(Instant::now() + Duration::from_secs_f32(SPAWN_PROTECTION_TIME));
+        server.broadcast_message(
+            DefaultChannel::ReliableOrdered,
+            GameNetworkPacket::NET_PLAYER_PROTECTED(self.id.raw(), SPAWN_PROTECTION_TIME)
+                .serialized()
+                .unwrap(),
+        );
+    }
+
+    /// ends spawn protection early, players give it up by firing
+    fn unprotect(&mut self, server: &mut RenetServer) {
+        if self
+            .protected_until
+            .take()
+            .is_some_and(|until| Instant::now() < until)
+        {
+            server.broadcast_message(
+                DefaultChannel::ReliableOrdered,
+                GameNetworkPacket::NET_PLAYER_PROTECTED(self.id.raw(), 0.0)
+                    .serialized()
+                    .unwrap(),
+            );
+        }
+    }
+
+    /// applies damage on the server side, `source` is credited for the kill
+    ///
+    /// returns the damage actually dealt, none while spawn protected
+    fn hurt(&mut self, damage: Damage, source: Option<(RawClientId, WeaponVariant)>) -> Damage {
+        if self.is_protected() {
+            return 0;
+        }
+
+        // damage past `Health::MAX` would turn negative and heal
+        let damage_taken = damage.min(Health::MAX as Damage) as Health;
+        self.data.health = self.data.health.saturating_sub(damage_taken).max(0);
+
+        if let Some((source, weapon)) = source {
+            self.data._last = Some(source);
+            self.last_weapon = Some(weapon);
+            self.contributors.insert(source);
+        }
+
+        damage
+    }
+
+    /// records a chat message unless the player is over the rate limit
+    fn may_chat(&mut self) -> bool {
+        let window = Duration::from_secs_f32(CHAT_RATE_WINDOW);
+        while let Some(sent) = self.chat_times.front() {
+            if sent.elapsed() < window {
+                break;
+            }
+
+            self.chat_times.pop_front();
+        }
+
+        if self.chat_times.len() >= CHAT_RATE_LIMIT {
+            return false;
+        }
+
+        self.chat_times.push_back(Instant::now());
+        true
+    }
+
+    /// puts the player back on the given spawn and lets everyone know
+    fn respawn(&mut self, server: &mut RenetServer, spawn: (i32, i32)) {
+        self.dead = false;
+        self.contributors.clear();
+        self.last_weapon = None;
+        self.data._last = None;
+        self.data.health = ENTITY_PLAYER_MAX_HEALTH;
+        self.data.weapon = WeaponVariant::AKA_69;
+        self.data.position = Self::world_position(spawn);
+        self.standing_on = None;
+
+        server.broadcast_message(
+            DefaultChannel::ReliableUnordered,
+            GameNetworkPacket::NET_PLAYER_RESPAWN(self.id.raw(), self.data)
+                .serialized()
+                .unwrap(),
+        );
+        self.protect(server);
+    }
 }
 
 #[derive(Debug)]
@@ -399,6 +1282,9 @@ pub struct ServerState {
     players: HashMap<ClientId, Client>,
     players_count: usize,
     projectiles: HashMap<RawProjectileId, ProjectileData>,
+    /// players waiting to be disconnected once their kick reason went out
+    kicks: Vec<(ClientId, Instant)>,
+    bans: BanList,
 }
 
 impl Default for ServerState {
@@ -407,11 +1293,55 @@ impl Default for ServerState {
             players: HashMap::new(),
             projectiles: HashMap::new(),
             players_count: 0,
+            kicks: Vec::new(),
+            bans: BanList::default(),
         }
     }
 }
 
 impl ServerState {
+    /// centers of the living players a player of `team` should not spawn next to, teammates
+    /// and the players in `except` left out
+    fn threats(&self, except: &[ClientId], team: Option<Team>) -> Vec<(f32, f32)> {
+        self.players
+            .values()
+            .filter(|client| !except.contains(&client.id) && !client.dead)
+            .filter(|client| team.is_none() || client.data.team != team)
+            .map(Client::center)
+            .collect()
+    }
+
+    /// respawns the players together, every spawn is picked away from the enemies still
+    /// standing and from the spawns already handed out before anyone is moved
+    fn respawn(&mut self, server: &mut RenetServer, map: &Map, ids: &[ClientId]) {
+        let mut picked: Vec<(ClientId, Option<Team>, (i32, i32))> = Vec::new();
+        for &id in ids {
+            let Some(team) = self.players.get(&id).map(|client| client.data.team) else {
+                continue;
+            };
+
+            let mut threats = self.threats(ids, team);
+            threats.extend(
+                picked
+                    .iter()
+                    .filter(|(_, other, _)| team.is_none() || *other != team)
+                    .map(|(_, _, cell)| {
+                        let rect = TileGrid::cell_rect(*cell);
+                        (rect.x + rect.width / 2.0, rect.y + rect.height / 2.0)
+                    }),
+            );
+            let taken = picked.iter().map(|(_, _, cell)| *cell).collect::<Vec<_>>();
+
+            picked.push((id, team, map.spawn_position(team, &threats, &taken)));
+        }
+
+        for (id, _, cell) in picked {
+            if let Some(player) = self.players.get_mut(&id) {
+                player.respawn(server, cell);
+            }
+        }
+    }
+
     pub fn get_players_raw(&self) -> HashMap<u64, PlayerData> {
         HashMap::from(
             self.players
@@ -423,78 +1353,438 @@ impl ServerState {
                 .collect::<HashMap<u64, PlayerData>>(),
         )
     }
+
+    pub fn scoreboard(&self, server: &RenetServer) -> Vec<ScoreboardEntry> {
+        self.players
+            .values()
+            .map(|client| ScoreboardEntry {
+                id: client.id.raw(),
+                name: client.name.clone(),
+                team: client.data.team,
+                stats: client.stats,
+                ping: (server.rtt(client.id) * 1000.0) as u32,
+            })
+            .collect()
+    }
+
+    /// routes a chat message to everyone or only to the sender's team
+    pub fn broadcast_chat(&self, server: &mut RenetServer, message: ChatMessage) {
+        log::info!("[{:?}] {}: {}", message.channel, message.name, message.text);
+
+        let sender = message.sender;
+        let team = sender
+            .and_then(|id| self.players.get(&ClientId::from_raw(id)))
+            .and_then(|client| client.data.team);
+        let channel = message.channel;
+        let buffer = GameNetworkPacket::NET_CHAT_MESSAGE(message)
+            .serialized()
+            .unwrap();
+
+        match (channel, team) {
+            (ChatChannel::TEAM, Some(team)) => {
+                for client in self.players.values() {
+                    if client.data.team == Some(team) {
+                        server.send_message(
+                            client.id,
+                            DefaultChannel::ReliableOrdered,
+                            buffer.clone(),
+                        );
+                    }
+                }
+            }
+            // nobody shares a team with a player who has none
+            (ChatChannel::TEAM, None) => {
+                if let Some(sender) = sender {
+                    server.send_message(
+                        ClientId::from_raw(sender),
+                        DefaultChannel::ReliableOrdered,
+                        buffer,
+                    );
+                }
+            }
+            _ => server.broadcast_message(DefaultChannel::ReliableOrdered, buffer),
+        }
+    }
+
+    /// looks a player up by id, by the last four digits of it (`#1234`) or by name
+    pub fn find_player(&self, target: &str) -> Option<ClientId> {
+        if let Ok(id) = target.trim_start_matches('#').parse::<u64>() {
+            let by_id = self.players.keys().find(|client_id| {
+                client_id.raw() == id || (target.starts_with('#') && client_id.raw() % 10000 == id)
+            });
+
+            if by_id.is_some() {
+                return by_id.copied();
+            }
+        }
+
+        self.players
+            .values()
+            .find(|client| client.name.eq_ignore_ascii_case(target))
+            .map(|client| client.id)
+    }
+
+    /// removes a player from the game and lets everyone know why
+    pub fn kick(&mut self, server: &mut RenetServer, client_id: ClientId, reason: &str) {
+        let Some(client) = self.players.get(&client_id) else {
+            return;
+        };
+
+        log::warn!("kicking {} ({}): {}", client.name, client_id, reason);
+        let message = ChatMessage::system(format!("{} was kicked: {}", client.name, reason));
+
+        self.refuse(server, client_id, reason.to_string());
+        self.broadcast_chat(server, message);
+    }
+
+    /// tells the client why it is being dropped, the connection is closed shortly after
+    /// since renet's disconnect reasons can not carry any text
+    pub fn refuse(&mut self, server: &mut RenetServer, client_id: ClientId, reason: String) {
+        server.send_message(
+            client_id,
+            DefaultChannel::ReliableOrdered,
+            GameNetworkPacket::NET_DISCONNECT_REASON(reason)
+                .serialized()
+                .unwrap(),
+        );
+
+        if !self.kicks.iter().any(|(id, _)| *id == client_id) {
+            self.kicks.push((client_id, Instant::now()));
+        }
+    }
+
+    /// team new players should join to keep both sides even
+    pub fn smallest_team(&self) -> Team {
+        let reds = self
+            .players
+            .values()
+            .filter(|client| client.data.team == Some(Team::RED))
+            .count();
+
+        if reds * 2 <= self.players.len() {
+            Team::RED
+        } else {
+            Team::BLUE
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, PartialEq)]
 pub struct Map {
-    pub tiles: Tiles,
+    pub header: MapHeader,
+    pub tiles: TileGrid,
+    /// tiles as loaded, what clients download and what a restart puts back
+    original: TileGrid,
+    /// floor, decoration and overhead tiles, only passed on to clients
+    layers: BTreeMap<MapLayer, TileGrid>,
+    /// health left on destructible cells that were hit
+    health: HashMap<(i32, i32), Health>,
+    /// changes not sent to clients yet
+    delta: MapDelta,
+    pub spawns: Vec<SpawnPoint>,
+    /// open ground players appear on when the map has no spawn points of its own
+    ground_spawns: Vec<(i32, i32)>,
+    pub entities: Vec<MapEntity>,
+    /// capture zones as grid rectangles (x, y, width, height)
+    pub zones: Vec<(i32, i32, i32, i32)>,
 }
 
 impl Map {
     fn load(name: &str) -> Result<Self, io::Error> {
-        let map_path = current_dir().unwrap().join("maps").join(name);
+        if name == RANDOM_MAP {
+            let file = generate(&GeneratorSettings {
+                seed: random(),
+                ..Default::default()
+            });
+            log::info!("generated map {}", file.header.name);
+            return Ok(Self::from_file(file));
+        }
 
-        let mut map: Tiles = HashMap::new();
-        match File::open(map_path) {
-            Ok(ref mut file) => {
-                let mut buffer = String::new();
-                match file.read_to_string(&mut buffer) {
-                    Ok(_bytes) => {
-                        for (y, line) in buffer.lines().enumerate() {
-                            for (x, symbol) in line.chars().enumerate() {
-                                let tile = match symbol {
-                                    'S' => TileVariant::WALL_SIDE,
-                                    'T' => TileVariant::WALL_TOP,
-                                    _ => TileVariant::GROUND,
-                                };
-                                map.insert((x as i32, y as i32), tile);
-                            }
-                        }
+        let map_path = current_dir().unwrap().join("maps").join(name);
+        let source = std::fs::read_to_string(map_path)?;
 
-                        Ok(Self { tiles: map })
-                    }
-                    Err(err) => Err(err),
+        // a map without ground would leave nowhere to spawn
+        for diagnostic in lint(name, &source) {
+            match diagnostic.severity {
+                Severity::ERROR => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        diagnostic.to_string(),
+                    ))
                 }
+                Severity::WARNING => log::warn!("{}: {}", name, diagnostic),
             }
-            Err(err) => Err(err),
         }
+
+        let file = MapFile::parse(name, &source)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        Ok(Self::from_file(file))
     }
 
-    fn serialized(&self) -> Vec<u8> {
-        let packet = GameNetworkPacket::NET_WORLD_MAP(self.tiles.clone());
-        let mut map_buffer = Vec::new();
+    fn from_file(mut file: MapFile) -> Self {
+        // doors are tiles while playing so collisions and deltas treat them like any other
+        for entity in &file.entities {
+            if let MapEntityKind::DOOR(open) = entity.kind {
+                let door = if open {
+                    TileVariant::DOOR_OPEN
+                } else {
+                    TileVariant::DOOR
+                };
+                file.tiles.set(entity.position, Some(door));
+            }
+        }
 
-        packet
-            .serialize(&mut Serializer::new(&mut map_buffer))
-            .unwrap();
+        Self {
+            zones: file.zones(),
+            ground_spawns: Self::ground_spawns(&file.tiles),
+            header: file.header,
+            original: file.tiles.clone(),
+            layers: file.layers,
+            health: HashMap::new(),
+            delta: MapDelta::default(),
+            tiles: file.tiles,
+            spawns: file.spawns,
+            entities: file.entities,
+        }
+    }
+
+    fn package(&self) -> MapPackage {
+        let tiles = MapTiles {
+            collision: self.original.clone(),
+            layers: self.layers.clone(),
+        };
+        let package = MapPackage::new(&self.header.name, &tiles);
+        log::info!(
+            "map {} packed into {} bytes ({})",
+            package.info.name,
+            package.info.size,
+            package.info.hash
+        );
 
-        map_buffer
+        package
     }
 
-    fn get_ground(&self) -> Tiles {
-        let mut map: Tiles = HashMap::new();
-        for ((x, y), tile) in &self.tiles {
-            if *tile == TileVariant::GROUND {
-                map.insert((*x, *y), tile.clone());
+    /// everything broken or damaged so far, for players joining mid match
+    fn changes(&self) -> MapDelta {
+        let mut delta = MapDelta::default();
+        for (position, tile) in self.tiles.iter() {
+            let damage = self.damage_stage(position, tile);
+            if damage > 0 || self.original.get(position) != Some(tile) {
+                delta.push(TileChange {
+                    position,
+                    tile,
+                    damage,
+                });
             }
         }
 
-        map
+        delta
     }
 
-    fn get_random_spawn_position(&self) -> (i32, i32) {
-        let mut rng = thread_rng();
-        let ground_tiles = self.get_ground();
-        let ground_tiles = ground_tiles
+    /// puts back everything broken during the match, returns the cells that changed
+    fn restore(&mut self) -> MapDelta {
+        let mut restored = MapDelta::default();
+        for change in self.changes().changes {
+            if let Some(tile) = self.original.get(change.position) {
+                restored.push(TileChange {
+                    tile,
+                    damage: 0,
+                    ..change
+                });
+            }
+        }
+
+        self.tiles = self.original.clone();
+        self.health.clear();
+        self.delta = MapDelta::default();
+        restored
+    }
+
+    fn damage_stage(&self, position: (i32, i32), tile: TileVariant) -> u8 {
+        match (self.health.get(&position), tile.properties().health) {
+            (Some(health), Some(max)) => damage_stage(*health, max),
+            _ => 0,
+        }
+    }
+
+    /// wears down a destructible cell, it looks worse at every damage stage and turns
+    /// into ground once it breaks
+    fn damage_tile(&mut self, position: (i32, i32), damage: Damage) {
+        let Some(tile) = self.tiles.get(position) else {
+            return;
+        };
+        let Some(max) = tile.properties().health else {
+            return;
+        };
+
+        let stage = self.damage_stage(position, tile);
+        let health = self.health.entry(position).or_insert(max);
+        *health = (*health as i32 - damage as i32).max(0) as Health;
+
+        if *health == 0 {
+            self.health.remove(&position);
+            self.tiles.set(position, Some(TileVariant::GROUND));
+            self.delta.push(TileChange {
+                position,
+                tile: TileVariant::GROUND,
+                damage: 0,
+            });
+        } else if self.damage_stage(position, tile) != stage {
+            self.delta.push(TileChange {
+                position,
+                tile,
+                damage: self.damage_stage(position, tile),
+            });
+        }
+    }
+
+    /// opens or closes the door closest to `(x, y)` within reach, a door stays open
+    /// while someone is standing in it
+    fn use_door(&mut self, (x, y): (f32, f32), occupied: &[Rectangle]) {
+        let door = self
+            .entities
+            .iter()
+            .filter(|entity| matches!(entity.kind, MapEntityKind::DOOR(_)))
+            .map(|entity| {
+                let rect = TileGrid::cell_rect(entity.position);
+                let (dx, dy) = (
+                    rect.x + rect.width / 2.0 - x,
+                    rect.y + rect.height / 2.0 - y,
+                );
+                (entity.position, (dx * dx + dy * dy).sqrt())
+            })
+            .filter(|(_, distance)| *distance <= DOOR_USE_RANGE)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(position, _)| position);
+        let Some(position) = door else {
+            return;
+        };
+
+        let tile = match self.tiles.get(position) {
+            Some(TileVariant::DOOR) => TileVariant::DOOR_OPEN,
+            Some(TileVariant::DOOR_OPEN) => {
+                let cell = TileGrid::cell_rect(position);
+                if occupied.iter().any(|rect| rect.check_collision_recs(&cell)) {
+                    return;
+                }
+                TileVariant::DOOR
+            }
+            _ => return,
+        };
+
+        self.tiles.set(position, Some(tile));
+        self.delta.push(TileChange {
+            position,
+            tile,
+            damage: 0,
+        });
+    }
+
+    /// changes since the last call, sent out once per tick
+    fn take_delta(&mut self) -> MapDelta {
+        std::mem::take(&mut self.delta)
+    }
+
+    /// ground cells with all four sides open, any ground at all when there are none so
+    /// players are never boxed in by walls on arrival
+    fn ground_spawns(tiles: &TileGrid) -> Vec<(i32, i32)> {
+        let ground = tiles
+            .iter()
+            .filter(|(_, tile)| *tile == TileVariant::GROUND)
+            .map(|(position, _)| position)
+            .collect::<Vec<_>>();
+        let open = ground
+            .iter()
+            .copied()
+            .filter(|&position| {
+                tiles
+                    .adjacent(position)
+                    .filter(|(_, tile)| !tile.is_solid())
+                    .count()
+                    == 4
+            })
+            .collect::<Vec<_>>();
+
+        if open.is_empty() {
+            ground
+        } else {
+            open
+        }
+    }
+
+    /// grid position to spawn at, the map's own spawn points are preferred over open ground,
+    /// a team without spawns of its own shares the neutral ones
+    ///
+    /// candidates are scored by how far the nearest of `threats` is, halved when it can see
+    /// the cell, and one of the best few is picked so spawns stay hard to predict
+    fn spawn_position(
+        &self,
+        team: Option<Team>,
+        threats: &[(f32, f32)],
+        taken: &[(i32, i32)],
+    ) -> (i32, i32) {
+        // without teams every spawn point is fair game
+        let team_spawns = self
+            .spawns
             .iter()
-            .collect::<Vec<(&(i32, i32), &TileVariant)>>();
-        let tile_pair = ground_tiles.choose(&mut rng).unwrap();
+            .filter(|spawn| team.is_none() || spawn.team == team)
+            .map(|spawn| spawn.position)
+            .collect::<Vec<_>>();
+        let free_spawns = self
+            .spawns
+            .iter()
+            .filter(|spawn| spawn.team.is_none())
+            .map(|spawn| spawn.position)
+            .collect::<Vec<_>>();
+
+        let mut candidates = [team_spawns, free_spawns, self.ground_spawns.clone()]
+            .into_iter()
+            .find(|cells| !cells.is_empty())
+            .unwrap_or_default()
+            .into_iter()
+            // a door may have been shut on it
+            .filter(|&cell| !self.tiles.is_solid(cell))
+            .map(|cell| (cell, self.spawn_score(cell, threats)))
+            .collect::<Vec<_>>();
+        // players share a spawn only once every one of them is taken
+        if candidates.iter().any(|(cell, _)| !taken.contains(cell)) {
+            candidates.retain(|(cell, _)| !taken.contains(cell));
+        }
 
-        *tile_pair.0
+        let mut rng = thread_rng();
+        candidates.shuffle(&mut rng);
+        candidates.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        candidates.truncate(SPAWN_CHOICES);
+
+        candidates
+            .choose(&mut rng)
+            .map(|(cell, _)| *cell)
+            .or_else(|| self.spawns.first().map(|spawn| spawn.position))
+            .unwrap_or_default()
     }
-    fn bounds(&self) -> (f32, f32) {
-        let length = (self.tiles.len() as f32).sqrt() * WORLD_TILE_SIZE;
-        (length, length)
+
+    /// distance to the nearest threat up to `SPAWN_SAFE_DISTANCE`, halved when in its sight
+    fn spawn_score(&self, cell: (i32, i32), threats: &[(f32, f32)]) -> f32 {
+        let rect = TileGrid::cell_rect(cell);
+        let center = (rect.x + rect.width / 2.0, rect.y + rect.height / 2.0);
+
+        threats
+            .iter()
+            .map(|&threat| {
+                let (dx, dy) = (threat.0 - center.0, threat.1 - center.1);
+                let distance = (dx * dx + dy * dy).sqrt().min(SPAWN_SAFE_DISTANCE);
+                if self.tiles.line_of_sight(threat, center) {
+                    distance / 2.0
+                } else {
+                    distance
+                }
+            })
+            .fold(SPAWN_SAFE_DISTANCE, f32::min)
+    }
+
+    pub fn bounds(&self) -> (f32, f32) {
+        self.tiles.bounds()
     }
 
     fn in_of_bounds(&self, x: f32, y: f32, width: f32, height: f32) -> bool {
@@ -502,3 +1792,220 @@ impl Map {
         x > 0.0 && x <= bounds.0 - width && y > 0.0 && y < bounds.1 - height
     }
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerSettings {
+    pub port: u16,
+    pub map: String,
+    pub mode: GameMode,
+    pub teams: bool,
+    /// match length in seconds, 0 plays forever
+    #[serde(default)]
+    pub time_limit: u32,
+    /// maps played in turn, every map in `maps/` when empty, `random` generates a fresh arena
+    #[serde(default)]
+    pub maps: Vec<String>,
+    /// let players pick the next map instead of following the rotation
+    #[serde(default = "ServerSettings::default_map_vote")]
+    pub map_vote: bool,
+    #[serde(default = "ServerSettings::default_rcon_port")]
+    pub rcon_port: u16,
+    /// rcon stays off while this is empty
+    #[serde(default)]
+    pub rcon_password: String,
+}
+
+impl ServerSettings {
+    fn default_map_vote() -> bool {
+        true
+    }
+
+    fn default_rcon_port() -> u16 {
+        6970
+    }
+
+    /// rules bound to sockets when the server starts, changing them live would do nothing
+    const STARTUP_RULES: [&'static str; 3] = ["port", "rcon_port", "rcon_password"];
+
+    /// every rule as shown to admins, the rcon password is never echoed back
+    pub fn rules(&self) -> Result<serde_json::Value, String> {
+        let mut rules = serde_json::to_value(self).map_err(|err| err.to_string())?;
+        if let Some(password) = rules.get_mut("rcon_password") {
+            if password
+                .as_str()
+                .is_some_and(|password| !password.is_empty())
+            {
+                *password = serde_json::Value::String(String::from("<hidden>"));
+            }
+        }
+
+        Ok(rules)
+    }
+
+    /// changes a single rule from its textual value, e.g. `teams true`
+    pub fn set(&mut self, rule: &str, value: &str) -> Result<(), String> {
+        if Self::STARTUP_RULES.contains(&rule) {
+            return Err(format!(
+                "`{}` only applies on startup, change it in server.json",
+                rule
+            ));
+        }
+
+        let mut rules = serde_json::to_value(&*self).map_err(|err| err.to_string())?;
+        let slot = rules
+            .get_mut(rule)
+            .ok_or_else(|| format!("unknown rule `{}`", rule))?;
+
+        // bare words such as `KING_OF_THE_HILL` are taken as strings
+        *slot = serde_json::from_str(value)
+            .unwrap_or_else(|_| serde_json::Value::String(value.to_string()));
+        *self = serde_json::from_value(rules)
+            .map_err(|err| format!("invalid value for `{}`: {}", rule, err))?;
+
+        Ok(())
+    }
+
+    pub fn load(path: &PathBuf) -> Self {
+        let default_server_settings = ServerSettings {
+            port: 6969,
+            map: String::from("default.map"),
+            mode: GameMode::DEATHMATCH,
+            teams: false,
+            time_limit: 0,
+            maps: Vec::new(),
+            map_vote: Self::default_map_vote(),
+            rcon_port: Self::default_rcon_port(),
+            rcon_password: String::new(),
+        };
+
+        match File::open(path) {
+            Ok(mut file) => {
+                let mut buffer = String::new();
+                if let Ok(bytes) = file.read_to_string(&mut buffer) {
+                    match serde_json::from_str::<Self>(&buffer) {
+                        Ok(settings) => {
+                            log::info!("read {} bytes from server settings", bytes);
+                            return settings;
+                        }
+                        Err(err) => log::warn!("malformed server settings: {}", err),
+                    }
+                } else {
+                    log::warn!("failed to read server settings file");
+                }
+                default_server_settings
+            }
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => {
+                log::warn!("`server.json` not found, creating one...");
+
+                if let Ok(mut file) = File::create(path) {
+                    if let Err(err) =
+                        serde_json::to_writer_pretty(&mut file, &default_server_settings)
+                    {
+                        log::error!("failed to write default server settings: {}", err);
+                    }
+                } else {
+                    log::error!("failed to create `server.json` file in {:?}", path);
+                }
+
+                default_server_settings
+            }
+            Err(err) => {
+                log::error!("failed to open `server.json` file: {}", err);
+                default_server_settings
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(source: &str) -> Map {
+        Map::from_file(MapFile::parse("test", source).unwrap())
+    }
+
+    fn center(cell: (i32, i32)) -> (f32, f32) {
+        let rect = TileGrid::cell_rect(cell);
+        (rect.x + rect.width / 2.0, rect.y + rect.height / 2.0)
+    }
+
+    #[test]
+    fn stages_damage_on_destructible_tiles_only() {
+        let mut map = map("piw-pew map 2\n[grid]\n.BX\n");
+        map.damage_tile((0, 0), 50);
+        assert_eq!(map.tiles.get((0, 0)), Some(TileVariant::GROUND));
+        assert!(map.take_delta().is_empty());
+
+        // 120 health, a stage every 40
+        map.damage_tile((1, 0), 39);
+        assert!(map.take_delta().is_empty());
+        map.damage_tile((1, 0), 1);
+        assert_eq!(
+            map.take_delta().changes,
+            vec![TileChange {
+                position: (1, 0),
+                tile: TileVariant::BRICK,
+                damage: 1,
+            }]
+        );
+        assert_eq!(map.damage_stage((1, 0), TileVariant::BRICK), 1);
+    }
+
+    #[test]
+    fn restores_broken_and_damaged_tiles() {
+        let mut map = map("piw-pew map 2\n[grid]\n.BX\n");
+        map.damage_tile((1, 0), 50);
+        map.damage_tile((2, 0), 255);
+        assert_eq!(map.tiles.get((2, 0)), Some(TileVariant::GROUND));
+
+        let changes = map.changes();
+        assert_eq!(changes.changes.len(), 2);
+        assert!(changes.changes.contains(&TileChange {
+            position: (1, 0),
+            tile: TileVariant::BRICK,
+            damage: 1,
+        }));
+
+        let restored = map.restore();
+        assert_eq!(
+            restored.changes,
+            vec![
+                TileChange {
+                    position: (1, 0),
+                    tile: TileVariant::BRICK,
+                    damage: 0,
+                },
+                TileChange {
+                    position: (2, 0),
+                    tile: TileVariant::CRATE,
+                    damage: 0,
+                },
+            ]
+        );
+        assert_eq!(map.tiles, map.original);
+        assert!(map.changes().is_empty());
+        assert!(map.take_delta().is_empty());
+
+        // a restored tile takes its full health again
+        map.damage_tile((1, 0), 39);
+        assert_eq!(map.damage_stage((1, 0), TileVariant::BRICK), 0);
+    }
+
+    #[test]
+    fn prefers_spawns_away_from_enemies() {
+        let source = format!(
+            "piw-pew map 2\n[spawns]\n0,0\n1,0\n2,0\n3,0\n20,0\n[grid]\n{}\n",
+            ".".repeat(21)
+        );
+        let map = map(&source);
+        let threats = [center((0, 0))];
+
+        assert!(map.spawn_score((20, 0), &threats) > map.spawn_score((3, 0), &threats));
+        // one of the best few is picked, never the spawns right next to the enemy
+        for _ in 0..32 {
+            let spawn = map.spawn_position(None, &threats, &[]);
+            assert!(spawn.0 >= 2, "spawned at {:?}", spawn);
+        }
+    }
+}
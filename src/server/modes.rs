@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+
+use lib::prelude::*;
+use lib::types::*;
+use renet::{DefaultChannel, RenetServer};
+
+use crate::{Client, Map, ServerState};
+
+/// ticks between two zone state broadcasts
+static ZONE_BROADCAST_INTERVAL: u32 = 6;
+
+pub enum ServerMode {
+    Deathmatch,
+    KingOfTheHill(KingOfTheHill),
+    LastManStanding(LastManStanding),
+}
+
+impl ServerMode {
+    pub fn new(mode: GameMode, map: &Map) -> Self {
+        match mode {
+            GameMode::DEATHMATCH => Self::Deathmatch,
+            GameMode::KING_OF_THE_HILL => Self::KingOfTheHill(KingOfTheHill::new(map)),
+            GameMode::LAST_MAN_STANDING => Self::LastManStanding(LastManStanding::new(map)),
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        server: &mut RenetServer,
+        state: &mut ServerState,
+        map: &Map,
+        dt: Duration,
+    ) {
+        match self {
+            Self::Deathmatch => {}
+            Self::KingOfTheHill(koth) => koth.update(server, state, dt),
+            Self::LastManStanding(lms) => lms.update(server, state, map),
+        }
+    }
+
+    /// whether players who died are put back into the game right away
+    pub fn respawns(&self) -> bool {
+        match self {
+            Self::LastManStanding(lms) => lms.phase == RoundPhase::Waiting,
+            _ => true,
+        }
+    }
+
+    pub fn on_player_joined(&mut self, player: &mut Client) {
+        // latecomers spectate until the next round
+        if !self.respawns() {
+            player.dead = true;
+            player.data.health = 0;
+        }
+    }
+
+    pub fn on_player_left(&mut self, id: RawClientId) {
+        if let Self::KingOfTheHill(koth) = self {
+            koth.remove_player(id);
+        }
+    }
+}
+
+pub struct KingOfTheHill {
+    zones: Vec<ZoneData>,
+    points: HashMap<ZoneOwner, f32>,
+    ticks: u32,
+}
+
+impl KingOfTheHill {
+    pub fn new(map: &Map) -> Self {
+        if map.zones.is_empty() {
+            log::warn!("king of the hill is running on a map without capture zones");
+        }
+
+        Self {
+            zones: map
+                .zones
+                .iter()
+                .enumerate()
+                .map(|(id, area)| ZoneData::new(id as u8, *area))
+                .collect(),
+            points: HashMap::new(),
+            ticks: 0,
+        }
+    }
+
+    pub fn update(&mut self, server: &mut RenetServer, state: &ServerState, dt: Duration) {
+        let dt = dt.as_secs_f32();
+
+        for zone in &mut self.zones {
+            let mut occupants: Vec<ZoneOwner> = Vec::new();
+            for player in state.players.values().filter(|player| !player.dead) {
+                let (x, y) = player.data.position;
+                let center = (x + ENTITY_PLAYER_SIZE / 2.0, y + ENTITY_PLAYER_SIZE / 2.0);
+                let occupant = ZoneOwner::of(player.id.raw(), player.data.team);
+
+                if zone.contains(center) && !occupants.contains(&occupant) {
+                    occupants.push(occupant);
+                }
+            }
+
+            // an interrupted capture starts over from zero
+            zone.status = match occupants.as_slice() {
+                [] => ZoneStatus::IDLE,
+                [occupant] if zone.owner == Some(*occupant) => {
+                    *self.points.entry(*occupant).or_insert(0.0) += ZONE_POINTS_PER_SECOND * dt;
+                    ZoneStatus::SCORING
+                }
+                [occupant] => {
+                    if zone.status != ZoneStatus::CAPTURING(*occupant) {
+                        zone.progress = 0.0;
+                    }
+
+                    zone.progress += dt / ZONE_CAPTURE_TIME;
+                    if zone.progress >= 1.0 {
+                        log::info!("zone {} captured by {:?}", zone.id, occupant);
+                        zone.owner = Some(*occupant);
+                        zone.progress = 0.0;
+                        ZoneStatus::SCORING
+                    } else {
+                        ZoneStatus::CAPTURING(*occupant)
+                    }
+                }
+                _ => ZoneStatus::CONTESTED,
+            };
+        }
+
+        self.ticks += 1;
+        if self.ticks >= ZONE_BROADCAST_INTERVAL {
+            self.ticks = 0;
+            server.broadcast_message(
+                DefaultChannel::Unreliable,
+                GameNetworkPacket::NET_ZONE_STATE(self.zones.clone(), self.scores())
+                    .serialized()
+                    .unwrap(),
+            );
+        }
+    }
+
+    pub fn scores(&self) -> HashMap<ZoneOwner, u32> {
+        self.points
+            .iter()
+            .map(|(owner, points)| (*owner, *points as u32))
+            .collect()
+    }
+
+    pub fn remove_player(&mut self, id: RawClientId) {
+        let owner = ZoneOwner::PLAYER(id);
+        self.points.remove(&owner);
+
+        for zone in &mut self.zones {
+            if zone.owner == Some(owner) {
+                zone.owner = None;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RoundPhase {
+    /// not enough players for a round, everyone respawns freely
+    Waiting,
+    Active(Instant),
+    Intermission(Instant),
+}
+
+pub struct LastManStanding {
+    phase: RoundPhase,
+    round: u32,
+    center: (f32, f32),
+    radius: f32,
+    initial_radius: f32,
+    last_damage: Instant,
+    ticks: u32,
+}
+
+impl LastManStanding {
+    pub fn new(map: &Map) -> Self {
+        let (width, height) = map.bounds();
+        let initial_radius = (width * width + height * height).sqrt() / 2.0;
+
+        Self {
+            phase: RoundPhase::Waiting,
+            round: 0,
+            center: (width / 2.0, height / 2.0),
+            radius: initial_radius,
+            initial_radius,
+            last_damage: Instant::now(),
+            ticks: 0,
+        }
+    }
+
+    pub fn update(&mut self, server: &mut RenetServer, state: &mut ServerState, map: &Map) {
+        match self.phase {
+            RoundPhase::Waiting => {
+                if state.players.len() >= 2 {
+                    self.start_round(server, state, map);
+                }
+            }
+            RoundPhase::Active(started) => {
+                let elapsed = started.elapsed().as_secs_f32() - SAFE_ZONE_GRACE_TIME;
+                let t = (elapsed / SAFE_ZONE_SHRINK_TIME).clamp(0.0, 1.0);
+                self.radius =
+                    self.initial_radius + (SAFE_ZONE_MIN_RADIUS - self.initial_radius) * t;
+
+                if self.last_damage.elapsed() >= Duration::from_secs(1) {
+                    self.last_damage = Instant::now();
+                    self.damage_outside(server, state);
+                }
+
+                self.ticks += 1;
+                if self.ticks >= ZONE_BROADCAST_INTERVAL {
+                    self.ticks = 0;
+                    server.broadcast_message(
+                        DefaultChannel::Unreliable,
+                        GameNetworkPacket::NET_SAFE_ZONE(self.center, self.radius)
+                            .serialized()
+                            .unwrap(),
+                    );
+                }
+
+                let alive = state
+                    .players
+                    .values()
+                    .filter(|player| !player.dead)
+                    .collect::<Vec<_>>();
+
+                if alive.len() <= 1 {
+                    let winner = alive
+                        .first()
+                        .map(|player| (player.id.raw(), player.name.clone()));
+
+                    match &winner {
+                        Some((_, name)) => log::info!("round {} won by {}", self.round, name),
+                        None => log::info!("round {} ended without survivors", self.round),
+                    }
+
+                    server.broadcast_message(
+                        DefaultChannel::ReliableOrdered,
+                        GameNetworkPacket::NET_ROUND_END(winner)
+                            .serialized()
+                            .unwrap(),
+                    );
+                    self.phase = RoundPhase::Intermission(Instant::now());
+                }
+            }
+            RoundPhase::Intermission(since) => {
+                if since.elapsed().as_secs_f32() < ROUND_INTERMISSION_TIME {
+                    return;
+                }
+
+                if state.players.len() >= 2 {
+                    self.start_round(server, state, map);
+                } else {
+                    self.phase = RoundPhase::Waiting;
+                    let dead = state
+                        .players
+                        .values()
+                        .filter(|player| player.dead)
+                        .map(|player| player.id)
+                        .collect::<Vec<_>>();
+                    state.respawn(server, map, &dead);
+                }
+            }
+        }
+    }
+
+    fn start_round(&mut self, server: &mut RenetServer, state: &mut ServerState, map: &Map) {
+        self.round += 1;
+        self.radius = self.initial_radius;
+        self.phase = RoundPhase::Active(Instant::now());
+        log::info!("round {} started", self.round);
+
+        let mut ids = state.players.keys().copied().collect::<Vec<_>>();
+        ids.sort_by_key(|id| id.raw());
+        state.respawn(server, map, &ids);
+
+        server.broadcast_message(
+            DefaultChannel::ReliableOrdered,
+            GameNetworkPacket::NET_ROUND_START(self.round)
+                .serialized()
+                .unwrap(),
+        );
+    }
+
+    fn damage_outside(&self, server: &mut RenetServer, state: &mut ServerState) {
+        for player in state.players.values_mut().filter(|player| !player.dead) {
+            let (x, y) = player.data.position;
+            let (dx, dy) = (
+                x + ENTITY_PLAYER_SIZE / 2.0 - self.center.0,
+                y + ENTITY_PLAYER_SIZE / 2.0 - self.center.1,
+            );
+
+            if (dx * dx + dy * dy).sqrt() > self.radius {
+                let damage = player.hurt(SAFE_ZONE_DAMAGE, None);
+                if damage == 0 {
+                    continue;
+                }
+
+                server.broadcast_message(
+                    DefaultChannel::ReliableUnordered,
+                    GameNetworkPacket::NET_PLAYER_DAMAGE(player.id.raw(), damage)
+                        .serialized()
+                        .unwrap(),
+                );
+            }
+        }
+    }
+}
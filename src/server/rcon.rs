@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+
+use lib::prelude::*;
+use rand::prelude::*;
+
+/// how long a handed out nonce stays valid
+static RCON_CHALLENGE_TIMEOUT: Duration = Duration::from_secs(10);
+/// failed attempts an address gets before being ignored for a while
+static RCON_MAX_FAILURES: u32 = 5;
+static RCON_LOCKOUT: Duration = Duration::from_secs(60);
+
+/// remote console listening on its own udp port
+pub struct Rcon {
+    socket: UdpSocket,
+    password: String,
+    /// outstanding nonce per peer, consumed by the first command using it
+    challenges: HashMap<SocketAddr, (u64, Instant)>,
+    failures: HashMap<IpAddr, (u32, Instant)>,
+}
+
+impl Rcon {
+    pub fn bind(port: u16, password: String) -> io::Result<Self> {
+        let socket = UdpSocket::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port))?;
+        socket.set_nonblocking(true)?;
+
+        Ok(Self {
+            socket,
+            password,
+            challenges: HashMap::new(),
+            failures: HashMap::new(),
+        })
+    }
+
+    /// drains pending datagrams and returns the authenticated command lines
+    pub fn poll(&mut self) -> Vec<(SocketAddr, String)> {
+        let mut commands = Vec::new();
+        let mut buffer = [0; RCON_MAX_PACKET_SIZE];
+
+        self.challenges
+            .retain(|_, (_, issued)| issued.elapsed() < RCON_CHALLENGE_TIMEOUT);
+        self.failures
+            .retain(|_, (_, last)| last.elapsed() < RCON_LOCKOUT);
+
+        loop {
+            let (size, addr) = match self.socket.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    log::error!("rcon socket error: {}", err);
+                    break;
+                }
+            };
+
+            if self.is_locked_out(addr.ip()) {
+                continue;
+            }
+
+            match rmp_serde::from_slice::<RconPacket>(&buffer[..size]) {
+                Ok(RconPacket::RCON_CHALLENGE_REQUEST) => {
+                    let nonce = thread_rng().gen::<u64>();
+                    self.challenges.insert(addr, (nonce, Instant::now()));
+                    self.send(addr, RconPacket::RCON_CHALLENGE(nonce));
+                }
+                Ok(RconPacket::RCON_COMMAND(nonce, command, signature)) => {
+                    let valid = self.challenges.remove(&addr).is_some_and(|(issued, _)| {
+                        issued == nonce && rcon_verify(&self.password, nonce, &command, &signature)
+                    });
+
+                    if valid {
+                        self.failures.remove(&addr.ip());
+                        commands.push((addr, command));
+                    } else {
+                        log::warn!("rcon authentication failed from {}", addr);
+                        let failures = self
+                            .failures
+                            .entry(addr.ip())
+                            .or_insert((0, Instant::now()));
+                        *failures = (failures.0 + 1, Instant::now());
+                        self.reply(addr, Err(String::from("authentication failed")));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        commands
+    }
+
+    pub fn reply(&self, addr: SocketAddr, result: Result<String, String>) {
+        // keep the answer within a single datagram
+        let result = result.map(truncated).map_err(truncated);
+
+        self.send(addr, RconPacket::RCON_RESPONSE(result));
+    }
+
+    fn send(&self, addr: SocketAddr, packet: RconPacket) {
+        if let Err(err) = self.socket.send_to(&packet.serialized().unwrap(), addr) {
+            log::warn!("failed to answer rcon peer {}: {}", addr, err);
+        }
+    }
+
+    fn is_locked_out(&self, ip: IpAddr) -> bool {
+        self.failures
+            .get(&ip)
+            .is_some_and(|(count, _)| *count >= RCON_MAX_FAILURES)
+    }
+}
+
+fn truncated(mut text: String) -> String {
+    let limit = RCON_MAX_PACKET_SIZE - 64;
+    if text.len() > limit {
+        let mut end = limit;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        text.truncate(end);
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// an rcon server on a free local port and a peer socket talking to it
+    fn connected() -> (Rcon, UdpSocket) {
+        let rcon = Rcon::bind(0, String::from("hunter2")).unwrap();
+        let port = rcon.socket.local_addr().unwrap().port();
+        let peer = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        peer.connect((Ipv4Addr::LOCALHOST, port)).unwrap();
+        peer.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+
+        (rcon, peer)
+    }
+
+    fn send(peer: &UdpSocket, packet: RconPacket) {
+        peer.send(&packet.serialized().unwrap()).unwrap();
+    }
+
+    fn receive(peer: &UdpSocket) -> RconPacket {
+        let mut buffer = [0; RCON_MAX_PACKET_SIZE];
+        let size = peer.recv(&mut buffer).unwrap();
+        rmp_serde::from_slice(&buffer[..size]).unwrap()
+    }
+
+    /// polls once the datagrams sent over loopback have had time to arrive
+    fn poll(rcon: &mut Rcon) -> Vec<String> {
+        std::thread::sleep(Duration::from_millis(50));
+        rcon.poll()
+            .into_iter()
+            .map(|(_, command)| command)
+            .collect()
+    }
+
+    fn challenge(rcon: &mut Rcon, peer: &UdpSocket) -> u64 {
+        send(peer, RconPacket::RCON_CHALLENGE_REQUEST);
+        poll(rcon);
+        match receive(peer) {
+            RconPacket::RCON_CHALLENGE(nonce) => nonce,
+            packet => panic!("expected a challenge, got {:?}", packet),
+        }
+    }
+
+    #[test]
+    fn accepts_commands_signed_over_the_nonce() {
+        let (mut rcon, peer) = connected();
+        let nonce = challenge(&mut rcon, &peer);
+
+        let signature = rcon_sign("hunter2", nonce, "status");
+        send(
+            &peer,
+            RconPacket::RCON_COMMAND(nonce, String::from("status"), signature),
+        );
+        assert_eq!(poll(&mut rcon), vec!["status"]);
+    }
+
+    #[test]
+    fn rejects_replayed_commands() {
+        let (mut rcon, peer) = connected();
+        let nonce = challenge(&mut rcon, &peer);
+
+        let signature = rcon_sign("hunter2", nonce, "status");
+        let command = RconPacket::RCON_COMMAND(nonce, String::from("status"), signature);
+        send(&peer, command.clone());
+        assert_eq!(poll(&mut rcon).len(), 1);
+
+        // the nonce was used up by the first command
+        send(&peer, command);
+        assert!(poll(&mut rcon).is_empty());
+        assert_eq!(
+            receive(&peer),
+            RconPacket::RCON_RESPONSE(Err(String::from("authentication failed")))
+        );
+    }
+
+    #[test]
+    fn rejects_commands_signed_with_another_password() {
+        let (mut rcon, peer) = connected();
+        let nonce = challenge(&mut rcon, &peer);
+
+        let signature = rcon_sign("hunter3", nonce, "status");
+        send(
+            &peer,
+            RconPacket::RCON_COMMAND(nonce, String::from("status"), signature),
+        );
+        assert!(poll(&mut rcon).is_empty());
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use std::env::current_dir;
+use std::fs;
+
+use lib::prelude::*;
+use lib::types::*;
+use rand::prelude::*;
+
+/// every `.map` file in the `maps/` directory, sorted by name
+pub fn available_maps() -> Vec<String> {
+    let mut maps = fs::read_dir(current_dir().unwrap().join("maps"))
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .filter(|name| name.ends_with(".map"))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    maps.sort();
+    maps
+}
+
+/// maps offered once the current one is over, the next one in rotation always comes first
+pub fn candidates(rotation: &[String], current: &str) -> Vec<String> {
+    let pool = if rotation.is_empty() {
+        available_maps()
+    } else {
+        rotation.to_vec()
+    };
+
+    let next = pool
+        .iter()
+        .position(|map| map == current)
+        .map(|index| pool[(index + 1) % pool.len()].clone())
+        .or_else(|| pool.first().cloned())
+        .unwrap_or(current.to_string());
+
+    let mut others = pool
+        .into_iter()
+        .filter(|map| *map != next && map != current)
+        .collect::<Vec<_>>();
+    others.sort();
+    others.dedup();
+    others.shuffle(&mut thread_rng());
+
+    std::iter::once(next)
+        .chain(others)
+        .take(MAP_VOTE_CANDIDATES)
+        .collect()
+}
+
+pub struct MapVote {
+    pub candidates: Vec<String>,
+    votes: HashMap<RawClientId, usize>,
+    started: Instant,
+}
+
+impl MapVote {
+    pub fn new(candidates: Vec<String>) -> Self {
+        Self {
+            candidates,
+            votes: HashMap::new(),
+            started: Instant::now(),
+        }
+    }
+
+    /// records a vote, a player may change their mind until the vote closes
+    pub fn cast(&mut self, id: RawClientId, choice: usize) -> bool {
+        if choice >= self.candidates.len() || self.votes.get(&id) == Some(&choice) {
+            return false;
+        }
+
+        self.votes.insert(id, choice);
+        true
+    }
+
+    pub fn remove(&mut self, id: RawClientId) -> bool {
+        self.votes.remove(&id).is_some()
+    }
+
+    pub fn tally(&self) -> Vec<u32> {
+        let mut tally = vec![0; self.candidates.len()];
+        for choice in self.votes.values() {
+            tally[*choice] += 1;
+        }
+
+        tally
+    }
+
+    pub fn remaining(&self) -> f32 {
+        (MAP_VOTE_TIME - self.started.elapsed().as_secs_f32()).max(0.0)
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.remaining() <= 0.0
+    }
+
+    /// most voted map, ties go to the one listed first
+    pub fn winner(&self) -> String {
+        let tally = self.tally();
+        let (index, _) = tally
+            .iter()
+            .enumerate()
+            .fold((0, 0), |best, (index, votes)| {
+                if *votes > best.1 {
+                    (index, *votes)
+                } else {
+                    best
+                }
+            });
+
+        self.candidates[index].clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vote() -> MapVote {
+        MapVote::new(vec![
+            String::from("next.map"),
+            String::from("b.map"),
+            String::from("c.map"),
+        ])
+    }
+
+    #[test]
+    fn offers_the_next_map_in_rotation_first() {
+        let rotation = ["a.map", "b.map", "c.map", "d.map"].map(String::from);
+        let offered = candidates(&rotation, "b.map");
+
+        assert_eq!(offered[0], "c.map");
+        assert_eq!(offered.len(), MAP_VOTE_CANDIDATES);
+        assert!(!offered.contains(&String::from("b.map")));
+        // wraps around at the end of the rotation
+        assert_eq!(candidates(&rotation, "d.map")[0], "a.map");
+    }
+
+    #[test]
+    fn falls_back_to_the_next_map_without_votes() {
+        assert_eq!(vote().winner(), "next.map");
+    }
+
+    #[test]
+    fn ties_go_to_the_map_listed_first() {
+        let mut vote = vote();
+        vote.cast(1, 2);
+        vote.cast(2, 1);
+        assert_eq!(vote.tally(), vec![0, 1, 1]);
+        assert_eq!(vote.winner(), "b.map");
+
+        vote.cast(3, 2);
+        assert_eq!(vote.winner(), "c.map");
+    }
+
+    #[test]
+    fn ignores_votes_for_maps_that_are_not_candidates() {
+        let mut vote = vote();
+        assert!(!vote.cast(1, 3));
+        assert!(!vote.cast(1, usize::MAX));
+        assert_eq!(vote.tally(), vec![0, 0, 0]);
+        assert_eq!(vote.winner(), "next.map");
+    }
+
+    #[test]
+    fn counts_only_the_latest_vote_of_a_player() {
+        let mut vote = vote();
+        assert!(vote.cast(1, 1));
+        assert!(!vote.cast(1, 1));
+        assert!(vote.cast(1, 2));
+        assert_eq!(vote.tally(), vec![0, 0, 1]);
+
+        assert!(vote.remove(1));
+        assert_eq!(vote.tally(), vec![0, 0, 0]);
+    }
+}